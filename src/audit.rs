@@ -0,0 +1,145 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use color_eyre::eyre::{Result, WrapErr};
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Records listed when query sets no limit.
+pub const LIMIT: usize = 100;
+
+/// Append-only log of messages, reactions and receipts sent through API, one JSON record per
+/// line, for review of who reached whom.
+pub struct Audit {
+    path: PathBuf,
+
+    /// Log opened for appending, locked for records not to interleave.
+    file: Mutex<tokio::fs::File>,
+}
+
+/// Call made to API, with who made it and how it ended.
+#[derive(Object, Serialize, Deserialize)]
+pub struct Record {
+    /// Milliseconds since Unix epoch call was made at.
+    pub timestamp: u64,
+
+    /// Name of API token call was made with, `None` if API is open to anyone.
+    pub caller: Option<String>,
+
+    pub action: Action,
+
+    /// Account call was made as, when daemon serves several.
+    pub account: Option<String>,
+
+    /// Number, identifier, or `group.ID` of group, reached by call.
+    pub recipient: String,
+
+    /// Status code call was answered with.
+    pub status: u16,
+
+    /// Reason call failed, if it did.
+    pub error: Option<String>,
+}
+
+/// Kind of call recorded.
+#[derive(Enum, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[oai(rename_all(lowercase))]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Send,
+    React,
+    Receipt,
+}
+
+/// Conditions records must meet to be listed, each left out being met by any.
+pub struct Filter {
+    pub caller: Option<String>,
+    pub recipient: Option<String>,
+    pub action: Option<Action>,
+
+    /// Milliseconds since Unix epoch records must be made at or after.
+    pub since: Option<u64>,
+
+    /// Milliseconds since Unix epoch records must be made before.
+    pub until: Option<u64>,
+
+    /// Most records listed, latest ones being kept.
+    pub limit: usize,
+}
+
+impl Audit {
+    /// Open log at `path`, creating it if needed and keeping records already in it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if file cannot be opened for appending.
+    pub fn open(path: &Path) -> Result<Arc<Self>> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+
+        Ok(Arc::new(Self {
+            path: path.to_owned(),
+            file: Mutex::new(tokio::fs::File::from_std(file)),
+        }))
+    }
+
+    /// Append record to log, logging failures rather than failing call it records.
+    pub async fn record(&self, record: &Record) {
+        let Ok(mut line) = serde_json::to_vec(record) else {
+            return;
+        };
+
+        line.push(b'\n');
+
+        let mut file = self.file.lock().await;
+
+        // Flush right away, for records to be on disk even if process is killed
+        let written = match file.write_all(&line).await {
+            Ok(()) => file.flush().await,
+            Err(error) => Err(error),
+        };
+
+        drop(file);
+
+        if let Err(error) = written {
+            tracing::error!("Failed to write audit record: {error}");
+        }
+    }
+
+    /// Records matching filter, oldest first.
+    pub async fn query(&self, filter: &Filter) -> std::io::Result<Vec<Record>> {
+        let text = tokio::fs::read_to_string(&self.path).await?;
+
+        // Skip lines not parsing, like one being written while reading
+        let records = text
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Record>(line).ok())
+            .filter(|r| filter.matches(r));
+
+        let mut records: Vec<_> = records.collect();
+
+        records.drain(..records.len().saturating_sub(filter.limit));
+
+        Ok(records)
+    }
+}
+
+impl Filter {
+    fn matches(&self, record: &Record) -> bool {
+        self.caller
+            .as_ref()
+            .is_none_or(|c| record.caller.as_ref() == Some(c))
+            && self
+                .recipient
+                .as_ref()
+                .is_none_or(|r| &record.recipient == r)
+            && self.action.is_none_or(|a| record.action == a)
+            && self.since.is_none_or(|s| record.timestamp >= s)
+            && self.until.is_none_or(|u| record.timestamp < u)
+    }
+}
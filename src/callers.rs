@@ -0,0 +1,425 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use color_eyre::eyre::{Result, bail};
+use poem_openapi::{Enum, Object};
+
+use super::clients::Client;
+use super::numbers;
+use super::outbox::now;
+
+/// Paths served to anyone, for probes and documentation to work without a token.
+const PUBLIC: [&str; 2] = ["/ready", "/docs"];
+
+/// Paths restricted tokens may call, those endpoints checking recipients against restrictions;
+/// other ones read or change resources of accounts regardless of recipients.
+const SCOPED: [&str; 12] = [
+    "/send",
+    "/broadcast",
+    "/react",
+    "/receive",
+    "/typing",
+    "/attachments",
+    "/v2/send",
+    "/v1/reactions",
+    "/v1/receipts",
+    "/v1/typing-indicator",
+    "/v1/about",
+    "/v1/health",
+];
+
+/// Prefix of administration endpoints, reserved to admin token or unrestricted API tokens.
+const ADMIN: &str = "/admin";
+
+/// Integrations allowed to call API, by token they authenticate with.
+pub struct Callers {
+    callers: Vec<(String, Arc<Caller>)>,
+
+    /// Token administration endpoints are reserved to, unrestricted API tokens if absent.
+    admin: Option<String>,
+}
+
+/// Integration calling API, with quotas of messages it can send and recipients it can reach.
+#[derive(Default)]
+pub struct Caller {
+    /// Name of token caller authenticated with, `None` if API is open to anyone.
+    pub name: Option<String>,
+
+    quotas: Mutex<Vec<Quota>>,
+
+    /// Numbers, identifiers and `group.ID` keys of groups caller can reach, `None` for any.
+    allowed: Option<HashSet<String>>,
+}
+
+/// Most messages sent over each window of a period, with those sent over current window.
+struct Quota {
+    period: Period,
+    limit: u64,
+
+    /// Index of window, counted in periods since Unix epoch.
+    window: u64,
+    used: u64,
+}
+
+/// Period quotas are counted over, windows of which start on the hour or at midnight UTC.
+#[derive(Enum, Clone, Copy)]
+#[oai(rename_all(lowercase))]
+pub enum Period {
+    Hour,
+    Day,
+}
+
+/// Usage of a quota of a caller, over current window.
+#[derive(Object)]
+pub struct Usage {
+    caller: String,
+    period: Period,
+    limit: u64,
+    used: u64,
+
+    /// Milliseconds since Unix epoch usage is reset at.
+    resets_at: u64,
+}
+
+impl Callers {
+    /// Callers of `(name, token)` pairs, with quotas of `(name, (limit, period))` ones and
+    /// recipients of `(name, recipient)` ones, numbers of which are in country of `country_code`
+    /// unless international.
+    ///
+    /// # Errors
+    ///
+    /// Fails on quotas and recipients of names without a token, and on invalid numbers.
+    pub fn new(
+        admin: Option<String>,
+        tokens: &[(String, String)],
+        quotas: &[(String, (u64, Period))],
+        recipients: &[(String, String)],
+        country_code: Option<&str>,
+    ) -> Result<Self> {
+        let unknown = |name: &String| tokens.iter().all(|(t, _)| t != name);
+
+        if let Some((name, _)) = quotas.iter().find(|(n, _)| unknown(n)) {
+            bail!("Quota of `{name}` has no token, set one with `--api-token`");
+        }
+
+        if let Some((name, _)) = recipients.iter().find(|(n, _)| unknown(n)) {
+            bail!("Recipient of `{name}` has no token, set one with `--api-token`");
+        }
+
+        // Bring numbers to the form recipients of requests are matched in
+        let mut keys = Vec::new();
+
+        for (name, recipient) in recipients {
+            let key = if recipient.starts_with("group.") {
+                recipient.clone()
+            } else {
+                match numbers::e164(recipient, country_code) {
+                    Ok(Some(number)) => number,
+                    Ok(None) => recipient.clone(),
+                    Err(error) => bail!("Invalid number `{recipient}` of `{name}`: {error}"),
+                }
+            };
+
+            keys.push((name, key));
+        }
+
+        let callers = tokens.iter().map(|(name, token)| {
+            let quotas = quotas
+                .iter()
+                .filter(|(n, _)| n == name)
+                .map(|(_, (limit, period))| Quota {
+                    period: *period,
+                    limit: *limit,
+                    window: 0,
+                    used: 0,
+                });
+
+            let allowed: HashSet<_> = keys
+                .iter()
+                .filter(|(n, _)| *n == name)
+                .map(|(_, key)| key.clone())
+                .collect();
+
+            let caller = Caller {
+                name: Some(name.clone()),
+                quotas: Mutex::new(quotas.collect()),
+                allowed: Some(allowed).filter(|a| !a.is_empty()),
+            };
+
+            (token.clone(), Arc::new(caller))
+        });
+
+        Ok(Self {
+            callers: callers.collect(),
+            admin,
+        })
+    }
+
+    /// Caller token is that of, anonymous one if API is open to anyone, `None` if it is unknown.
+    fn authenticate(&self, token: Option<&str>) -> Option<Arc<Caller>> {
+        if self.callers.is_empty() {
+            return Some(Arc::default());
+        }
+
+        let token = token?;
+
+        // Compare with every token in full, for timing not to tell how close a guess is
+        let mut found = None;
+
+        for (known, caller) in &self.callers {
+            if same(known.as_bytes(), token.as_bytes()) {
+                found = Some(Arc::clone(caller));
+            }
+        }
+
+        found
+    }
+
+    /// Caller token is that of if it may call administration endpoints: admin token if one is
+    /// set, API token without quotas nor recipients otherwise, never anyone when API is open.
+    fn administrator(&self, token: Option<&str>) -> Option<Arc<Caller>> {
+        let token = token?;
+
+        if let Some(admin) = &self.admin {
+            let caller = Caller {
+                name: Some(String::from("admin")),
+                ..Caller::default()
+            };
+
+            return same(admin.as_bytes(), token.as_bytes()).then(|| Arc::new(caller));
+        }
+
+        let caller = self.authenticate(Some(token))?;
+
+        (caller.name.is_some() && caller.is_unrestricted()).then_some(caller)
+    }
+
+    /// Usage of quotas of every caller, by name of caller.
+    pub fn usage(&self) -> Vec<Usage> {
+        let now = now();
+
+        let mut usage: Vec<_> = self
+            .callers
+            .iter()
+            .flat_map(|(_, c)| c.usage(now))
+            .collect();
+
+        usage.sort_by(|a, b| a.caller.cmp(&b.caller));
+
+        usage
+    }
+}
+
+impl Caller {
+    /// Caller known by name only, neither limited by quotas nor recipients, for sends it was
+    /// checked for already.
+    pub fn named(name: Option<String>) -> Self {
+        Self {
+            name,
+            ..Self::default()
+        }
+    }
+
+    /// Count message against quotas, or tell how long until one would fit if any is used up.
+    pub fn spend(&self) -> Result<(), Duration> {
+        let now = now();
+
+        let mut quotas = self.quotas.lock().unwrap_or_else(PoisonError::into_inner);
+
+        for quota in quotas.iter_mut() {
+            let window = now / quota.period.millis();
+
+            if quota.window != window {
+                quota.window = window;
+                quota.used = 0;
+            }
+
+            if quota.used >= quota.limit {
+                let resets_at = (window + 1) * quota.period.millis();
+
+                return Err(Duration::from_millis(resets_at - now));
+            }
+        }
+
+        // Count message only once it fits every quota
+        for quota in quotas.iter_mut() {
+            quota.used += 1;
+        }
+
+        drop(quotas);
+
+        Ok(())
+    }
+
+    /// Whether caller has no quotas and can reach anyone.
+    fn is_unrestricted(&self) -> bool {
+        let quotas = self.quotas.lock().unwrap_or_else(PoisonError::into_inner);
+
+        self.allowed.is_none() && quotas.is_empty()
+    }
+
+    /// Whether caller can reach recipient, by number, identifier or `group.ID` key.
+    pub fn may_reach(&self, key: &str) -> bool {
+        self.allowed.as_ref().is_none_or(|a| a.contains(key))
+    }
+
+    fn usage(&self, now: u64) -> Vec<Usage> {
+        let quotas = self.quotas.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let usage = quotas.iter().map(|quota| {
+            let window = now / quota.period.millis();
+
+            // Quotas are only reset once used again, those of a past window are unused
+            let used = if quota.window == window {
+                quota.used
+            } else {
+                0
+            };
+
+            Usage {
+                caller: self.name.clone().unwrap_or_default(),
+                period: quota.period,
+                limit: quota.limit,
+                used,
+                resets_at: (window + 1) * quota.period.millis(),
+            }
+        });
+
+        let usage = usage.collect();
+
+        drop(quotas);
+
+        usage
+    }
+}
+
+impl Period {
+    const fn millis(self) -> u64 {
+        match self {
+            Self::Hour => 60 * 60 * 1000,
+            Self::Day => 24 * 60 * 60 * 1000,
+        }
+    }
+}
+
+/// Let requests through if their bearer token is known, for endpoints to tell who calls them.
+pub async fn authenticate<E: poem::Endpoint>(
+    next: E,
+    mut req: poem::Request,
+    callers: Arc<Callers>,
+) -> poem::Result<poem::Response> {
+    use poem::IntoResponse;
+    use poem::http::StatusCode;
+    use poem::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    let path = req.uri().path();
+
+    let public = PUBLIC.iter().any(|p| path.starts_with(p));
+
+    let scoped = SCOPED.iter().any(|p| {
+        path.strip_prefix(p)
+            .is_some_and(|r| r.is_empty() || r.starts_with('/'))
+    });
+
+    // Administration endpoints change where messages go, restricted tokens must not reach them
+    if path.starts_with(ADMIN) {
+        let Some(caller) = callers.administrator(token) else {
+            let resp = poem::Response::builder()
+                .status(StatusCode::FORBIDDEN)
+                .body(
+                    "Administration endpoints need `--admin-token`, or an unrestricted API token",
+                );
+
+            return Ok(resp);
+        };
+
+        req.extensions_mut().insert(caller);
+
+        return Ok(next.call(req).await?.into_response());
+    }
+
+    let caller = match callers.authenticate(token) {
+        Some(caller) => caller,
+        None if public => Arc::default(),
+        None => {
+            if let Some(Client(client)) = req.extensions().get() {
+                tracing::warn!("Turned away request from {client} without a known API token");
+            }
+
+            let resp = poem::Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header(WWW_AUTHENTICATE, "Bearer")
+                .body("Missing or unknown API token");
+
+            return Ok(resp);
+        }
+    };
+
+    // Deny by default what restrictions of caller would not be checked for
+    if !public && !scoped && !caller.is_unrestricted() {
+        let name = caller.name.as_deref().unwrap_or_default();
+
+        let resp = poem::Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(format!(
+                "API token `{name}` is restricted to sending to its recipients"
+            ));
+
+        return Ok(resp);
+    }
+
+    req.extensions_mut().insert(caller);
+
+    Ok(next.call(req).await?.into_response())
+}
+
+/// Whether byte strings are equal, taking as long whichever byte differs.
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Split `name=token` argument into name of caller and its token.
+pub fn parse_token(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((name, token)) if !name.is_empty() && !token.is_empty() => {
+            Ok((name.to_owned(), token.to_owned()))
+        }
+        _ => Err(String::from("expected `name=token`")),
+    }
+}
+
+/// Split `name=recipient` argument into name of caller and recipient it can reach.
+pub fn parse_recipient(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((name, recipient)) if !name.is_empty() && !recipient.is_empty() => {
+            Ok((name.to_owned(), recipient.to_owned()))
+        }
+        _ => Err(String::from("expected `name=number` or `name=group.ID`")),
+    }
+}
+
+/// Split `name=count/period` argument into name of caller and its quota.
+pub fn parse_quota(arg: &str) -> Result<(String, (u64, Period)), String> {
+    const EXPECTED: &str = "expected `name=count/hour` or `name=count/day`";
+
+    let Some((name, quota)) = arg.split_once('=') else {
+        return Err(String::from(EXPECTED));
+    };
+
+    let (limit, period) = match quota.split_once('/') {
+        Some((limit, "hour")) => (limit, Period::Hour),
+        Some((limit, "day")) => (limit, Period::Day),
+        _ => return Err(String::from(EXPECTED)),
+    };
+
+    let limit = limit.parse().map_err(|_| String::from(EXPECTED))?;
+
+    Ok((name.to_owned(), (limit, period)))
+}
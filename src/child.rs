@@ -0,0 +1,77 @@
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{Result, bail};
+use tokio::io::Join;
+use tokio::process::{ChildStdin, ChildStdout, Command};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Standard streams of a daemon speaking JSON-RPC over stdio.
+pub type Pipes = Join<ChildStdout, ChildStdin>;
+
+/// Delay before first restart, doubled on each consecutive crash.
+const BACKOFF_MIN: Duration = Duration::from_secs(1);
+
+/// Upper bound on delay between restarts.
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Run duration after which daemon is considered healthy, resetting backoff.
+const HEALTHY_AFTER: Duration = Duration::from_secs(60);
+
+/// Launch daemon from command line and restart it whenever it exits.
+///
+/// When `pipes` is provided, standard streams of each new process are sent over it,
+/// otherwise they are inherited and daemon is expected to listen on a socket.
+pub async fn supervise(command: String, pipes: Option<UnboundedSender<Pipes>>) {
+    let mut backoff = BACKOFF_MIN;
+
+    loop {
+        let started = Instant::now();
+
+        match run(&command, pipes.as_ref()).await {
+            Ok(status) => tracing::warn!("Daemon exited with {status}"),
+            Err(error) => tracing::warn!("Daemon failed to run: {error}"),
+        }
+
+        if started.elapsed() > HEALTHY_AFTER {
+            backoff = BACKOFF_MIN;
+        }
+
+        tracing::info!("Restarting daemon in {}s", backoff.as_secs());
+        tokio::time::sleep(backoff).await;
+
+        backoff = (backoff * 2).min(BACKOFF_MAX);
+    }
+}
+
+/// Spawn a single daemon process, wait for it to exit.
+async fn run(
+    command: &str,
+    pipes: Option<&UnboundedSender<Pipes>>,
+) -> Result<std::process::ExitStatus> {
+    let mut words = command.split_whitespace();
+
+    let Some(program) = words.next() else {
+        bail!("Empty daemon command");
+    };
+
+    let mut cmd = Command::new(program);
+    cmd.args(words).kill_on_drop(true);
+
+    if pipes.is_some() {
+        cmd.stdin(Stdio::piped()).stdout(Stdio::piped());
+    }
+
+    let mut child = cmd.spawn()?;
+
+    if let Some(pipes) = pipes {
+        let (Some(stdout), Some(stdin)) = (child.stdout.take(), child.stdin.take()) else {
+            bail!("Daemon standard streams are unavailable");
+        };
+
+        // Connection may have been given up on, keep process running regardless
+        let _ = pipes.send(tokio::io::join(stdout, stdin));
+    }
+
+    Ok(child.wait().await?)
+}
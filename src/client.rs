@@ -1,37 +1,239 @@
+#![expect(clippy::too_many_arguments)]
+
 use serde_json::Value;
 
+/// Requests of `signal-cli` JSON-RPC interface, implemented by clients as [`SignalClient`].
 #[jsonrpsee::proc_macros::rpc(client)]
-trait Signal {
+pub trait Signal {
     #[method(name = "sendReaction", param_kind = map)]
     fn react(
         &self,
+        account: Option<&str>,
         recipient: Option<&str>,
         groupId: Option<&str>,
         emoji: &str,
         targetAuthor: &str,
         targetTimestamp: u64,
+        remove: bool,
     ) -> Result<Value, ErrorObjectOwned>;
 
     #[method(name = "sendReceipt", param_kind = map)]
-    fn receive(&self, recipient: &str, targetTimestamp: u64) -> Result<Value, ErrorObjectOwned>;
+    fn receive(
+        &self,
+        account: Option<&str>,
+        recipient: &str,
+        targetTimestamp: u64,
+        #[argument(rename = "type")] kind: &str,
+    ) -> Result<Value, ErrorObjectOwned>;
 
     #[method(name = "send", param_kind = map)]
     fn send(
         &self,
+        account: Option<&str>,
         recipient: Option<&str>,
         groupId: Option<&str>,
         message: &str,
         attachments: &[String],
+        textStyle: &[String],
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "send", param_kind = map)]
+    fn send_note_to_self(
+        &self,
+        account: Option<&str>,
+        message: &str,
+        noteToSelf: bool,
     ) -> Result<Value, ErrorObjectOwned>;
 
     #[method(name = "sendTyping", param_kind = map)]
     fn send_typing(
         &self,
+        account: Option<&str>,
         recipient: Option<&str>,
         groupId: Option<&str>,
         stop: bool,
     ) -> Result<Value, ErrorObjectOwned>;
 
+    #[method(name = "listGroups", param_kind = map)]
+    fn list_groups(&self, account: Option<&str>) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "updateGroup", param_kind = map)]
+    fn update_group(
+        &self,
+        account: Option<&str>,
+        groupId: Option<&str>,
+        name: Option<&str>,
+        description: Option<&str>,
+        members: Option<&[String]>,
+        link: Option<&str>,
+        setPermissionAddMember: Option<&str>,
+        setPermissionEditDetails: Option<&str>,
+        setPermissionSendMessages: Option<&str>,
+        expiration: Option<u64>,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "updateGroup", param_kind = map)]
+    fn add_group_members(
+        &self,
+        account: Option<&str>,
+        groupId: &str,
+        members: &[String],
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "updateGroup", param_kind = map)]
+    fn remove_group_members(
+        &self,
+        account: Option<&str>,
+        groupId: &str,
+        removeMembers: &[String],
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "updateGroup", param_kind = map)]
+    fn add_group_admins(
+        &self,
+        account: Option<&str>,
+        groupId: &str,
+        admins: &[String],
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "updateGroup", param_kind = map)]
+    fn remove_group_admins(
+        &self,
+        account: Option<&str>,
+        groupId: &str,
+        removeAdmins: &[String],
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "updateGroup", param_kind = map)]
+    fn accept_group_invite(
+        &self,
+        account: Option<&str>,
+        groupId: &str,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "quitGroup", param_kind = map)]
+    fn quit_group(
+        &self,
+        account: Option<&str>,
+        groupId: &str,
+        delete: bool,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "block", param_kind = map)]
+    fn block_group(&self, account: Option<&str>, groupId: &str) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "listAccounts", param_kind = map)]
+    fn list_accounts(&self) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "listDevices", param_kind = map)]
+    fn list_devices(&self, account: Option<&str>) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "addDevice", param_kind = map)]
+    fn add_device(&self, account: Option<&str>, uri: &str) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "removeDevice", param_kind = map)]
+    fn remove_device(
+        &self,
+        account: Option<&str>,
+        deviceId: u32,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "listIdentities", param_kind = map)]
+    fn list_identities(
+        &self,
+        account: Option<&str>,
+        number: Option<&str>,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "trust", param_kind = map)]
+    fn trust(
+        &self,
+        account: Option<&str>,
+        recipient: &str,
+        trustAllKnownKeys: bool,
+        verifiedSafetyNumber: Option<&str>,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "listContacts", param_kind = map)]
+    fn list_contacts(&self, account: Option<&str>) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "updateContact", param_kind = map)]
+    fn update_contact(
+        &self,
+        account: Option<&str>,
+        recipient: &str,
+        name: Option<&str>,
+        expiration: Option<u64>,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "updateProfile", param_kind = map)]
+    fn update_profile(
+        &self,
+        account: Option<&str>,
+        givenName: Option<&str>,
+        about: Option<&str>,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "updateConfiguration", param_kind = map)]
+    fn update_configuration(
+        &self,
+        account: Option<&str>,
+        readReceipts: Option<bool>,
+        unidentifiedDeliveryIndicators: Option<bool>,
+        typingIndicators: Option<bool>,
+        linkPreviews: Option<bool>,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "addStickerPack", param_kind = map)]
+    fn add_sticker_pack(&self, account: Option<&str>, uri: &str)
+    -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "getUserStatus", param_kind = map)]
+    fn user_status(&self, recipient: &[&str]) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "register", param_kind = map)]
+    fn register(
+        &self,
+        account: &str,
+        voice: bool,
+        captcha: Option<&str>,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "verify", param_kind = map)]
+    fn verify(
+        &self,
+        account: &str,
+        verificationCode: &str,
+        pin: Option<&str>,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "unregister", param_kind = map)]
+    fn unregister(&self, account: &str, deleteAccount: bool) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "deleteLocalAccountData", param_kind = map)]
+    fn delete_local_account_data(&self, account: &str) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "getAttachment", param_kind = map)]
+    fn get_attachment(&self, account: Option<&str>, id: &str) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "getAvatar", param_kind = map)]
+    fn get_avatar(
+        &self,
+        account: Option<&str>,
+        contact: Option<&str>,
+        groupId: Option<&str>,
+    ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "startLink", param_kind = map)]
+    fn start_link(&self) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "finishLink", param_kind = map)]
+    fn finish_link(&self, deviceLinkUri: &str, deviceName: &str)
+    -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "version")]
+    fn version(&self) -> Result<Value, ErrorObjectOwned>;
+
     #[subscription(name = "subscribeReceive" => "receive", unsubscribe = "unsubscribeReceive", item = Value, param_kind = map)]
-    async fn subscribe_receive(&self) -> SubscriptionResult;
+    async fn subscribe_receive(&self, account: Option<&str>) -> SubscriptionResult;
 }
@@ -0,0 +1,119 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// Address of client request comes from, as told by trusted proxies it went through.
+#[derive(Clone, Copy)]
+pub struct Client(pub IpAddr);
+
+/// Reverse proxies trusted to tell address of clients they forward requests of.
+pub struct Proxies(Vec<IpAddr>);
+
+impl Proxies {
+    pub const fn new(trusted: Vec<IpAddr>) -> Self {
+        Self(trusted)
+    }
+
+    /// Address of client, last one forwarding headers name that is not a trusted proxy, or peer
+    /// of socket if it is not trusted to forward requests itself.
+    fn client(&self, req: &poem::Request) -> Option<IpAddr> {
+        let peer = req.remote_addr().as_socket_addr()?.ip();
+
+        if !self.0.contains(&peer) {
+            return Some(peer);
+        }
+
+        let forwarded = forwarded(req);
+
+        // Walk chain from proxy closest to us, as clients can put anything at start of it
+        let mut client = peer;
+
+        for addr in forwarded.into_iter().rev() {
+            client = addr;
+
+            if !self.0.contains(&addr) {
+                break;
+            }
+        }
+
+        Some(client)
+    }
+}
+
+/// Tell endpoints address of client behind trusted proxies, logging requests with it.
+pub async fn identify<E: poem::Endpoint>(
+    next: E,
+    mut req: poem::Request,
+    proxies: Arc<Proxies>,
+) -> poem::Result<poem::Response> {
+    use poem::IntoResponse;
+
+    let client = proxies.client(&req);
+
+    if let Some(client) = client {
+        req.extensions_mut().insert(Client(client));
+    }
+
+    let (method, path) = (req.method().clone(), req.uri().path().to_owned());
+
+    let resp = match next.call(req).await {
+        Ok(resp) => resp.into_response(),
+        Err(error) => error.into_response(),
+    };
+
+    let client = client.map_or_else(|| String::from("unknown"), |c| c.to_string());
+
+    tracing::debug!("{method} {path} from {client}: {}", resp.status());
+
+    Ok(resp)
+}
+
+/// Addresses request was forwarded for, first client to last proxy, from `Forwarded` header or
+/// `X-Forwarded-For` if absent.
+fn forwarded(req: &poem::Request) -> Vec<IpAddr> {
+    let values = |name| {
+        req.headers()
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>()
+    };
+
+    let elements = values("forwarded");
+
+    if elements.is_empty() {
+        return values("x-forwarded-for")
+            .into_iter()
+            .filter_map(parse)
+            .collect();
+    }
+
+    elements
+        .into_iter()
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.trim().split_once('=')?;
+
+                key.eq_ignore_ascii_case("for").then_some(value)
+            })
+        })
+        .filter_map(parse)
+        .collect()
+}
+
+/// Address of node, as written in forwarding headers, quoted or not, with port or not.
+fn parse(node: &str) -> Option<IpAddr> {
+    let node = node.trim_matches('"');
+
+    if let Ok(addr) = node.parse() {
+        return Some(addr);
+    }
+
+    // Addresses with port, IPv6 ones in brackets
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+
+    node.split_once(':')?.0.parse().ok()
+}
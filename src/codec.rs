@@ -3,28 +3,87 @@ use std::io::{Error as ErrorIo, Result as ResultIo};
 use tokio_util::bytes::BytesMut;
 use tokio_util::codec::{Decoder, Encoder};
 
-pub struct Codec;
+pub struct Codec {
+    /// Upper bound on size in bytes of each decoded line, excluding delimiter.
+    max_length: usize,
+
+    /// Number of buffered bytes already searched for delimiter, to avoid scanning them again.
+    scanned: usize,
+}
+
+impl Codec {
+    #[must_use]
+    pub const fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+            scanned: 0,
+        }
+    }
+}
 
 impl Decoder for Codec {
     type Item = String;
     type Error = ErrorIo;
 
     fn decode(&mut self, buf: &mut BytesMut) -> ResultIo<Option<Self::Item>> {
-        let Some(i) = buf.as_ref().iter().position(|&b| b == b'\n') else {
-            return Ok(None);
-        };
+        loop {
+            let position = buf.as_ref()[self.scanned..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map(|i| self.scanned + i);
+
+            // Refuse to buffer indefinitely, whether or not delimiter was received yet
+            if position.unwrap_or(buf.len()) > self.max_length {
+                let max = self.max_length;
+                return Err(ErrorIo::other(format!("frame exceeds {max} bytes")));
+            }
+
+            let Some(i) = position else {
+                self.scanned = buf.len();
+                return Ok(None);
+            };
 
-        let line = buf.split_to(i);
-        let _ = buf.split_to(1);
+            self.scanned = 0;
 
-        let Ok(s) = core::str::from_utf8(line.as_ref()) else {
-            return Err(ErrorIo::other("invalid UTF-8"));
-        };
+            let line = buf.split_to(i);
+            let _ = buf.split_to(1);
 
-        Ok(Some(s.to_string()))
+            // Skip keep-alive lines, possibly terminated by CRLF
+            if let Some(s) = parse(&line)? {
+                return Ok(Some(s));
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> ResultIo<Option<Self::Item>> {
+        if let Some(s) = self.decode(buf)? {
+            return Ok(Some(s));
+        }
+
+        // Last line may lack delimiter when peer closes connection
+        self.scanned = 0;
+
+        parse(&buf.split())
     }
 }
 
+/// Decode line stripped of its delimiter, ignoring it when blank.
+///
+/// Multibyte UTF-8 sequences never contain a newline byte, so complete lines decode on their own.
+fn parse(line: &[u8]) -> ResultIo<Option<String>> {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+
+    let Ok(s) = core::str::from_utf8(line) else {
+        return Err(ErrorIo::other("invalid UTF-8"));
+    };
+
+    Ok(Some(s.to_string()))
+}
+
 impl Encoder<String> for Codec {
     type Error = ErrorIo;
 
@@ -35,3 +94,90 @@ impl Encoder<String> for Codec {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio_util::bytes::BytesMut;
+    use tokio_util::codec::Decoder;
+
+    use super::Codec;
+
+    /// Lines decoded from bytes received in given chunks.
+    fn lines(codec: &mut Codec, chunks: &[&[u8]]) -> Vec<String> {
+        let mut buf = BytesMut::new();
+
+        let mut lines = Vec::new();
+
+        for chunk in chunks {
+            buf.extend_from_slice(chunk);
+
+            while let Some(line) = codec.decode(&mut buf).unwrap() {
+                lines.push(line);
+            }
+        }
+
+        lines
+    }
+
+    #[test]
+    fn lines_may_end_with_crlf() {
+        let mut codec = Codec::new(64);
+
+        let chunks: [&[u8]; 2] = [b"{\"a\":1}\r\n{\"b\"", b":2}\r\n"];
+
+        assert_eq!(lines(&mut codec, &chunks), ["{\"a\":1}", "{\"b\":2}"]);
+    }
+
+    #[test]
+    fn blank_lines_are_skipped() {
+        let mut codec = Codec::new(64);
+
+        let chunks: [&[u8]; 3] = [b"\n\r\n", b"  \n", b"{}\n\n"];
+
+        assert_eq!(lines(&mut codec, &chunks), ["{}"]);
+    }
+
+    #[test]
+    fn characters_may_be_split_across_reads() {
+        let mut codec = Codec::new(64);
+
+        let bytes = "\"héllo 😀\"\n".as_bytes();
+
+        // Split within both multibyte characters
+        let chunks = [&bytes[..3], &bytes[3..10], &bytes[10..]];
+
+        assert_eq!(lines(&mut codec, &chunks), ["\"héllo 😀\""]);
+    }
+
+    #[test]
+    fn last_line_may_lack_delimiter() {
+        let mut codec = Codec::new(64);
+
+        let mut buf = BytesMut::from("{}");
+
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        assert_eq!(codec.decode_eof(&mut buf).unwrap().unwrap(), "{}");
+    }
+
+    #[test]
+    fn lines_longer_than_max_length_are_refused() {
+        let mut codec = Codec::new(4);
+
+        assert_eq!(lines(&mut codec, &[b"abcd\n"]), ["abcd"]);
+
+        // Refused before delimiter arrives, not to buffer indefinitely
+        let mut buf = BytesMut::from("abc");
+
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        buf.extend_from_slice(b"de");
+
+        let error = codec.decode(&mut buf).unwrap_err();
+
+        assert_eq!(error.to_string(), "frame exceeds 4 bytes");
+
+        let mut codec = Codec::new(4);
+
+        assert!(codec.decode(&mut BytesMut::from("abcde\n")).is_err());
+    }
+}
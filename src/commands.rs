@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use clap::Command;
+use clap_complete::Shell;
+use color_eyre::eyre::{Result, bail};
+
+use serde_json::Value;
+
+use super::config::Setting;
+use super::daemon::Daemon;
+use super::{Address, Args, DeliveryStatus, SendResp, failed_send, webhook_client};
+
+/// Print completion script of `command` for `shell`, for packagers to install.
+pub fn completions(mut command: Command, shell: Shell) {
+    let name = command.get_name().to_owned();
+
+    clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+}
+
+/// Print man page of `command`, in `roff` format.
+pub fn man(command: Command) -> Result<()> {
+    clap_mangen::Man::new(command).render(&mut std::io::stdout())?;
+
+    Ok(())
+}
+
+/// Print options in effect as configuration file entries, commented with where they are set from.
+pub fn print_config(settings: &[Setting]) {
+    for setting in settings {
+        let values: Vec<_> = setting
+            .values
+            .iter()
+            .map(|v| Value::from(v.as_str()))
+            .collect();
+
+        // Single values are accepted as such even by options that can be repeated
+        let value = match <[Value; 1]>::try_from(values) {
+            Ok([value]) => value,
+            Err(values) => Value::Array(values),
+        };
+
+        println!("{} = {value}  # {}", setting.name, setting.source.as_str());
+    }
+}
+
+/// Send message through daemon, printing outcome of delivery to each recipient.
+pub async fn send(
+    signal: &Daemon,
+    account: Option<&str>,
+    to: Option<&str>,
+    group: Option<&str>,
+    message: &str,
+) -> Result<()> {
+    use poem_openapi::types::ToJSON;
+
+    use super::client::SignalClient;
+
+    let resp: SendResp = match signal.send(account, to, group, message, &[], &[]).await {
+        Ok(value) => serde_json::from_value(value)?,
+        Err(error) => failed_send(&error).ok_or(error)?,
+    };
+
+    let mut failures = 0;
+
+    for result in &resp.results {
+        let Address { number, uuid } = &result.recipient;
+
+        let recipient = number.as_deref().or(uuid.as_deref()).unwrap_or("?");
+
+        if result.status != DeliveryStatus::Success {
+            failures += 1;
+        }
+
+        let status = result.status.to_json();
+
+        let status = status.as_ref().and_then(Value::as_str).unwrap_or_default();
+
+        println!("{recipient}: {status}");
+    }
+
+    if failures > 0 {
+        bail!(
+            "Failed to deliver to {failures} of {} recipients",
+            resp.results.len()
+        );
+    }
+
+    println!("Sent at {}", resp.timestamp);
+
+    Ok(())
+}
+
+/// Send a note to self from account, failing unless daemon reports it delivered.
+pub async fn self_test(signal: &Daemon, account: Option<&str>) -> Result<()> {
+    use poem_openapi::types::ToJSON;
+
+    use super::client::SignalClient;
+
+    let message = "signal-http self-test, sent on start";
+
+    let resp: SendResp = match signal.send_note_to_self(account, message, true).await {
+        Ok(value) => serde_json::from_value(value)?,
+        Err(error) => failed_send(&error).ok_or(error)?,
+    };
+
+    if let Some(result) = resp
+        .results
+        .iter()
+        .find(|r| r.status != DeliveryStatus::Success)
+    {
+        let status = result.status.to_json();
+
+        let status = status.as_ref().and_then(Value::as_str).unwrap_or_default();
+
+        bail!("Self-test note to self failed with `{status}`");
+    }
+
+    Ok(())
+}
+
+/// Report reachability of each daemon, and of webhooks if requested, failing if any is not.
+pub async fn check(args: &Args, webhooks: bool) -> Result<()> {
+    let options = super::options(args)?;
+
+    let mut outcomes = Vec::new();
+
+    // Connect to daemons one by one, since failing over would hide unreachable ones
+    if let Some(command) = &args.spawn_daemon {
+        let signal = Daemon::spawn(command.clone(), args.daemon.clone(), options).await;
+
+        outcomes.push(report(
+            &format!("daemon `{command}`"),
+            version(signal).await,
+        ));
+    } else {
+        for addr in &args.daemon {
+            let signal = Daemon::connect(vec![addr.clone()], options.clone()).await;
+
+            outcomes.push(report(&format!("daemon {addr}"), version(signal).await));
+        }
+    }
+
+    if webhooks {
+        let client = webhook_client(args)?;
+
+        let urls = args
+            .webhook
+            .iter()
+            .chain(args.account_webhook.iter().map(|(_, url)| url));
+
+        for url in urls {
+            outcomes.push(report(&format!("webhook {url}"), ping(&client, url).await));
+        }
+    }
+
+    if outcomes.is_empty() {
+        bail!("Nothing to check, provide `--daemon` or `--spawn-daemon`");
+    }
+
+    let failures = outcomes.iter().filter(|ok| !**ok).count();
+
+    if failures > 0 {
+        bail!("{failures} of {} checks failed", outcomes.len());
+    }
+
+    Ok(())
+}
+
+/// Print outcome of a check, telling whether it succeeded.
+fn report(target: &str, outcome: Result<String>) -> bool {
+    match outcome {
+        Ok(detail) => {
+            println!("ok    {target}: {detail}");
+
+            true
+        }
+        Err(error) => {
+            println!("fail  {target}: {error}");
+
+            false
+        }
+    }
+}
+
+/// Describe version of `signal-cli` daemon is running.
+async fn version(signal: Result<Arc<Daemon>>) -> Result<String> {
+    use super::client::SignalClient;
+
+    let value = signal?.version().await?;
+
+    let version = value
+        .get("version")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown");
+
+    Ok(format!("signal-cli {version}"))
+}
+
+/// Post test payload to webhook, for it to be acknowledged with a successful status.
+async fn ping(client: &reqwest::Client, url: &str) -> Result<String> {
+    let payload = serde_json::json!({ "ping": super::NAME });
+
+    let resp = client.post(url).json(&payload).send().await?;
+
+    Ok(resp.error_for_status()?.status().to_string())
+}
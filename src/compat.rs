@@ -0,0 +1,1415 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use poem::web::Data;
+use poem_openapi::param::{Path, Query};
+use poem_openapi::payload::{Binary, Json};
+use poem_openapi::{ApiResponse, Enum, Object};
+use serde_json::Value;
+
+use super::inbox::Inbox;
+use super::{
+    Api, Audited, Calling, Client, OrInternalServerError, ResultPoem, Signal, Staged, Trusting,
+    group_id, send_audited, unprocessable,
+};
+use super::{
+    React, ReceiptKind, Receive, Recipient, RecipientForm, RecipientKind, Send, Sent, Typing,
+};
+
+/// Endpoints matching API of `bbernhard/signal-cli-rest-api`, for drop-in compatibility.
+pub struct Compat;
+
+/// Attachments directory of daemon, if reachable from local filesystem.
+#[derive(Clone)]
+pub struct Attachments(pub Option<PathBuf>);
+
+/// JSON body upstream clients may post without, content type included, standing for defaults.
+struct Optional<T>(Option<T>);
+
+impl<'a, T> poem_openapi::ApiExtractor<'a> for Optional<T>
+where
+    T: poem_openapi::types::ParseFromJSON + poem_openapi::types::ToJSON,
+{
+    const TYPES: &'static [poem_openapi::ApiExtractorType] =
+        &[poem_openapi::ApiExtractorType::RequestObject];
+
+    type ParamType = ();
+    type ParamRawType = ();
+
+    fn register(registry: &mut poem_openapi::registry::Registry) {
+        <Json<Option<T>> as poem_openapi::ApiExtractor>::register(registry);
+    }
+
+    fn request_meta() -> Option<poem_openapi::registry::MetaRequest> {
+        <Json<Option<T>> as poem_openapi::ApiExtractor>::request_meta()
+    }
+
+    async fn from_request(
+        request: &'a poem::Request,
+        body: &mut poem::RequestBody,
+        _: poem_openapi::ExtractParamOptions<()>,
+    ) -> poem::Result<Self> {
+        use poem_openapi::payload::ParsePayload;
+
+        // Content type is not checked, for bodies of clients leaving it out to be read too
+        let Json(value) = <Json<Option<T>> as ParsePayload>::from_request(request, body).await?;
+
+        Ok(Self(value))
+    }
+}
+
+#[poem_openapi::OpenApi]
+impl Compat {
+    /// Fetch messages received since last call, waiting `timeout` seconds for the first one.
+    #[oai(path = "/v1/receive/:number", method = "get")]
+    async fn receive(
+        &self,
+        number: Path<String>,
+        timeout: Query<Option<u64>>,
+        max_messages: Query<Option<usize>>,
+        inbox: Data<&Arc<Inbox>>,
+    ) -> ResultPoem<Json<Vec<Value>>> {
+        let timeout = Duration::from_secs(timeout.unwrap_or(1));
+        let max = max_messages.unwrap_or(usize::MAX);
+
+        let events = inbox.drain(&number, max, timeout).await;
+
+        // Serialize through serde rather than schema, to keep fields not modeled yet
+        let events = events.iter().map(serde_json::to_value);
+
+        Ok(Json(
+            events
+                .collect::<Result<_, _>>()
+                .or_internal_server_error()?,
+        ))
+    }
+
+    /// Send a message to a single recipient.
+    #[oai(path = "/v2/send", method = "post")]
+    async fn send(
+        &self,
+        Json(mut b): Json<SendCompat>,
+        sig: Signal<'_, '_>,
+        staging: Staged<'_>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+        trusting: Trusting<'_>,
+    ) -> ResultPoem<Sent> {
+        let Some(recipient) = b.recipients.pop() else {
+            return unprocessable("Missing message recipient");
+        };
+
+        if !b.recipients.is_empty() {
+            return unprocessable("Multi-recipient messages are not supported");
+        }
+
+        // Adapt payload to match crate API
+        let body = Send {
+            account: b.number,
+            message: b.message,
+            recipient: RecipientForm::Prefixed(recipient).into(),
+            text_mode: None,
+            attachments: None,
+            uploads: None,
+            urgent: None,
+        };
+
+        // Forward call to native endpoint to centralize logic
+        send_audited(
+            body,
+            Query(None),
+            sig,
+            staging,
+            Data(&None),
+            Data(&None),
+            caller,
+            audit,
+            trusting,
+        )
+        .await
+    }
+
+    /// List groups of account.
+    #[oai(path = "/v1/groups/:number", method = "get")]
+    async fn groups(
+        &self,
+        number: Path<String>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Json<Vec<GroupEntry>>> {
+        let groups = list_groups(&signal, &number).await?;
+
+        Ok(Json(groups.into_iter().map(GroupEntry::from).collect()))
+    }
+
+    /// Create a new group.
+    #[oai(path = "/v1/groups/:number", method = "post")]
+    async fn create_group(
+        &self,
+        number: Path<String>,
+        Json(b): Json<CreateGroup>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<GroupCreated> {
+        use serde_json::from_value;
+
+        let perms = b.permissions.unwrap_or_default();
+
+        let value = signal
+            .update_group(
+                Some(&number),
+                None,
+                Some(&b.name),
+                b.description.as_deref(),
+                Some(&b.members),
+                b.group_link.map(GroupLink::as_str),
+                perms.add_members.map(Permission::as_str),
+                perms.edit_group.map(Permission::as_str),
+                perms.send_messages.map(Permission::as_str),
+                b.expiration_time,
+            )
+            .await
+            .or_internal_server_error()?;
+
+        let GroupUpdated { group_id } = from_value(value).or_internal_server_error()?;
+
+        Ok(GroupCreated::Created(Json(GroupId {
+            id: compat_group_id(&group_id),
+        })))
+    }
+
+    /// Show details of a single group.
+    #[oai(path = "/v1/groups/:number/:groupid", method = "get")]
+    async fn group(
+        &self,
+        number: Path<String>,
+        groupid: Path<String>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Json<GroupEntry>> {
+        let internal_id = group_id(&groupid)?;
+
+        let groups = list_groups(&signal, &number).await?;
+
+        let Some(group) = groups.into_iter().find(|g| g.id == internal_id) else {
+            return Err(poem::error::NotFoundError.into());
+        };
+
+        Ok(Json(GroupEntry::from(group)))
+    }
+
+    /// Update name, description, or settings of a group.
+    #[oai(path = "/v1/groups/:number/:groupid", method = "put")]
+    async fn update_group(
+        &self,
+        number: Path<String>,
+        groupid: Path<String>,
+        Json(b): Json<UpdateGroup>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let internal_id = group_id(&groupid)?;
+
+        let perms = b.permissions.unwrap_or_default();
+
+        signal
+            .update_group(
+                Some(&number),
+                Some(&internal_id),
+                b.name.as_deref(),
+                b.description.as_deref(),
+                None,
+                b.group_link.map(GroupLink::as_str),
+                perms.add_members.map(Permission::as_str),
+                perms.edit_group.map(Permission::as_str),
+                perms.send_messages.map(Permission::as_str),
+                b.expiration_time,
+            )
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Leave group and delete its local data.
+    #[oai(path = "/v1/groups/:number/:groupid", method = "delete")]
+    async fn delete_group(
+        &self,
+        number: Path<String>,
+        groupid: Path<String>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let internal_id = group_id(&groupid)?;
+
+        signal
+            .quit_group(Some(&number), &internal_id, true)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Add members to a group.
+    #[oai(path = "/v1/groups/:number/:groupid/members", method = "post")]
+    async fn add_members(
+        &self,
+        number: Path<String>,
+        groupid: Path<String>,
+        body: Json<Members>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let internal_id = group_id(&groupid)?;
+
+        signal
+            .add_group_members(Some(&number), &internal_id, &body.members)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Remove members from a group.
+    #[oai(path = "/v1/groups/:number/:groupid/members", method = "delete")]
+    async fn remove_members(
+        &self,
+        number: Path<String>,
+        groupid: Path<String>,
+        body: Json<Members>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let internal_id = group_id(&groupid)?;
+
+        signal
+            .remove_group_members(Some(&number), &internal_id, &body.members)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Grant admin rights to group members.
+    #[oai(path = "/v1/groups/:number/:groupid/admins", method = "post")]
+    async fn add_admins(
+        &self,
+        number: Path<String>,
+        groupid: Path<String>,
+        body: Json<Admins>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let internal_id = group_id(&groupid)?;
+
+        signal
+            .add_group_admins(Some(&number), &internal_id, &body.admins)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Revoke admin rights from group members.
+    #[oai(path = "/v1/groups/:number/:groupid/admins", method = "delete")]
+    async fn remove_admins(
+        &self,
+        number: Path<String>,
+        groupid: Path<String>,
+        body: Json<Admins>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let internal_id = group_id(&groupid)?;
+
+        signal
+            .remove_group_admins(Some(&number), &internal_id, &body.admins)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Accept invitation to a group.
+    #[oai(path = "/v1/groups/:number/:groupid/join", method = "post")]
+    async fn join_group(
+        &self,
+        number: Path<String>,
+        groupid: Path<String>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let internal_id = group_id(&groupid)?;
+
+        signal
+            .accept_group_invite(Some(&number), &internal_id)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Leave a group, keeping its local data.
+    #[oai(path = "/v1/groups/:number/:groupid/quit", method = "post")]
+    async fn quit_group(
+        &self,
+        number: Path<String>,
+        groupid: Path<String>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let internal_id = group_id(&groupid)?;
+
+        signal
+            .quit_group(Some(&number), &internal_id, false)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Block a group, ignoring its future messages.
+    #[oai(path = "/v1/groups/:number/:groupid/block", method = "post")]
+    async fn block_group(
+        &self,
+        number: Path<String>,
+        groupid: Path<String>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let internal_id = group_id(&groupid)?;
+
+        signal
+            .block_group(Some(&number), &internal_id)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Send emoji reaction to a message.
+    #[oai(path = "/v1/reactions/:number", method = "post")]
+    async fn react(
+        &self,
+        Path(number): Path<String>,
+        Json(b): Json<ReactCompat>,
+        signal: Signal<'_, '_>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+    ) -> ResultPoem<Done> {
+        // Forward call to native endpoint to centralize logic
+        Api.react(b.into_native(number, false)?, signal, caller, audit)
+            .await?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Remove emoji reaction from a message.
+    #[oai(path = "/v1/reactions/:number", method = "delete")]
+    async fn unreact(
+        &self,
+        Path(number): Path<String>,
+        Json(b): Json<ReactCompat>,
+        signal: Signal<'_, '_>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+    ) -> ResultPoem<Done> {
+        // Forward call to native endpoint to centralize logic
+        Api.react(b.into_native(number, true)?, signal, caller, audit)
+            .await?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Send read or viewed receipt event.
+    #[oai(path = "/v1/receipts/:number", method = "post")]
+    async fn receipt(
+        &self,
+        Path(number): Path<String>,
+        Json(b): Json<ReceiptCompat>,
+        signal: Signal<'_, '_>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+    ) -> ResultPoem<Done> {
+        // Adapt payload to match native API
+        let body = Receive {
+            account: Some(number),
+            recipient: b.recipient,
+            timestamp: b.timestamp,
+            kind: Some(b.receipt_type),
+        };
+
+        // Forward call to native endpoint to centralize logic
+        Api.receive(Json(body), signal, caller, audit).await?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Show typing indicator.
+    #[oai(path = "/v1/typing-indicator/:number", method = "put")]
+    async fn start_typing(
+        &self,
+        Path(number): Path<String>,
+        Json(b): Json<TypingCompat>,
+        signal: Signal<'_, '_>,
+        caller: Calling<'_>,
+    ) -> ResultPoem<Done> {
+        // Forward call to native endpoint to centralize logic
+        Api.typing(b.into_native(number, false)?, signal, caller)
+            .await?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Hide typing indicator.
+    #[oai(path = "/v1/typing-indicator/:number", method = "delete")]
+    async fn stop_typing(
+        &self,
+        Path(number): Path<String>,
+        Json(b): Json<TypingCompat>,
+        signal: Signal<'_, '_>,
+        caller: Calling<'_>,
+    ) -> ResultPoem<Done> {
+        // Forward call to native endpoint to centralize logic
+        Api.typing(b.into_native(number, true)?, signal, caller)
+            .await?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Describe service version and supported features.
+    #[oai(path = "/v1/about", method = "get")]
+    #[expect(clippy::unused_async)]
+    async fn about(&self) -> Json<About> {
+        let capabilities = BTreeMap::from([(String::from("v2/send"), Vec::new())]);
+
+        Json(About {
+            versions: vec![String::from("v1"), String::from("v2")],
+            build: 2,
+            mode: String::from("json-rpc"),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            capabilities,
+        })
+    }
+
+    /// Check that daemon answers requests.
+    #[oai(path = "/v1/health", method = "get")]
+    async fn health(&self, signal: Signal<'_, '_>) -> Health {
+        match signal.version().await {
+            Ok(_) => Health::Healthy,
+            Err(error) => {
+                tracing::warn!("{error}");
+                Health::Unavailable
+            }
+        }
+    }
+
+    /// Start linking a new device, returning QR code to scan with primary device.
+    #[oai(path = "/v1/qrcodelink", method = "get")]
+    async fn qrcodelink(
+        &self,
+        device_name: Query<String>,
+        qrcode_version: Query<Option<i16>>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<LinkQrCode> {
+        use serde_json::from_value;
+
+        let value = signal.start_link().await.or_internal_server_error()?;
+
+        let LinkStarted { device_link_uri } = from_value(value).or_internal_server_error()?;
+
+        let png = render_qr_code(&device_link_uri, qrcode_version.0)?;
+
+        // Daemon completes linking once code is scanned, wait for it in the background
+        let signal = Arc::clone(&signal);
+        tokio::spawn(async move {
+            if let Err(error) = signal.finish_link(&device_link_uri, &device_name).await {
+                tracing::warn!("{error}");
+            }
+        });
+
+        Ok(LinkQrCode::Png(Binary(png)))
+    }
+
+    /// List identifiers of stored attachments.
+    #[oai(path = "/v1/attachments", method = "get")]
+    async fn attachments(&self, dir: Data<&Attachments>) -> ResultPoem<Json<Vec<String>>> {
+        let dir = attachments_dir(&dir)?;
+
+        let mut entries = tokio::fs::read_dir(dir).await.or_internal_server_error()?;
+
+        let mut ids = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await.or_internal_server_error()? {
+            if let Ok(id) = entry.file_name().into_string() {
+                ids.push(id);
+            }
+        }
+
+        Ok(Json(ids))
+    }
+
+    /// Download a stored attachment.
+    #[oai(path = "/v1/attachments/:id", method = "get")]
+    async fn attachment(
+        &self,
+        id: Path<String>,
+        dir: Data<&Attachments>,
+    ) -> ResultPoem<Attachment> {
+        use std::io::ErrorKind;
+
+        let path = attachment_path(&dir, &id)?;
+
+        match tokio::fs::read(path).await {
+            Ok(bytes) => Ok(Attachment::Data(Binary(bytes))),
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                Err(poem::error::NotFoundError.into())
+            }
+            Err(error) => Err(poem::error::InternalServerError(error)),
+        }
+    }
+
+    /// Delete a stored attachment.
+    #[oai(path = "/v1/attachments/:id", method = "delete")]
+    async fn delete_attachment(
+        &self,
+        id: Path<String>,
+        dir: Data<&Attachments>,
+    ) -> ResultPoem<Done> {
+        use std::io::ErrorKind;
+
+        let path = attachment_path(&dir, &id)?;
+
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => Ok(Done::NoContent),
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                Err(poem::error::NotFoundError.into())
+            }
+            Err(error) => Err(poem::error::InternalServerError(error)),
+        }
+    }
+
+    /// List accounts registered on daemon.
+    #[oai(path = "/v1/accounts", method = "get")]
+    async fn accounts(&self, signal: Signal<'_, '_>) -> ResultPoem<Json<Vec<String>>> {
+        use serde_json::from_value;
+
+        let value = signal.list_accounts().await.or_internal_server_error()?;
+
+        let accounts: Vec<AccountInfo> = from_value(value).or_internal_server_error()?;
+
+        Ok(Json(accounts.into_iter().map(|a| a.number).collect()))
+    }
+
+    /// List devices linked to account.
+    #[oai(path = "/v1/devices/:number", method = "get")]
+    async fn devices(
+        &self,
+        number: Path<String>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Json<Vec<DeviceEntry>>> {
+        use serde_json::from_value;
+
+        let value = signal
+            .list_devices(Some(&number))
+            .await
+            .or_internal_server_error()?;
+
+        let devices: Vec<DeviceInfo> = from_value(value).or_internal_server_error()?;
+
+        Ok(Json(devices.into_iter().map(DeviceEntry::from).collect()))
+    }
+
+    /// Link a new device to account, from its `sgnl://linkdevice` URI.
+    #[oai(path = "/v1/devices/:number", method = "post")]
+    async fn add_device(
+        &self,
+        number: Path<String>,
+        body: Json<AddDevice>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        signal
+            .add_device(Some(&number), &body.uri)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Unlink a device from account.
+    #[oai(path = "/v1/devices/:number/:device_id", method = "delete")]
+    async fn remove_device(
+        &self,
+        number: Path<String>,
+        device_id: Path<u32>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        signal
+            .remove_device(Some(&number), *device_id)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// List identity keys of known contacts.
+    #[oai(path = "/v1/identities/:number", method = "get")]
+    async fn identities(
+        &self,
+        number: Path<String>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Json<Vec<IdentityEntry>>> {
+        use serde_json::from_value;
+
+        let value = signal
+            .list_identities(Some(&number), None)
+            .await
+            .or_internal_server_error()?;
+
+        let identities: Vec<IdentityInfo> = from_value(value).or_internal_server_error()?;
+
+        Ok(Json(
+            identities.into_iter().map(IdentityEntry::from).collect(),
+        ))
+    }
+
+    /// Trust identity key of a contact, after verifying safety number or blindly.
+    #[oai(path = "/v1/identities/:number/trust/:numberToTrust", method = "put")]
+    async fn trust(
+        &self,
+        number: Path<String>,
+        #[oai(name = "numberToTrust")] number_to_trust: Path<String>,
+        Json(b): Json<TrustIdentity>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let trust_all = b.trust_all_known_keys.unwrap_or(false);
+
+        if !trust_all && b.verified_safety_number.is_none() {
+            return unprocessable("Either verify safety number or trust all known keys");
+        }
+
+        let safety_number = b.verified_safety_number.as_deref();
+
+        signal
+            .trust(Some(&number), &number_to_trust, trust_all, safety_number)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// List contacts of account.
+    #[oai(path = "/v1/contacts/:number", method = "get")]
+    async fn contacts(
+        &self,
+        number: Path<String>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Json<Vec<ContactEntry>>> {
+        use serde_json::from_value;
+
+        let value = signal
+            .list_contacts(Some(&number))
+            .await
+            .or_internal_server_error()?;
+
+        let contacts: Vec<ContactInfo> = from_value(value).or_internal_server_error()?;
+
+        Ok(Json(contacts.into_iter().map(ContactEntry::from).collect()))
+    }
+
+    /// Update name or disappearing messages timer of a contact.
+    #[oai(path = "/v1/contacts/:number", method = "put")]
+    async fn update_contact(
+        &self,
+        number: Path<String>,
+        body: Json<UpdateContact>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let name = body.name.as_deref();
+
+        signal
+            .update_contact(
+                Some(&number),
+                &body.recipient,
+                name,
+                body.expiration_in_seconds,
+            )
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Update profile of account.
+    #[oai(path = "/v1/profiles/:number", method = "put")]
+    async fn update_profile(
+        &self,
+        number: Path<String>,
+        body: Json<UpdateProfile>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        signal
+            .update_profile(Some(&number), body.name.as_deref(), body.about.as_deref())
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Done::NoContent)
+    }
+
+    /// Check whether phone numbers are registered on Signal.
+    #[oai(path = "/v1/search", method = "get")]
+    async fn search(
+        &self,
+        numbers: Query<Vec<String>>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Json<Vec<SearchEntry>>> {
+        use serde_json::from_value;
+
+        // Accept both repeated parameters and comma-separated lists
+        let numbers: Vec<_> = numbers.iter().flat_map(|n| n.split(',')).collect();
+
+        if numbers.is_empty() {
+            return unprocessable("Missing numbers to search");
+        }
+
+        let value = signal
+            .user_status(&numbers)
+            .await
+            .or_internal_server_error()?;
+
+        let statuses: Vec<UserStatus> = from_value(value).or_internal_server_error()?;
+
+        Ok(Json(statuses.into_iter().map(SearchEntry::from).collect()))
+    }
+
+    /// Request verification code to register phone number, by SMS or voice call.
+    #[oai(path = "/v1/register/:number", method = "post")]
+    async fn register(
+        &self,
+        number: Path<String>,
+        body: Optional<Register>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Created> {
+        let body = body.0.unwrap_or_default();
+
+        let voice = body.use_voice.unwrap_or(false);
+
+        signal
+            .register(&number, voice, body.captcha.as_deref())
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Created::Created)
+    }
+
+    /// Complete registration with received verification code.
+    #[oai(path = "/v1/register/:number/verify/:token", method = "post")]
+    async fn verify(
+        &self,
+        number: Path<String>,
+        token: Path<String>,
+        body: Optional<Verify>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Created> {
+        let body = body.0.unwrap_or_default();
+
+        signal
+            .verify(&number, &token, body.pin.as_deref())
+            .await
+            .or_internal_server_error()?;
+
+        Ok(Created::Created)
+    }
+
+    /// Unregister phone number, optionally deleting account from servers and local data.
+    #[oai(path = "/v1/unregister/:number", method = "post")]
+    async fn unregister(
+        &self,
+        number: Path<String>,
+        body: Json<Unregister>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Done> {
+        let delete_account = body.delete_account.unwrap_or(false);
+
+        signal
+            .unregister(&number, delete_account)
+            .await
+            .or_internal_server_error()?;
+
+        if body.delete_local_data.unwrap_or(false) {
+            signal
+                .delete_local_account_data(&number)
+                .await
+                .or_internal_server_error()?;
+        }
+
+        Ok(Done::NoContent)
+    }
+}
+
+/// Fetch groups of account from daemon.
+async fn list_groups(signal: &Signal<'_, '_>, account: &str) -> ResultPoem<Vec<GroupInfo>> {
+    use serde_json::from_value;
+
+    let value = signal
+        .list_groups(Some(account))
+        .await
+        .or_internal_server_error()?;
+
+    from_value(value).or_internal_server_error()
+}
+
+/// Upstream API identifies groups with base64 encoding of daemon identifier, itself base64.
+fn compat_group_id(internal_id: &str) -> String {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    format!("group.{}", STANDARD.encode(internal_id))
+}
+
+#[expect(clippy::result_large_err)]
+fn attachments_dir(attachments: &Attachments) -> ResultPoem<&std::path::Path> {
+    use poem::error::Error;
+    use poem::http::StatusCode;
+
+    let Some(dir) = &attachments.0 else {
+        let msg = "Attachments directory is not configured";
+        return Err(Error::from_string(msg, StatusCode::NOT_IMPLEMENTED));
+    };
+
+    Ok(dir)
+}
+
+/// Path of attachment `id` in attachments directory.
+#[expect(clippy::result_large_err)]
+fn attachment_path(attachments: &Attachments, id: &str) -> ResultPoem<std::path::PathBuf> {
+    let dir = attachments_dir(attachments)?;
+
+    // Identifiers are plain file names, reject anything that could escape directory
+    if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+        return unprocessable("Invalid attachment id");
+    }
+
+    Ok(dir.join(id))
+}
+
+/// Encode data as a QR code, rendered to a PNG image.
+#[expect(clippy::result_large_err)]
+fn render_qr_code(data: &str, version: Option<i16>) -> ResultPoem<Vec<u8>> {
+    use qrcode::{Color, EcLevel, QrCode, Version};
+
+    /// Side length of a module in pixels.
+    const SCALE: usize = 8;
+
+    /// Margin around code in modules, required by readers.
+    const QUIET_ZONE: usize = 4;
+
+    if version.is_some_and(|v| !(1..=40).contains(&v)) {
+        return unprocessable("QR code version must be between 1 and 40");
+    }
+
+    let code = match version {
+        None => QrCode::new(data),
+        Some(v) => QrCode::with_version(data, Version::Normal(v), EcLevel::M),
+    };
+
+    let Ok(code) = code else {
+        return unprocessable("Link does not fit in QR code of requested version");
+    };
+
+    let width = code.width();
+    let size = (width + 2 * QUIET_ZONE) * SCALE;
+
+    // Start from blank image, then paint dark modules
+    let mut pixels = vec![u8::MAX; size * size];
+
+    for (i, _) in code
+        .to_colors()
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c == Color::Dark)
+    {
+        let (x, y) = (
+            (i % width + QUIET_ZONE) * SCALE,
+            (i / width + QUIET_ZONE) * SCALE,
+        );
+
+        for row in y..y + SCALE {
+            pixels[row * size + x..row * size + x + SCALE].fill(0);
+        }
+    }
+
+    let dimension = u32::try_from(size).or_internal_server_error()?;
+
+    let mut png = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut png, dimension, dimension);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().or_internal_server_error()?;
+    writer
+        .write_image_data(&pixels)
+        .or_internal_server_error()?;
+    writer.finish().or_internal_server_error()?;
+
+    Ok(png)
+}
+
+/// Upstream API accepts both phone numbers and prefixed group identifiers as recipients.
+#[expect(clippy::result_large_err)]
+fn parse_recipient(recipient: String) -> ResultPoem<Recipient> {
+    if recipient.starts_with("group.") {
+        return Ok(Recipient {
+            kind: RecipientKind::Group,
+            value: group_id(&recipient)?,
+        });
+    }
+
+    Ok(Recipient {
+        kind: RecipientKind::Person,
+        value: recipient,
+    })
+}
+
+#[derive(Object)]
+struct SendCompat {
+    number: Option<String>,
+    recipients: Vec<String>,
+    message: String,
+}
+
+#[derive(ApiResponse)]
+enum Done {
+    /// Operation succeeded.
+    #[oai(status = 204)]
+    NoContent,
+}
+
+#[derive(ApiResponse)]
+enum Created {
+    /// Operation succeeded.
+    #[oai(status = 201)]
+    Created,
+}
+
+#[derive(ApiResponse)]
+enum GroupCreated {
+    /// Group was created.
+    #[oai(status = 201)]
+    Created(Json<GroupId>),
+}
+
+#[derive(ApiResponse)]
+enum Health {
+    /// Daemon is reachable.
+    #[oai(status = 204)]
+    Healthy,
+
+    /// Daemon did not answer.
+    #[oai(status = 503)]
+    Unavailable,
+}
+
+#[derive(Object)]
+struct DeviceEntry {
+    id: u32,
+    name: String,
+    creation_timestamp: Option<u64>,
+    last_seen_timestamp: Option<u64>,
+}
+
+impl From<DeviceInfo> for DeviceEntry {
+    fn from(device: DeviceInfo) -> Self {
+        Self {
+            id: device.id,
+            name: device.name.unwrap_or_default(),
+            creation_timestamp: device.created_timestamp,
+            last_seen_timestamp: device.last_seen_timestamp,
+        }
+    }
+}
+
+#[derive(Object)]
+struct AddDevice {
+    uri: String,
+}
+
+#[derive(Object)]
+struct IdentityEntry {
+    number: String,
+    status: String,
+    fingerprint: String,
+    added: String,
+    safety_number: String,
+    uuid: String,
+}
+
+impl From<IdentityInfo> for IdentityEntry {
+    fn from(identity: IdentityInfo) -> Self {
+        Self {
+            number: identity.number.unwrap_or_default(),
+            status: identity.trust_level.unwrap_or_default(),
+            fingerprint: identity.fingerprint.unwrap_or_default(),
+            added: identity
+                .added_timestamp
+                .map(|t| t.to_string())
+                .unwrap_or_default(),
+            safety_number: identity.safety_number.unwrap_or_default(),
+            uuid: identity.uuid.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Object)]
+struct TrustIdentity {
+    verified_safety_number: Option<String>,
+    trust_all_known_keys: Option<bool>,
+}
+
+#[derive(Object)]
+struct ContactEntry {
+    number: String,
+    uuid: String,
+    name: String,
+    profile_name: String,
+    username: String,
+    color: String,
+    blocked: bool,
+    message_expiration: String,
+}
+
+impl From<ContactInfo> for ContactEntry {
+    fn from(contact: ContactInfo) -> Self {
+        let profile = contact.profile.unwrap_or_default();
+
+        // Mirror display of profile names by Signal clients
+        let profile_name = [profile.given_name, profile.family_name]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Self {
+            number: contact.number.unwrap_or_default(),
+            uuid: contact.uuid.unwrap_or_default(),
+            name: contact.name.unwrap_or_default(),
+            profile_name,
+            username: contact.username.unwrap_or_default(),
+            color: contact.color.unwrap_or_default(),
+            blocked: contact.is_blocked,
+            message_expiration: format!("{}s", contact.message_expiration_time),
+        }
+    }
+}
+
+#[derive(Object)]
+struct UpdateContact {
+    recipient: String,
+    name: Option<String>,
+    expiration_in_seconds: Option<u64>,
+}
+
+#[derive(Object)]
+struct UpdateProfile {
+    name: Option<String>,
+    about: Option<String>,
+}
+
+#[derive(Object)]
+struct SearchEntry {
+    number: String,
+    registered: bool,
+}
+
+impl From<UserStatus> for SearchEntry {
+    fn from(status: UserStatus) -> Self {
+        Self {
+            number: status.number.unwrap_or(status.recipient),
+            registered: status.is_registered,
+        }
+    }
+}
+
+#[derive(Object, Default)]
+struct Register {
+    captcha: Option<String>,
+    use_voice: Option<bool>,
+}
+
+#[derive(Object, Default)]
+struct Verify {
+    pin: Option<String>,
+}
+
+#[derive(Object)]
+struct Unregister {
+    delete_account: Option<bool>,
+    delete_local_data: Option<bool>,
+}
+
+#[derive(ApiResponse)]
+enum Attachment {
+    /// Raw attachment content.
+    #[oai(status = 200, content_type = "application/octet-stream")]
+    Data(Binary<Vec<u8>>),
+}
+
+#[derive(ApiResponse)]
+enum LinkQrCode {
+    /// Code to scan from primary device.
+    #[oai(status = 200, content_type = "image/png")]
+    Png(Binary<Vec<u8>>),
+}
+
+#[derive(Object)]
+struct About {
+    versions: Vec<String>,
+    build: u32,
+    mode: String,
+    version: String,
+    capabilities: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Object)]
+struct GroupId {
+    id: String,
+}
+
+#[derive(Object)]
+struct GroupEntry {
+    name: String,
+    id: String,
+    internal_id: String,
+    members: Vec<String>,
+    blocked: bool,
+    pending_invites: Vec<String>,
+    pending_requests: Vec<String>,
+    invite_link: String,
+    admins: Vec<String>,
+    description: String,
+}
+
+impl From<GroupInfo> for GroupEntry {
+    fn from(group: GroupInfo) -> Self {
+        let numbers =
+            |members: Vec<Member>| members.into_iter().filter_map(Member::into_id).collect();
+
+        Self {
+            name: group.name.unwrap_or_default(),
+            id: compat_group_id(&group.id),
+            members: numbers(group.members),
+            blocked: group.is_blocked,
+            pending_invites: numbers(group.pending_members),
+            pending_requests: numbers(group.requesting_members),
+            invite_link: group.group_invite_link.unwrap_or_default(),
+            admins: numbers(group.admins),
+            description: group.description.unwrap_or_default(),
+            internal_id: group.id,
+        }
+    }
+}
+
+#[derive(Object)]
+struct CreateGroup {
+    name: String,
+    members: Vec<String>,
+    description: Option<String>,
+    permissions: Option<GroupPermissions>,
+    group_link: Option<GroupLink>,
+    expiration_time: Option<u64>,
+}
+
+#[derive(Object)]
+struct UpdateGroup {
+    name: Option<String>,
+    description: Option<String>,
+    permissions: Option<GroupPermissions>,
+    group_link: Option<GroupLink>,
+    expiration_time: Option<u64>,
+}
+
+#[derive(Object, Default)]
+struct GroupPermissions {
+    add_members: Option<Permission>,
+    edit_group: Option<Permission>,
+    send_messages: Option<Permission>,
+}
+
+#[derive(Object)]
+struct Members {
+    members: Vec<String>,
+}
+
+#[derive(Object)]
+struct Admins {
+    admins: Vec<String>,
+}
+
+#[derive(Enum, Clone, Copy)]
+#[oai(rename_all = "kebab-case")]
+enum Permission {
+    OnlyAdmins,
+    EveryMember,
+}
+
+impl Permission {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::OnlyAdmins => "only-admins",
+            Self::EveryMember => "every-member",
+        }
+    }
+}
+
+#[derive(Enum, Clone, Copy)]
+#[oai(rename_all = "kebab-case")]
+enum GroupLink {
+    Disabled,
+    Enabled,
+    EnabledWithApproval,
+}
+
+impl GroupLink {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Disabled => "disabled",
+            Self::Enabled => "enabled",
+            Self::EnabledWithApproval => "enabled-with-approval",
+        }
+    }
+}
+
+#[derive(Object)]
+struct ReactCompat {
+    reaction: String,
+    recipient: String,
+    target_author: String,
+    timestamp: u64,
+}
+
+impl ReactCompat {
+    #[expect(clippy::result_large_err)]
+    fn into_native(self, account: String, remove: bool) -> ResultPoem<Json<React>> {
+        Ok(Json(React {
+            account: Some(account),
+            recipient: parse_recipient(self.recipient)?,
+            emoji: self.reaction,
+            author: self.target_author,
+            timestamp: self.timestamp,
+            remove: Some(remove),
+        }))
+    }
+}
+
+#[derive(Object)]
+struct ReceiptCompat {
+    receipt_type: ReceiptKind,
+    recipient: String,
+    timestamp: u64,
+}
+
+#[derive(Object)]
+struct TypingCompat {
+    recipient: String,
+}
+
+impl TypingCompat {
+    #[expect(clippy::result_large_err)]
+    fn into_native(self, account: String, stop: bool) -> ResultPoem<Json<Typing>> {
+        Ok(Json(Typing {
+            account: Some(account),
+            recipient: parse_recipient(self.recipient)?,
+            stop,
+        }))
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupInfo {
+    id: String,
+    name: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    is_blocked: bool,
+    #[serde(default)]
+    members: Vec<Member>,
+    #[serde(default)]
+    pending_members: Vec<Member>,
+    #[serde(default)]
+    requesting_members: Vec<Member>,
+    #[serde(default)]
+    admins: Vec<Member>,
+    group_invite_link: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct Member {
+    number: Option<String>,
+    uuid: Option<String>,
+}
+
+impl Member {
+    /// Prefer phone number, fall back on service identifier for members hiding it.
+    fn into_id(self) -> Option<String> {
+        self.number.or(self.uuid)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct AccountInfo {
+    number: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeviceInfo {
+    id: u32,
+    name: Option<String>,
+    created_timestamp: Option<u64>,
+    last_seen_timestamp: Option<u64>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IdentityInfo {
+    number: Option<String>,
+    uuid: Option<String>,
+    fingerprint: Option<String>,
+    safety_number: Option<String>,
+    trust_level: Option<String>,
+    added_timestamp: Option<u64>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContactInfo {
+    number: Option<String>,
+    uuid: Option<String>,
+    name: Option<String>,
+    username: Option<String>,
+    color: Option<String>,
+    #[serde(default)]
+    is_blocked: bool,
+    #[serde(default)]
+    message_expiration_time: u64,
+    profile: Option<ProfileInfo>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ProfileInfo {
+    given_name: Option<String>,
+    family_name: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserStatus {
+    recipient: String,
+    number: Option<String>,
+    is_registered: bool,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LinkStarted {
+    device_link_uri: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupUpdated {
+    group_id: String,
+}
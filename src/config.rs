@@ -0,0 +1,460 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use clap::parser::{ArgMatches, ValueSource};
+use clap::{Arg, Command, Parser};
+use color_eyre::eyre::{Result, bail};
+use poem_openapi::{Enum, Object};
+use toml::Value;
+
+/// Prefix of environment variables options can be set with, followed by their upper-case name.
+const ENV_PREFIX: &str = "SIGNAL_HTTP_";
+
+/// Words in names of options holding secrets, whose values are masked when reported.
+const SECRETS: [&str; 3] = ["password", "secret", "token"];
+
+/// Placeholder standing in for masked values.
+const MASK: &str = "********";
+
+/// Option in effect, with where its value comes from.
+#[derive(Object, Clone)]
+pub struct Setting {
+    /// Long name of option.
+    pub name: String,
+
+    /// Values of option, masked if they are secret.
+    pub values: Vec<String>,
+
+    pub source: Source,
+
+    /// Values of option as given, secrets included, to parse them again on reload.
+    #[oai(skip)]
+    pub raw: Vec<OsString>,
+}
+
+#[derive(Enum, Clone, Copy)]
+#[oai(rename_all = "snake_case")]
+pub enum Source {
+    Default,
+    Environment,
+    File,
+    CommandLine,
+}
+
+impl Source {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Environment => "environment",
+            Self::File => "file",
+            Self::CommandLine => "command line",
+        }
+    }
+
+    /// Whether option was set on command line or in environment, taking precedence over file.
+    #[must_use]
+    pub const fn is_explicit(self) -> bool {
+        matches!(self, Self::CommandLine | Self::Environment)
+    }
+}
+
+/// Parse command line, filling options it leaves out from environment, then `--config` file.
+///
+/// File keys are long option names, arrays repeat options and tables pass `key=value` pairs.
+///
+/// # Errors
+///
+/// Fails if configuration file cannot be read, exits on invalid options instead.
+pub fn parse<T: Parser>() -> Result<(T, Vec<Setting>)> {
+    // Print usage and exit on invalid options, like parsing command line alone does
+    try_parse().map_err(|error| match error.downcast::<clap::Error>() {
+        Ok(error) => error.exit(),
+        Err(error) => error,
+    })
+}
+
+/// Parse options like [`parse`], reporting invalid ones instead of exiting.
+///
+/// # Errors
+///
+/// Fails if configuration file cannot be read, or if options are invalid.
+pub fn try_parse<T: Parser>() -> Result<(T, Vec<Setting>)> {
+    let cli: Vec<_> = std::env::args_os().collect();
+
+    // Locate file first, tolerating required options it may provide
+    let partial = command::<T>()
+        .ignore_errors(true)
+        .try_get_matches_from(&cli)?;
+
+    // Command line and environment take precedence, even over options repeated in file
+    let explicit = |arg: &Arg| {
+        matches!(
+            partial.value_source(arg.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        )
+    };
+
+    let (file, from_file) = match partial.get_one::<PathBuf>("config") {
+        None => (Vec::new(), HashSet::new()),
+        Some(path) => args_of(path, &command::<T>(), explicit)?,
+    };
+
+    // Insert file options right after binary name, as if they had been typed first
+    let at = cli.len().min(1);
+
+    let matches =
+        command::<T>().try_get_matches_from(cli[..at].iter().chain(&file).chain(&cli[at..]))?;
+
+    let settings = settings(&command::<T>(), &matches, &from_file);
+
+    Ok((T::from_arg_matches(&matches)?, settings))
+}
+
+/// Options process started with, as `settings` describe, with those of configuration file at
+/// `path` read again over them, along with settings of those not set explicitly.
+///
+/// Options set on command line or in environment keep values they started with, others missing
+/// from file are reset to their defaults. Command line and environment of process are not
+/// read again, for options to be reloaded however they were first parsed.
+///
+/// # Errors
+///
+/// Fails if configuration file cannot be read, or if options it holds are invalid.
+pub fn reload<T: Parser>(path: &Path, settings: &[Setting]) -> Result<(T, Vec<Setting>)> {
+    use clap::error::ErrorKind;
+
+    let explicit = |arg: &Arg| {
+        settings
+            .iter()
+            .find(|s| s.source.is_explicit() && arg.get_long() == Some(&s.name))
+    };
+
+    // Explicit options default to values they started with, file not overriding them
+    let command = T::command().mut_args(|arg| match explicit(&arg) {
+        Some(setting) => arg.default_values(&setting.raw),
+        None => arg,
+    });
+
+    let (file, from_file) = args_of(path, &command, |arg| explicit(arg).is_some())?;
+
+    let line: Vec<_> = std::iter::once(OsString::from(command.get_name()))
+        .chain(file)
+        .collect();
+
+    // Options required at start were given then, whether or not file still has them; values are
+    // checked before requirements are, so those of file were valid if only requirements failed
+    let matches = match command.clone().try_get_matches_from(&line) {
+        Err(error) if error.kind() == ErrorKind::MissingRequiredArgument => command
+            .clone()
+            .ignore_errors(true)
+            .try_get_matches_from(&line)?,
+        matches => matches?,
+    };
+
+    let mut reloaded = self::settings(&command, &matches, &from_file);
+
+    // Explicit options keep settings they started with, rather than pass for defaults
+    reloaded.retain(|s| {
+        !settings
+            .iter()
+            .any(|e| e.source.is_explicit() && e.name == s.name)
+    });
+
+    Ok((T::from_arg_matches(&matches)?, reloaded))
+}
+
+/// Definition of options, each of them also read from its environment variable.
+#[must_use]
+pub fn command<T: Parser>() -> Command {
+    T::command().mut_args(|arg| {
+        let name = format!("{ENV_PREFIX}{}", arg.get_id().as_str().to_uppercase());
+
+        arg.env(name)
+    })
+}
+
+/// Turn entries of configuration file into options, skipping `explicit` ones set otherwise.
+///
+/// Identifiers of options taken from file are returned too, to tell where values come from.
+fn args_of(
+    path: &Path,
+    command: &Command,
+    explicit: impl Fn(&Arg) -> bool,
+) -> Result<(Vec<OsString>, HashSet<String>)> {
+    use clap::ArgAction;
+    use color_eyre::eyre::WrapErr;
+
+    let table: toml::Table = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read {}", path.display()))?
+        .parse()?;
+
+    let mut args = Vec::new();
+    let mut ids = HashSet::new();
+
+    for (key, value) in table {
+        let arg = command
+            .get_arguments()
+            .find(|a| a.get_long() == Some(&key) && a.get_id() != "config");
+
+        let Some(arg) = arg else {
+            bail!("Unknown option in {}: {key}", path.display());
+        };
+
+        if explicit(arg) {
+            continue;
+        }
+
+        ids.insert(arg.get_id().to_string());
+
+        // Flags take no value, set by `true` and left unset by `false`
+        if matches!(arg.get_action(), ArgAction::SetTrue) {
+            match value {
+                Value::Boolean(true) => args.push(OsString::from(format!("--{key}"))),
+                Value::Boolean(false) => {}
+                _ => bail!("Expected boolean for {key} in {}", path.display()),
+            }
+
+            continue;
+        }
+
+        for value in values(&key, value)? {
+            args.push(OsString::from(format!("--{key}={value}")));
+        }
+    }
+
+    Ok((args, ids))
+}
+
+/// Set option of configuration file to value, or remove it, keeping formatting of other entries.
+///
+/// With `entry`, only that key of table option is changed, or `entry=value` items of array one.
+///
+/// # Errors
+///
+/// Fails if configuration file cannot be read or written, or if option is neither table nor array.
+pub fn store(path: &Path, key: &str, entry: Option<&str>, value: Option<&str>) -> Result<()> {
+    use color_eyre::eyre::WrapErr;
+    use toml_edit::{DocumentMut, Item};
+
+    let mut document: DocumentMut = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read {}", path.display()))?
+        .parse()?;
+
+    match (entry, value) {
+        (None, Some(value)) => match document.get_mut(key).and_then(Item::as_value_mut) {
+            // Keep comments around value being replaced
+            Some(current) => {
+                let decor = current.decor().clone();
+
+                *current = value.into();
+                *current.decor_mut() = decor;
+            }
+            None => document[key] = toml_edit::value(value),
+        },
+        (None, None) => drop(document.remove(key)),
+        (Some(entry), value) => {
+            let item = document.entry(key).or_insert_with(toml_edit::table);
+
+            if let Some(array) = item.as_array_mut() {
+                let prefix = format!("{entry}=");
+
+                array.retain(|v| !v.as_str().is_some_and(|v| v.starts_with(&prefix)));
+                array.extend(value.map(|value| format!("{prefix}{value}")));
+            } else if let Some(table) = item.as_table_like_mut() {
+                match value {
+                    Some(value) => drop(table.insert(entry, toml_edit::value(value))),
+                    None => drop(table.remove(entry)),
+                }
+            } else {
+                bail!(
+                    "Option {key} of {} is neither table nor array",
+                    path.display()
+                );
+            }
+
+            // Drop option left without entries, rather than keep an empty table around
+            if document.get(key).is_some_and(|item| match item {
+                Item::Table(table) => table.is_empty(),
+                _ => item.as_array().is_some_and(toml_edit::Array::is_empty),
+            }) {
+                document.remove(key);
+            }
+        }
+    }
+
+    std::fs::write(path, document.to_string())
+        .wrap_err_with(|| format!("Failed to write {}", path.display()))
+}
+
+/// Describe value and origin of each option that is set, including to its default.
+fn settings(command: &Command, matches: &ArgMatches, from_file: &HashSet<String>) -> Vec<Setting> {
+    let settings = command.get_arguments().filter_map(|arg| {
+        let id = arg.get_id().as_str();
+
+        let source = match matches.value_source(id)? {
+            ValueSource::DefaultValue => Source::Default,
+            ValueSource::EnvVariable => Source::Environment,
+            _ if from_file.contains(id) => Source::File,
+            _ => Source::CommandLine,
+        };
+
+        let name = arg.get_long()?;
+
+        let raw: Vec<_> = matches.get_raw(id).into_iter().flatten().collect();
+
+        Some(Setting {
+            name: name.to_owned(),
+            values: raw
+                .iter()
+                .map(|v| mask(name, &v.to_string_lossy()))
+                .collect(),
+            source,
+            raw: raw.into_iter().map(ToOwned::to_owned).collect(),
+        })
+    });
+
+    settings.collect()
+}
+
+/// Hide value of secret options, and credentials of URLs, possibly given as `key=url`.
+#[must_use]
+pub fn mask(name: &str, value: &str) -> String {
+    use reqwest::Url;
+
+    if SECRETS.iter().any(|s| name.contains(s)) {
+        return String::from(MASK);
+    }
+
+    let (prefix, url) = match value.split_once('=') {
+        Some((key, url)) if !key.contains(':') => (&value[..=key.len()], url),
+        _ => ("", value),
+    };
+
+    match Url::parse(url) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some(MASK));
+
+            format!("{prefix}{url}")
+        }
+        _ => value.to_owned(),
+    }
+}
+
+/// Flatten value of `key` into values of as many options.
+fn values(key: &str, value: Value) -> Result<Vec<String>> {
+    match value {
+        Value::Array(values) => values.into_iter().map(|v| scalar(key, v)).collect(),
+        Value::Table(table) => table
+            .into_iter()
+            .map(|(k, v)| Ok(format!("{k}={}", scalar(key, v)?)))
+            .collect(),
+        value => Ok(vec![scalar(key, value)?]),
+    }
+}
+
+fn scalar(key: &str, value: Value) -> Result<String> {
+    Ok(match value {
+        Value::String(s) => s,
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Datetime(d) => d.to_string(),
+        Value::Array(_) | Value::Table(_) => bail!("Nested values are not supported for {key}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use clap::{CommandFactory, Parser};
+
+    use super::{Setting, Source};
+
+    #[derive(Parser)]
+    struct Options {
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(long)]
+        privacy: bool,
+
+        #[arg(long)]
+        account: Vec<String>,
+
+        #[arg(long)]
+        account_webhook: Vec<String>,
+    }
+
+    /// Write configuration file of test `name`, removed once dropped.
+    struct File(PathBuf);
+
+    impl File {
+        fn new(name: &str, contents: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("signal-http-{name}-{}.toml", std::process::id()));
+
+            std::fs::write(&path, contents).unwrap();
+
+            Self(path)
+        }
+    }
+
+    impl Drop for File {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    const CONTENTS: &str = r#"
+        dry-run = true
+        privacy = false
+        account = ["+15550000", "+15559999"]
+
+        [account-webhook]
+        "+15550000" = "http://127.0.0.1:9/"
+    "#;
+
+    #[test]
+    fn file_sets_flags_repeated_and_table_options() {
+        let file = File::new("parse", CONTENTS);
+
+        let (args, ids) = super::args_of(&file.0, &Options::command(), |_| false).unwrap();
+
+        let options = Options::try_parse_from(std::iter::once("test".into()).chain(args)).unwrap();
+
+        assert!(options.dry_run);
+        assert!(!options.privacy);
+        assert_eq!(options.account, ["+15550000", "+15559999"]);
+        assert_eq!(options.account_webhook, ["+15550000=http://127.0.0.1:9/"]);
+        assert!(ids.contains("privacy"));
+    }
+
+    #[test]
+    fn flags_of_file_are_rejected_unless_boolean() {
+        let file = File::new("flag", "dry-run = \"yes\"");
+
+        assert!(super::args_of(&file.0, &Options::command(), |_| false).is_err());
+    }
+
+    #[test]
+    fn reload_reads_flags_keeping_explicit_ones() {
+        let file = File::new("reload", CONTENTS);
+
+        let settings = [Setting {
+            name: String::from("privacy"),
+            values: vec![String::from("true")],
+            source: Source::CommandLine,
+            raw: vec!["true".into()],
+        }];
+
+        let (options, reloaded): (Options, _) = super::reload(&file.0, &settings).unwrap();
+
+        assert!(options.dry_run);
+        assert!(options.privacy);
+        assert_eq!(options.account.len(), 2);
+        assert!(reloaded.iter().all(|s| s.name != "privacy"));
+    }
+}
@@ -0,0 +1,1026 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, Weak};
+use std::time::Duration;
+
+use color_eyre::eyre::{Result, eyre};
+use jsonrpsee::core::client::{
+    BatchResponse, ClientT, Error as ErrorRpc, Subscription, SubscriptionClientT,
+};
+use jsonrpsee::core::params::BatchRequestBuilder;
+use jsonrpsee::core::traits::ToRpcParams;
+use jsonrpsee::ws_client::WsClient;
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::{Notify, broadcast};
+#[cfg(feature = "tls")]
+use tokio_rustls::rustls::ClientConfig;
+
+use super::child::{self, Pipes};
+use super::events::Event;
+use super::listings::Listings;
+use super::mock::Mock;
+use super::throttle::Throttle;
+use super::transport::traffic::{self, Traffic};
+
+/// Delay before first reconnection attempt, doubled after each failure.
+const BACKOFF_MIN: Duration = Duration::from_millis(500);
+
+/// Upper bound on delay between reconnection attempts.
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// Upper bound on time for daemon to answer health checks.
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Methods sending messages or events on behalf of account, skipped in dry-run mode.
+const SENDS: [&str; 4] = ["send", "sendReaction", "sendReceipt", "sendTyping"];
+
+/// Number of sent messages kept for mirrors lagging behind.
+const SENT_CAPACITY: usize = 64;
+
+/// Number of changed identities kept for alerts lagging behind.
+const IDENTITIES_CAPACITY: usize = 16;
+
+/// Methods changing groups of account, invalidating cached listing of them.
+const GROUP_UPDATES: [&str; 4] = ["block", "joinGroup", "quitGroup", "updateGroup"];
+
+/// Methods changing contacts of account, invalidating cached listing of them.
+const CONTACT_UPDATES: [&str; 2] = ["removeContact", "updateContact"];
+
+/// Tuning of daemon connection.
+#[derive(Clone)]
+pub struct Options {
+    /// Interval between health checks of connection, disabled when absent.
+    pub ping_interval: Option<Duration>,
+
+    /// Upper bound on time for daemon to answer each request.
+    pub request_timeout: Duration,
+
+    /// Upper bound on size in bytes of each message from daemon.
+    pub max_frame_length: usize,
+
+    /// Client configuration for `tls://` addresses.
+    #[cfg(feature = "tls")]
+    pub tls: Option<Arc<ClientConfig>>,
+
+    /// Duration to keep retrying initial connection for, before giving up.
+    pub wait: Duration,
+
+    /// Duration requests wait for connection to be re-established, before failing.
+    pub grace: Duration,
+
+    /// Logging of messages exchanged with daemon, disabled when absent.
+    pub traffic: Option<Traffic>,
+
+    /// Recording of messages exchanged with daemon, for later replay, disabled when absent.
+    pub record: Option<Arc<traffic::Recorder>>,
+
+    /// Whether to log sends instead of performing them, replying as if they succeeded.
+    pub dry_run: bool,
+
+    /// Duration group and contact listings are served from cache for, disabled when zero.
+    pub group_cache_ttl: Duration,
+
+    /// Duration to keep retrying requests for while rate limited, before reporting it.
+    pub rate_limit_budget: Duration,
+
+    /// Sends allowed per minute from each account, unlimited when zero.
+    pub send_rate: usize,
+
+    /// Length in characters messages are split in numbered parts beyond, not split when absent.
+    pub split: Option<usize>,
+
+    /// Country calling code national numbers of recipients are assumed to be in, if any.
+    pub country_code: Option<String>,
+}
+
+/// Connection to `signal-cli` daemon, transparently re-established when lost.
+pub struct Daemon {
+    client: RwLock<Arc<WsClient>>,
+
+    /// Whether connection is established and answering requests.
+    ready: AtomicBool,
+
+    /// Signaled when connection is deemed broken despite not being closed.
+    stale: Notify,
+
+    /// Signaled whenever connection is re-established.
+    reconnected: Notify,
+
+    /// Duration requests wait for connection to be re-established, before failing.
+    grace: Duration,
+
+    /// Redaction of sends logged instead of performed, sends go through when absent.
+    dry_run: Option<Traffic>,
+
+    /// Recent group listings, to spare daemon from resolving groups on every message.
+    groups: Listings,
+
+    /// Recent contact listings, for names of recipients to be resolved quickly.
+    contacts: Listings,
+
+    /// Duration to keep retrying requests for while rate limited, before reporting it.
+    rate_limit_budget: Duration,
+
+    /// Pacing of sends, in order within each conversation.
+    throttle: Throttle,
+
+    /// Length in characters messages are split in numbered parts beyond, not split when absent.
+    split: Option<usize>,
+
+    /// Country calling code national numbers of recipients are assumed to be in, if any.
+    country_code: Option<String>,
+
+    /// Messages sent successfully, for those mirroring them to subscribe to.
+    sent: broadcast::Sender<Outgoing>,
+
+    /// Events of identity keys reported changed, for those alerting of them to subscribe to.
+    identities: broadcast::Sender<Event>,
+}
+
+/// Message sent through daemon.
+#[derive(Clone)]
+pub struct Outgoing {
+    /// Parameters of `send` request.
+    pub params: serde_json::Value,
+
+    /// Result of `send` request, with timestamp of message.
+    pub result: serde_json::Value,
+}
+
+impl Daemon {
+    /// Connect to first reachable daemon among addresses, keep connection alive in the background.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no daemon is reachable before waiting period of options is over.
+    pub async fn connect(addrs: Vec<String>, options: Options) -> Result<Arc<Self>> {
+        Self::start(Connector::addresses(addrs), options).await
+    }
+
+    /// Serve requests from in-process mock instead of a real daemon.
+    ///
+    /// # Errors
+    ///
+    /// Fails if connection to mock cannot be set up.
+    pub async fn mock(mock: Arc<Mock>, options: Options) -> Result<Arc<Self>> {
+        Self::start(Connector::Mock(mock), options).await
+    }
+
+    /// Launch supervised daemon, then connect to its socket or standard streams.
+    ///
+    /// # Errors
+    ///
+    /// Fails if daemon cannot be launched, or does not start listening in time.
+    pub async fn spawn(command: String, addrs: Vec<String>, options: Options) -> Result<Arc<Self>> {
+        use tokio::sync::mpsc::unbounded_channel;
+
+        /// Number of connection attempts while daemon starts listening.
+        const ATTEMPTS: u32 = 60;
+
+        if addrs.is_empty() {
+            let (tx, rx) = unbounded_channel();
+
+            tokio::spawn(child::supervise(command, Some(tx)));
+
+            return Self::start(Connector::Pipes(rx), options).await;
+        }
+
+        tokio::spawn(child::supervise(command, None));
+
+        let mut connector = Connector::addresses(addrs);
+
+        // Daemon takes a moment to start listening
+        for _ in 1..ATTEMPTS {
+            match connector.connect(&options).await {
+                Ok(client) => return Ok(Self::supervise(client, connector, options)),
+                Err(error) => tracing::debug!("Daemon is not ready yet: {error}"),
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        Self::start(connector, options).await
+    }
+
+    async fn start(mut connector: Connector, options: Options) -> Result<Arc<Self>> {
+        use tokio::time::Instant;
+
+        let deadline = Instant::now() + options.wait;
+
+        let mut backoff = BACKOFF_MIN;
+
+        // Daemon may be started concurrently, by container orchestrators for instance
+        let client = loop {
+            match connector.connect(&options).await {
+                Ok(client) => break client,
+                Err(error) if Instant::now() + backoff < deadline => {
+                    tracing::warn!("Daemon is not reachable yet: {error}");
+                }
+                Err(error) => return Err(error),
+            }
+
+            tokio::time::sleep(backoff).await;
+
+            backoff = (backoff * 2).min(BACKOFF_MAX);
+        };
+
+        Ok(Self::supervise(client, connector, options))
+    }
+
+    fn supervise(client: WsClient, connector: Connector, options: Options) -> Arc<Self> {
+        let daemon = Arc::new(Self {
+            client: RwLock::new(Arc::new(client)),
+            ready: AtomicBool::new(true),
+            stale: Notify::new(),
+            reconnected: Notify::new(),
+            grace: options.grace,
+            dry_run: options
+                .dry_run
+                .then_some(options.traffic.unwrap_or(Traffic::Redacted)),
+            groups: Listings::new(options.group_cache_ttl),
+            contacts: Listings::new(options.group_cache_ttl),
+            rate_limit_budget: options.rate_limit_budget,
+            throttle: Throttle::new(options.send_rate),
+            split: options.split,
+            country_code: options.country_code.clone(),
+            sent: broadcast::channel(SENT_CAPACITY).0,
+            identities: broadcast::channel(IDENTITIES_CAPACITY).0,
+        });
+
+        if let Some(interval) = options.ping_interval {
+            tokio::spawn(ping(Arc::clone(&daemon), interval));
+        }
+
+        tokio::spawn(reconnect(Arc::clone(&daemon), connector, options));
+
+        daemon
+    }
+
+    /// Whether connection is established and answering requests.
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    /// Client over latest connection, may be broken while reconnection is underway.
+    pub fn current(&self) -> Arc<WsClient> {
+        let client = self.client.read().unwrap_or_else(PoisonError::into_inner);
+
+        Arc::clone(&client)
+    }
+
+    /// Client over latest connection, waiting up to grace period for it to be re-established.
+    async fn connected(&self) -> Arc<WsClient> {
+        let deadline = tokio::time::Instant::now() + self.grace;
+
+        loop {
+            // Register interest before checking connection, to avoid missing a concurrent swap
+            let reconnected = self.reconnected.notified();
+
+            let client = self.current();
+
+            if client.is_connected() {
+                return client;
+            }
+
+            if tokio::time::timeout_at(deadline, reconnected)
+                .await
+                .is_err()
+            {
+                return client;
+            }
+        }
+    }
+
+    fn replace(&self, client: WsClient) {
+        *self.client.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(client);
+
+        self.reconnected.notify_waiters();
+    }
+}
+
+/// Wait for connection to drop or go stale, then re-establish it with exponential backoff.
+async fn reconnect(daemon: Arc<Daemon>, mut connector: Connector, options: Options) {
+    loop {
+        let client = daemon.current();
+
+        tokio::select! {
+            error = client.on_disconnect() => tracing::warn!("Lost connection to daemon: {error}"),
+            () = daemon.stale.notified() => tracing::warn!("Daemon stopped answering, reconnecting"),
+        }
+
+        // Release broken connection, so pending subscriptions terminate once it is replaced
+        drop(client);
+
+        daemon.ready.store(false, Ordering::Relaxed);
+
+        connector.fail_over();
+
+        let mut backoff = BACKOFF_MIN;
+
+        let client = loop {
+            match connector.connect(&options).await {
+                Ok(client) => break client,
+                Err(error) => tracing::warn!("Failed to reconnect to daemon: {error}"),
+            }
+
+            tokio::time::sleep(backoff).await;
+
+            backoff = (backoff * 2).min(BACKOFF_MAX);
+        };
+
+        daemon.replace(client);
+        daemon.ready.store(true, Ordering::Relaxed);
+
+        tracing::info!("Reconnected to daemon");
+    }
+}
+
+/// Periodically check daemon answers requests, to detect half-open connections.
+async fn ping(daemon: Arc<Daemon>, interval: Duration) {
+    use tokio::time::MissedTickBehavior;
+
+    use super::client::SignalClient;
+
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    // Connection last reported as stale, weak so as not to keep it alive once replaced
+    let mut stale = Weak::new();
+
+    loop {
+        ticker.tick().await;
+
+        let client = daemon.current();
+
+        // Reconnection is already underway
+        if !client.is_connected() || Weak::ptr_eq(&stale, &Arc::downgrade(&client)) {
+            continue;
+        }
+
+        match tokio::time::timeout(PING_TIMEOUT.min(interval), client.version()).await {
+            // Errors reported by daemon itself prove connection works
+            Ok(Ok(_) | Err(ErrorRpc::Call(_))) => {
+                daemon.ready.store(true, Ordering::Relaxed);
+                continue;
+            }
+            Ok(Err(error)) => tracing::warn!("Daemon health check failed: {error}"),
+            Err(_) => tracing::warn!("Daemon health check timed out"),
+        }
+
+        daemon.ready.store(false, Ordering::Relaxed);
+        daemon.stale.notify_one();
+
+        stale = Arc::downgrade(&client);
+    }
+}
+
+/// Way to (re-)establish connection to daemon.
+enum Connector {
+    /// Network addresses or socket paths, tried in turn starting from the active one.
+    Addresses { addrs: Vec<String>, active: usize },
+
+    /// Standard streams of each process launched by supervisor.
+    Pipes(UnboundedReceiver<Pipes>),
+
+    /// In-process stand-in for daemon.
+    Mock(Arc<Mock>),
+}
+
+impl Connector {
+    const fn addresses(addrs: Vec<String>) -> Self {
+        Self::Addresses { addrs, active: 0 }
+    }
+
+    /// Start with next address on following attempt, since active one just failed.
+    const fn fail_over(&mut self) {
+        if let Self::Addresses { addrs, active } = self {
+            *active = (*active + 1) % addrs.len();
+        }
+    }
+
+    async fn connect(&mut self, options: &Options) -> Result<WsClient> {
+        match self {
+            Self::Addresses { addrs, active } => {
+                let mut last = eyre!("Missing daemon address");
+
+                for offset in 0..addrs.len() {
+                    let index = (*active + offset) % addrs.len();
+
+                    match connect(&addrs[index], options).await {
+                        Ok(client) => {
+                            if index != *active {
+                                tracing::info!("Failed over to daemon at {}", addrs[index]);
+                            }
+
+                            *active = index;
+
+                            return Ok(client);
+                        }
+                        Err(error) => {
+                            tracing::debug!("Failed to connect to {}: {error}", addrs[index]);
+                            last = error;
+                        }
+                    }
+                }
+
+                Err(last)
+            }
+            Self::Pipes(rx) => {
+                let pipes = rx
+                    .recv()
+                    .await
+                    .ok_or_else(|| eyre!("Daemon did not start"))?;
+
+                Ok(client_over(pipes, options))
+            }
+            Self::Mock(mock) => Ok(client_over(mock.connect(), options)),
+        }
+    }
+}
+
+/// Establish JSON-RPC connection to `signal-cli` daemon, over TCP, TLS, UNIX socket, or HTTP.
+async fn connect(addr: &str, options: &Options) -> Result<WsClient> {
+    use jsonrpsee::async_client::ClientBuilder;
+    use tokio::net::TcpStream;
+
+    use super::transport;
+
+    if addr.starts_with("http://") {
+        let (sender, receiver) = transport::http::connect(addr);
+        let (sender, receiver) =
+            traffic::wrap(sender, receiver, options.traffic, options.record.as_ref());
+
+        let builder = ClientBuilder::default().request_timeout(options.request_timeout);
+
+        return Ok(builder.build_with_tokio(sender, receiver));
+    }
+
+    if let Some(addr) = addr.strip_prefix("tls://") {
+        #[cfg(feature = "tls")]
+        {
+            let config = options
+                .tls
+                .clone()
+                .ok_or_else(|| eyre!("TLS is not configured"))?;
+
+            return Ok(client_over(
+                super::tls::connect(config, addr).await?,
+                options,
+            ));
+        }
+
+        #[cfg(not(feature = "tls"))]
+        color_eyre::eyre::bail!("TLS support is not enabled in this build: {addr}");
+    }
+
+    if let Some(path) = addr.strip_prefix("unix:") {
+        #[cfg(unix)]
+        return Ok(client_over(
+            tokio::net::UnixStream::connect(path).await?,
+            options,
+        ));
+
+        #[cfg(not(unix))]
+        color_eyre::eyre::bail!("UNIX sockets are not supported on this platform: {path}");
+    }
+
+    Ok(client_over(TcpStream::connect(addr).await?, options))
+}
+
+/// Speak JSON-RPC over any bidirectional byte stream.
+fn client_over<T>(io: T, options: &Options) -> WsClient
+where
+    T: 'static + Send + tokio::io::AsyncRead + tokio::io::AsyncWrite,
+{
+    use futures_util::stream::StreamExt;
+    use jsonrpsee::async_client::ClientBuilder;
+    use tokio_util::codec::Decoder;
+
+    use super::codec::Codec;
+    use super::transport::{Receiver, Sender};
+
+    let (sink, stream) = Codec::new(options.max_frame_length).framed(io).split();
+
+    let (sender, receiver) = traffic::wrap(
+        Sender::new(sink),
+        Receiver::new(stream),
+        options.traffic,
+        options.record.as_ref(),
+    );
+
+    ClientBuilder::default()
+        .request_timeout(options.request_timeout)
+        .build_with_tokio(sender, receiver)
+}
+
+// Delegate calls to latest connection, so handlers need not care about short reconnections
+
+impl ClientT for Daemon {
+    async fn notification<Params>(&self, method: &str, params: Params) -> Result<(), ErrorRpc>
+    where
+        Params: ToRpcParams + Send,
+    {
+        self.connected().await.notification(method, params).await
+    }
+
+    async fn request<R, Params>(&self, method: &str, params: Params) -> Result<R, ErrorRpc>
+    where
+        R: DeserializeOwned,
+        Params: ToRpcParams + Send,
+    {
+        let params = Raw(params.to_rpc_params()?);
+
+        // Send long messages in parts, replying with outcome of first one
+        if method == "send"
+            && let Some(parts) = self.split.and_then(|max| split(&params, max))
+        {
+            return self.request_parts(&params, parts).await;
+        }
+
+        if SENDS.contains(&method) {
+            return self.request_paced(method, params).await;
+        }
+
+        if method == "listGroups" || GROUP_UPDATES.contains(&method) {
+            return self.request_listed(&self.groups, method, params).await;
+        }
+
+        if method == "listContacts" || CONTACT_UPDATES.contains(&method) {
+            return self.request_listed(&self.contacts, method, params).await;
+        }
+
+        self.request_retried(method, params).await
+    }
+
+    async fn batch_request<'a, R>(
+        &self,
+        _: BatchRequestBuilder<'a>,
+    ) -> Result<BatchResponse<'a, R>, ErrorRpc>
+    where
+        R: DeserializeOwned + core::fmt::Debug + 'a,
+    {
+        // Batches would skip dry-run, pacing, splitting and retries of single requests
+        Err(ErrorRpc::Custom("Batch requests are not supported".into()))
+    }
+}
+
+impl SubscriptionClientT for Daemon {
+    async fn subscribe<'a, Notif, Params>(
+        &self,
+        subscribe_method: &'a str,
+        params: Params,
+        unsubscribe_method: &'a str,
+    ) -> Result<Subscription<Notif>, ErrorRpc>
+    where
+        Params: ToRpcParams + Send,
+        Notif: DeserializeOwned,
+    {
+        let client = self.connected().await;
+
+        client
+            .subscribe(subscribe_method, params, unsubscribe_method)
+            .await
+    }
+
+    async fn subscribe_to_method<Notif>(
+        &self,
+        method: &str,
+    ) -> Result<Subscription<Notif>, ErrorRpc>
+    where
+        Notif: DeserializeOwned,
+    {
+        self.connected().await.subscribe_to_method(method).await
+    }
+}
+
+impl Daemon {
+    /// Be told of every message sent from now on.
+    pub fn subscribe_sent(&self) -> broadcast::Receiver<Outgoing> {
+        self.sent.subscribe()
+    }
+
+    /// Be told of every identity key reported changed from now on.
+    pub fn subscribe_identities(&self) -> broadcast::Receiver<Event> {
+        self.identities.subscribe()
+    }
+
+    /// Tell subscribers identity key of a contact changed, with `identity_changed` event.
+    pub fn report_identity(&self, event: Event) {
+        let _ = self.identities.send(event);
+    }
+
+    /// Pace send, dry runs included for them to preview ordering and throttling.
+    async fn request_paced<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Raw,
+    ) -> Result<R, ErrorRpc> {
+        let _turn = self.throttle.turn(params.value().as_ref()).await;
+
+        self.request_in_turn(method, params).await
+    }
+
+    /// Send parts of message in a row, holding conversation for parts of other sends not to
+    /// come in between, replying with outcome of first one.
+    ///
+    /// Parts sent before one fails are reported along with error of that one, as `parts` and
+    /// outcomes of `delivered` ones in its data.
+    async fn request_parts<R: DeserializeOwned>(
+        &self,
+        params: &Raw,
+        parts: Vec<Raw>,
+    ) -> Result<R, ErrorRpc> {
+        use jsonrpsee::types::ErrorObject;
+        use jsonrpsee::types::error::INTERNAL_ERROR_CODE;
+        use serde_json::{Map, Value};
+
+        let _turn = self.throttle.turn(params.value().as_ref()).await;
+
+        let count = parts.len();
+
+        let mut delivered: Vec<Value> = Vec::with_capacity(count);
+
+        for part in parts {
+            let error = match self.request_in_turn("send", part).await {
+                Ok(result) => {
+                    delivered.push(result);
+                    continue;
+                }
+                Err(error) => error,
+            };
+
+            if delivered.is_empty() {
+                return Err(error);
+            }
+
+            let (code, message, data) = match &error {
+                ErrorRpc::Call(error) => (
+                    error.code(),
+                    error.message().to_owned(),
+                    error
+                        .data()
+                        .and_then(|d| serde_json::from_str(d.get()).ok()),
+                ),
+                error => (INTERNAL_ERROR_CODE, error.to_string(), None),
+            };
+
+            let mut data = match data {
+                Some(Value::Object(data)) => data,
+                _ => Map::new(),
+            };
+
+            data.insert(String::from("parts"), Value::from(count));
+            data.insert(String::from("delivered"), Value::from(delivered));
+
+            return Err(ErrorRpc::Call(ErrorObject::owned(
+                code,
+                message,
+                Some(data),
+            )));
+        }
+
+        Ok(serde_json::from_value(delivered.swap_remove(0))?)
+    }
+
+    /// Send once ceiling of account allows it if a message, conversation being held already.
+    async fn request_in_turn<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Raw,
+    ) -> Result<R, ErrorRpc> {
+        // Reactions, receipts and typing indicators are not messages, counted against ceiling
+        if method == "send" {
+            self.throttle.pace(params.value().as_ref()).await;
+        }
+
+        match self.dry_run {
+            Some(traffic) => dry_run(traffic, method, params),
+            None if method == "send" => self.request_sent(params).await,
+            None => self.request_retried(method, params).await,
+        }
+    }
+
+    /// Send message, telling subscribers of it once sent.
+    async fn request_sent<R: DeserializeOwned>(&self, params: Raw) -> Result<R, ErrorRpc> {
+        let result: serde_json::Value = self.request_retried("send", params.clone()).await?;
+
+        if self.sent.receiver_count() > 0
+            && let Some(params) = params.value()
+        {
+            let _ = self.sent.send(Outgoing {
+                params,
+                result: result.clone(),
+            });
+        }
+
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Country calling code national numbers of recipients are assumed to be in, if any.
+    pub fn country_code(&self) -> Option<&str> {
+        self.country_code.as_deref()
+    }
+
+    /// Forget cached groups of account, after daemon reported a change to one of them.
+    pub fn invalidate_groups(&self, account: Option<&str>) {
+        self.groups.invalidate(account);
+    }
+
+    /// Serve listings from cache, discarding it whenever what they list is changed through daemon.
+    async fn request_listed<R: DeserializeOwned>(
+        &self,
+        listings: &Listings,
+        method: &str,
+        params: Raw,
+    ) -> Result<R, ErrorRpc> {
+        use serde_json::Value;
+
+        let account = params
+            .value()
+            .and_then(|p| p.get("account")?.as_str().map(str::to_owned));
+
+        let account = account.as_deref();
+
+        if !method.starts_with("list") {
+            let resp = self.request_retried(method, params).await;
+
+            // Changes may apply even if request failed, such as when it timed out
+            listings.invalidate(account);
+
+            return resp;
+        }
+
+        if let Some(listing) = listings.get(account) {
+            return Ok(serde_json::from_value(listing)?);
+        }
+
+        let listing: Value = self.request_retried(method, params).await?;
+
+        listings.insert(account, listing.clone());
+
+        Ok(serde_json::from_value(listing)?)
+    }
+
+    /// Send request, trying again while daemon is rate limited and budget allows waiting.
+    async fn request_retried<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Raw,
+    ) -> Result<R, ErrorRpc> {
+        use tokio::time::Instant;
+
+        let deadline = Instant::now() + self.rate_limit_budget;
+
+        let mut backoff = BACKOFF_MIN;
+
+        loop {
+            let error = match self.connected().await.request(method, params.clone()).await {
+                Ok(resp) => return Ok(resp),
+                Err(error) => error,
+            };
+
+            let Some(limited) = RateLimited::of(&error) else {
+                // Requests daemon turned down are up to callers, others to operators
+                if !matches!(error, ErrorRpc::Call(_)) {
+                    tracing::error!("Request {method} to daemon failed: {error}");
+                }
+
+                return Err(error);
+            };
+
+            // Wait as long as daemon asks to, giving up early if that exceeds budget
+            let delay = limited.retry_after.unwrap_or_default().max(backoff);
+
+            if Instant::now() + delay > deadline {
+                return Err(error);
+            }
+
+            tracing::warn!("Rate limited on {method}, retrying in {}s", delay.as_secs());
+
+            tokio::time::sleep(delay).await;
+
+            backoff = (backoff * 2).min(BACKOFF_MAX);
+        }
+    }
+}
+
+/// Parameters serialized already, to be looked into or sent several times.
+#[derive(Clone)]
+struct Raw(Option<Box<serde_json::value::RawValue>>);
+
+impl Raw {
+    fn value(&self) -> Option<serde_json::Value> {
+        serde_json::from_str(self.0.as_deref()?.get()).ok()
+    }
+}
+
+impl ToRpcParams for Raw {
+    fn to_rpc_params(self) -> Result<Option<Box<serde_json::value::RawValue>>, serde_json::Error> {
+        Ok(self.0)
+    }
+}
+
+/// Parameters of sends for each numbered part of message longer than `max` characters, cut at
+/// word boundaries where possible, attachments going with first part and text styles with parts
+/// they cover; `None` if message fits.
+fn split(params: &Raw, max: usize) -> Option<Vec<Raw>> {
+    use serde_json::Value;
+
+    let mut params = params.value()?;
+
+    let message = params.get("message")?.as_str()?.to_owned();
+
+    let styles: Vec<String> = params
+        .get("textStyle")
+        .and_then(|s| serde_json::from_value(s.clone()).ok())
+        .unwrap_or_default();
+
+    if message.chars().count() <= max {
+        return None;
+    }
+
+    // Make room for numbering, growing it until it fits number of parts
+    let mut digits = 1;
+
+    let chunks = loop {
+        let chunks = chunks(&message, max.saturating_sub(4 + 2 * digits).max(1));
+
+        if chunks.len().to_string().len() <= digits {
+            break chunks;
+        }
+
+        digits += 1;
+    };
+
+    let count = chunks.len();
+
+    let mut parts = Vec::with_capacity(count);
+
+    for (i, (offset, chunk)) in chunks.into_iter().enumerate() {
+        let prefix = format!("({}/{count}) ", i + 1);
+
+        // Styles are ranges of UTF-16 code units, moved along with text they cover
+        let start = message[..offset].encode_utf16().count();
+        let end = start + chunk.encode_utf16().count();
+
+        let shifted: Vec<_> = styles
+            .iter()
+            .filter_map(|style| {
+                let mut fields = style.splitn(3, ':');
+
+                let from: usize = fields.next()?.parse().ok()?;
+                let to = from + fields.next()?.parse::<usize>().ok()?;
+
+                let (from, to) = (from.max(start), to.min(end));
+
+                (from < to).then(|| {
+                    let kind = fields.next().unwrap_or_default();
+
+                    format!("{}:{}:{kind}", from - start + prefix.len(), to - from)
+                })
+            })
+            .collect();
+
+        params["message"] = Value::from(prefix + chunk);
+        params["textStyle"] = Value::from(shifted);
+
+        parts.push(Raw(Some(serde_json::value::to_raw_value(&params).ok()?)));
+
+        params["attachments"] = Value::Array(Vec::new());
+    }
+
+    Some(parts)
+}
+
+/// Cut text in chunks of at most `budget` characters, at last whitespace within that many if any,
+/// along with byte offsets they start at.
+fn chunks(text: &str, budget: usize) -> Vec<(usize, &str)> {
+    let mut chunks = Vec::new();
+
+    let mut rest = text.trim_start();
+
+    while !rest.trim_end().is_empty() {
+        let offset = text.len() - rest.len();
+
+        // Byte offset past `budget` characters, rest fitting whole if there are not so many
+        let Some((end, _)) = rest.char_indices().nth(budget) else {
+            chunks.push((offset, rest.trim_end()));
+            break;
+        };
+
+        // Cut mid-word only when a single word does not fit
+        let cut = if rest[end..].starts_with(char::is_whitespace) {
+            end
+        } else {
+            rest[..end]
+                .rfind(char::is_whitespace)
+                .filter(|&i| i > 0)
+                .unwrap_or(end)
+        };
+
+        chunks.push((offset, rest[..cut].trim_end()));
+
+        rest = rest[cut..].trim_start();
+    }
+
+    chunks
+}
+
+/// Refusal of daemon to perform request for exceeding rate limits of Signal servers.
+pub struct RateLimited {
+    /// Delay before trying again is worth it, if told.
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimited {
+    /// Recognize rate limiting, reported with dedicated error code or as failure of every recipient.
+    pub fn of(error: &ErrorRpc) -> Option<Self> {
+        use serde_json::Value;
+
+        /// Code of errors `signal-cli` replies with when rate limited.
+        const RATE_LIMIT_CODE: i32 = -5;
+
+        /// Delivery failures resolved by waiting, or by solving a challenge meanwhile.
+        const LIMITS: [&str; 2] = ["RATE_LIMIT_FAILURE", "PROOF_REQUIRED_FAILURE"];
+
+        let ErrorRpc::Call(error) = error else {
+            return None;
+        };
+
+        let data: Value = error
+            .data()
+            .and_then(|data| serde_json::from_str(data.get()).ok())
+            .unwrap_or_default();
+
+        let results = data
+            .pointer("/response/results")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let is_limit = |r: &Value| {
+            r.get("type")
+                .and_then(Value::as_str)
+                .is_some_and(|t| LIMITS.contains(&t))
+        };
+
+        let limited = error.code() == RATE_LIMIT_CODE
+            || (!results.is_empty() && results.iter().all(is_limit));
+
+        if !limited {
+            return None;
+        }
+
+        let retry_after = results
+            .iter()
+            .chain([&data])
+            .filter_map(|r| r.get("retryAfterSeconds")?.as_u64())
+            .max();
+
+        Some(Self {
+            retry_after: retry_after.map(Duration::from_secs),
+        })
+    }
+}
+
+/// Log request instead of sending it, replying as daemon does when it succeeds for all recipients.
+fn dry_run<R: DeserializeOwned>(
+    traffic: Traffic,
+    method: &str,
+    params: impl ToRpcParams,
+) -> Result<R, ErrorRpc> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use serde_json::{Value, json};
+
+    let params: Value = match params.to_rpc_params()? {
+        Some(params) => serde_json::from_str(params.get())?,
+        None => Value::Null,
+    };
+
+    let recipients = match params.get("recipient") {
+        Some(Value::String(recipient)) => vec![recipient.as_str()],
+        Some(Value::Array(recipients)) => recipients.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+
+    let results: Vec<_> = recipients
+        .into_iter()
+        .map(|r| json!({ "recipientAddress": { "number": r }, "type": "SUCCESS" }))
+        .collect();
+
+    tracing::info!(
+        "Dry run, not sending {method}: {}",
+        traffic::show(traffic, params)
+    );
+
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let timestamp = u64::try_from(elapsed.as_millis()).unwrap_or_default();
+
+    Ok(serde_json::from_value(
+        json!({ "timestamp": timestamp, "results": results }),
+    )?)
+}
@@ -0,0 +1,789 @@
+#![expect(clippy::useless_let_if_seq)]
+
+use poem_openapi::payload::Json;
+use poem_openapi::{Enum, Object, Webhook};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Requests sent to `--webhook` endpoints, documented alongside the API.
+#[Webhook]
+pub trait Outgoing {
+    /// Incoming event, forwarded as received from daemon, with its name, account and timestamp
+    /// in `X-Signal-Event`, `X-Signal-Account` and `X-Signal-Timestamp` headers.
+    #[oai(name = "event", method = "post")]
+    fn event(&self, event: Json<Event>);
+}
+
+/// Notification of `receive` subscription.
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    /// Account event is addressed to, absent from single-account daemons.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub envelope: Option<Envelope>,
+
+    /// Failure of daemon to process envelope, such as decrypting it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ReceiveError>,
+
+    /// Kind of event made up from what daemon reports, absent from those forwarded as received.
+    #[oai(rename = "type")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Kind>,
+
+    /// Contact whose identity key changed, in `identity_changed` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<IdentityChange>,
+
+    /// Member requesting to join group through its invite link, in `join_request` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<JoinRequest>,
+
+    /// Call offered or hung up, in `call` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub call: Option<Call>,
+
+    /// Messages contact received, read or viewed, in `receipt` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<Receipt>,
+
+    /// Contact started or stopped typing, in `typing` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typing: Option<TypingIndicator>,
+
+    /// Fields not modeled yet, kept to forward events wholesale.
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// Kind of event made up from what daemon reports.
+#[derive(Enum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[oai(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// Identity key of a contact changed, after reinstalling Signal or because messages are
+    /// being intercepted.
+    IdentityChanged,
+
+    /// Someone requested to join a group through its invite link, waiting for an admin to
+    /// approve or deny them.
+    JoinRequest,
+
+    /// Contact offered a call or hung up, envelope daemon reported it in kept alongside.
+    Call,
+
+    /// Contact received, read or viewed messages, envelope daemon reported it in kept alongside.
+    Receipt,
+
+    /// Contact started or stopped typing, envelope daemon reported it in kept alongside.
+    Typing,
+}
+
+/// Contact whose identity key changed, and how it was noticed.
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityChange {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+
+    /// Safety number of new identity key, to verify with contact out of band.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safety_number: Option<String>,
+
+    /// Either `receive` or `send`, for messages of contact failing to decrypt or to be sent.
+    pub noticed: Noticed,
+}
+
+/// Member requesting to join group, approved or denied with
+/// `POST /groups/{id}/requests/{number}/approve` or `/deny`.
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct JoinRequest {
+    pub group_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+}
+
+/// Call offered or hung up by contact.
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct Call {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+
+    /// Identifier of call, shared by its offer and hangup, negative as often as not.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+
+    /// Either `offer` or `hangup`, calls hung up without being answered having been missed.
+    pub action: CallAction,
+
+    /// Either `AUDIO_CALL` or `VIDEO_CALL` for offers, `NORMAL`, `ACCEPTED`, `DECLINED`,
+    /// `BUSY` or `NEED_PERMISSION` for hangups.
+    #[oai(rename = "type")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+/// Step of call event reports.
+#[derive(Enum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[oai(rename_all(lowercase))]
+#[serde(rename_all = "lowercase")]
+pub enum CallAction {
+    Offer,
+    Hangup,
+}
+
+/// Messages contact received, read or viewed.
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+
+    /// Either `delivery`, `read` or `viewed`.
+    #[oai(rename = "type")]
+    #[serde(rename = "type")]
+    pub kind: ReceiptType,
+
+    /// Milliseconds since Unix epoch messages were received, read or viewed at.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub when: Option<u64>,
+
+    /// Timestamps of messages receipt is about, as returned when sending them.
+    pub timestamps: Vec<u64>,
+}
+
+/// What receipt tells of messages.
+#[derive(Enum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[oai(rename_all(lowercase))]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiptType {
+    Delivery,
+    Read,
+    Viewed,
+}
+
+/// Contact starting or stopping to type.
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct TypingIndicator {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+
+    /// Either `started` or `stopped`.
+    pub action: TypingAction,
+
+    /// Group contact is typing in, absent when typing to account directly.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+}
+
+/// Whether contact started or stopped typing.
+#[derive(Enum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[oai(rename_all(lowercase))]
+#[serde(rename_all = "lowercase")]
+pub enum TypingAction {
+    Started,
+    Stopped,
+}
+
+/// Exchange change of identity key was noticed on.
+#[derive(Enum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[oai(rename_all(lowercase))]
+#[serde(rename_all = "lowercase")]
+pub enum Noticed {
+    Receive,
+    Send,
+}
+
+impl Event {
+    /// Events mirroring message sent with `send` request, shaped like those of linked devices.
+    pub fn sent(params: &Value, result: &Value) -> Vec<Self> {
+        use serde_json::json;
+
+        let field = |value: &Value, key| value.get(key).cloned().unwrap_or_default();
+
+        let (account, timestamp) = (field(params, "account"), field(result, "timestamp"));
+
+        // One event per person, as messages sent from linked devices name a single destination
+        let destinations = match params.get("recipient") {
+            Some(Value::Array(recipients)) => recipients.clone(),
+            Some(recipient @ Value::String(_)) => vec![recipient.clone()],
+            _ => vec![Value::Null],
+        };
+
+        let group = params
+            .get("groupId")
+            .filter(|id| !id.is_null())
+            .map(|id| json!({ "groupId": id, "type": "DELIVER" }));
+
+        let events = destinations.into_iter().map(|destination| {
+            let sent = json!({
+                "destination": destination,
+                "destinationNumber": destination,
+                "timestamp": timestamp,
+                "message": field(params, "message"),
+                "groupInfo": group,
+            });
+
+            let envelope = json!({
+                "source": account,
+                "sourceNumber": account,
+                "timestamp": timestamp,
+                "syncMessage": { "sentMessage": sent },
+            });
+
+            serde_json::from_value(json!({ "account": account, "envelope": envelope }))
+        });
+
+        events.filter_map(Result::ok).collect()
+    }
+
+    /// Event as typed from notification of daemon, or kept as received if it does not fit the
+    /// model, for it to be forwarded regardless.
+    #[must_use]
+    pub fn parse(value: Value) -> Self {
+        let error = match serde_json::from_value(value.clone()) {
+            Ok(event) => return event,
+            Err(error) => error,
+        };
+
+        // Errors of typing quote values, which may be contents of messages
+        tracing::warn!(
+            "Forwarding event as received, failed to type it ({:?})",
+            error.classify()
+        );
+
+        let Value::Object(mut other) = value else {
+            return Self::received(None, Map::new());
+        };
+
+        let account = match other.remove("account") {
+            Some(Value::String(account)) => Some(account),
+            Some(account) => {
+                other.insert(String::from("account"), account);
+                None
+            }
+            None => None,
+        };
+
+        Self::received(account, other)
+    }
+
+    /// Event telling identity key of a contact changed.
+    #[must_use]
+    pub fn identity_changed(account: Option<String>, change: IdentityChange) -> Self {
+        Self {
+            identity: Some(change),
+            ..Self::made_up(account, Kind::IdentityChanged)
+        }
+    }
+
+    /// Event telling someone requested to join a group.
+    #[must_use]
+    pub fn join_request(account: Option<String>, request: JoinRequest) -> Self {
+        Self {
+            request: Some(request),
+            ..Self::made_up(account, Kind::JoinRequest)
+        }
+    }
+
+    /// Event of kind, for account, with fields of none.
+    fn made_up(account: Option<String>, kind: Kind) -> Self {
+        Self {
+            kind: Some(kind),
+            ..Self::received(account, Map::new())
+        }
+    }
+
+    /// Event for account, with fields not modeled only.
+    const fn received(account: Option<String>, other: Map<String, Value>) -> Self {
+        Self {
+            account,
+            envelope: None,
+            error: None,
+            kind: None,
+            identity: None,
+            request: None,
+            call: None,
+            receipt: None,
+            typing: None,
+            other,
+        }
+    }
+
+    /// Change of identity key of sender, if daemon failed to decrypt envelope because of it.
+    #[must_use]
+    pub fn identity_change(&self) -> Option<IdentityChange> {
+        let kind = self.error.as_ref()?.kind.as_deref()?;
+
+        // Daemon names the same failure differently depending on where it is caught
+        if !kind.contains("UntrustedIdentity") {
+            return None;
+        }
+
+        let envelope = self.envelope.as_ref()?;
+
+        Some(IdentityChange {
+            number: envelope.source_number.clone(),
+            uuid: envelope.source_uuid.clone(),
+            safety_number: None,
+            noticed: Noticed::Receive,
+        })
+    }
+
+    /// Type event after what its envelope reports, for calls, receipts and typing indicators
+    /// not to be told apart from messages by receivers themselves.
+    pub fn type_envelope(&mut self) {
+        self.type_call();
+        self.type_receipt();
+        self.type_typing();
+    }
+
+    /// Type event as a `call` one, if envelope offers a call or hangs one up.
+    fn type_call(&mut self) {
+        let Some(envelope) = &self.envelope else {
+            return;
+        };
+
+        let Some(message) = &envelope.call_message else {
+            return;
+        };
+
+        let (action, step) = match (&message.offer_message, &message.hangup_message) {
+            (Some(offer), _) => (CallAction::Offer, offer),
+            (None, Some(hangup)) => (CallAction::Hangup, hangup),
+            (None, None) => return,
+        };
+
+        let call = Call {
+            number: envelope.source_number.clone(),
+            uuid: envelope.source_uuid.clone(),
+            id: step.id,
+            action,
+            kind: step.kind.clone(),
+        };
+
+        self.kind = Some(Kind::Call);
+        self.call = Some(call);
+    }
+
+    /// Type event as a `receipt` one, if envelope tells messages were received, read or viewed.
+    fn type_receipt(&mut self) {
+        let Some(envelope) = &self.envelope else {
+            return;
+        };
+
+        let Some(message) = &envelope.receipt_message else {
+            return;
+        };
+
+        let kind = match (message.is_delivery, message.is_read, message.is_viewed) {
+            (_, _, Some(true)) => ReceiptType::Viewed,
+            (_, Some(true), _) => ReceiptType::Read,
+            (Some(true), _, _) => ReceiptType::Delivery,
+            _ => return,
+        };
+
+        let receipt = Receipt {
+            number: envelope.source_number.clone(),
+            uuid: envelope.source_uuid.clone(),
+            kind,
+            when: message.when,
+            timestamps: message.timestamps.clone(),
+        };
+
+        self.kind = Some(Kind::Receipt);
+        self.receipt = Some(receipt);
+    }
+
+    /// Type event as a `typing` one, if envelope tells contact started or stopped typing.
+    fn type_typing(&mut self) {
+        let Some(envelope) = &self.envelope else {
+            return;
+        };
+
+        let Some(message) = &envelope.typing_message else {
+            return;
+        };
+
+        let action = match message.action.as_deref() {
+            Some("STARTED") => TypingAction::Started,
+            Some("STOPPED") => TypingAction::Stopped,
+            _ => return,
+        };
+
+        let typing = TypingIndicator {
+            number: envelope.source_number.clone(),
+            uuid: envelope.source_uuid.clone(),
+            action,
+            group_id: message.group_id.clone(),
+            timestamp: message.timestamp,
+        };
+
+        self.kind = Some(Kind::Typing);
+        self.typing = Some(typing);
+    }
+
+    /// Name of what event reports, such as `message` or `receipt`, for receivers to route on
+    /// without parsing it.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self.kind {
+            Some(Kind::IdentityChanged) => return "identity_changed",
+            Some(Kind::JoinRequest) => return "join_request",
+            Some(Kind::Call) => return "call",
+            Some(Kind::Receipt) => return "receipt",
+            Some(Kind::Typing) => return "typing",
+            None => (),
+        }
+
+        if self.error.is_some() {
+            return "error";
+        }
+
+        let Some(envelope) = &self.envelope else {
+            return "other";
+        };
+
+        if let Some(message) = &envelope.data_message {
+            if message.reaction.is_some() {
+                "reaction"
+            } else {
+                "message"
+            }
+        } else if envelope.sync_message.is_some() {
+            "sync"
+        } else if envelope.receipt_message.is_some() {
+            "receipt"
+        } else if envelope.typing_message.is_some() {
+            "typing"
+        } else {
+            "other"
+        }
+    }
+
+    /// Whether event reports change to a group, such as its name or members being updated.
+    #[must_use]
+    pub fn changes_group(&self) -> bool {
+        let Some(envelope) = &self.envelope else {
+            return false;
+        };
+
+        let received = envelope
+            .data_message
+            .as_ref()
+            .and_then(|m| m.group_info.as_ref());
+
+        let sent = envelope
+            .sync_message
+            .as_ref()
+            .and_then(|m| m.sent_message.as_ref())
+            .and_then(|m| m.group_info.as_ref());
+
+        received
+            .into_iter()
+            .chain(sent)
+            .any(|g| g.kind.as_deref().is_some_and(|kind| kind != "DELIVER"))
+    }
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+pub struct ReceiveError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// Name of exception daemon failed with, such as `UntrustedIdentityException`.
+    #[oai(rename = "type")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_device: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_received_timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_delivered_timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_message: Option<DataMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_message: Option<SyncMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt_message: Option<ReceiptMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typing_message: Option<TypingMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub call_message: Option<CallMessage>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct DataMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_in_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_once: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reaction: Option<Reaction>,
+    #[oai(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_info: Option<GroupInfo>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct Reaction {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_author_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_author_uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_sent_timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_remove: Option<bool>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    /// Identifier to fetch attachment with, from `/v1/attachments/{id}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct GroupInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<u32>,
+
+    /// Either `DELIVER`, `UPDATE` or `QUIT`.
+    #[oai(rename = "type")]
+    #[serde(rename = "type")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct SyncMessage {
+    /// Message sent from another device of account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sent_message: Option<SentMessage>,
+    #[oai(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub read_messages: Vec<ReadMessage>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct SentMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination_uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_in_seconds: Option<u64>,
+    #[oai(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_info: Option<GroupInfo>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct ReadMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender_uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub when: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_delivery: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_read: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_viewed: Option<bool>,
+
+    /// Timestamps of messages receipt is about.
+    #[oai(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timestamps: Vec<u64>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct TypingMessage {
+    /// Either `STARTED` or `STOPPED`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct CallMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offer_message: Option<CallStep>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hangup_message: Option<CallStep>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// Offer or hangup of call, fields specific to either kept to forward them wholesale.
+#[derive(Object, Deserialize, Serialize, Clone)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct CallStep {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    #[oai(rename = "type")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+
+    #[oai(skip)]
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
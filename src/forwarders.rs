@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use poem_openapi::Object;
+
+/// Delay before restarting task that died, for one dying right away not to spin.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Health of task forwarding incoming messages of an account.
+pub struct Health {
+    account: Option<String>,
+
+    /// Whether subscription to incoming messages is up, assumed so until it fails.
+    subscribed: AtomicBool,
+
+    /// Number of times task died and was restarted.
+    restarts: AtomicU64,
+}
+
+impl Health {
+    pub const fn new(account: Option<String>) -> Self {
+        Self {
+            account,
+            subscribed: AtomicBool::new(true),
+            restarts: AtomicU64::new(0),
+        }
+    }
+
+    /// Tell whether subscription is up, as it is established or lost.
+    pub fn set_subscribed(&self, subscribed: bool) {
+        self.subscribed.store(subscribed, Ordering::Relaxed);
+    }
+}
+
+/// Tasks forwarding incoming messages of each account.
+pub struct Forwarders(Vec<Arc<Health>>);
+
+impl Forwarders {
+    pub const fn new(tasks: Vec<Arc<Health>>) -> Self {
+        Self(tasks)
+    }
+
+    /// Whether every task is subscribed to incoming messages.
+    pub fn is_healthy(&self) -> bool {
+        self.0.iter().all(|h| h.subscribed.load(Ordering::Relaxed))
+    }
+
+    /// State of each task, for operators to tell ones that died.
+    pub fn report(&self) -> Vec<Forwarder> {
+        self.0
+            .iter()
+            .map(|h| Forwarder {
+                account: h.account.clone(),
+                subscribed: h.subscribed.load(Ordering::Relaxed),
+                restarts: h.restarts.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+/// State of task forwarding incoming messages of an account.
+#[derive(Object)]
+pub struct Forwarder {
+    /// Account messages are forwarded for, all of them if absent.
+    account: Option<String>,
+
+    /// Whether subscription to incoming messages is up.
+    subscribed: bool,
+
+    /// Number of times task died and was restarted since start.
+    restarts: u64,
+}
+
+/// Run task started by `start`, restarting it whenever it ends or panics, reported unsubscribed
+/// until it subscribes again.
+pub async fn supervise<F, T>(health: Arc<Health>, mut start: F)
+where
+    F: FnMut() -> T,
+    T: Future<Output = ()> + Send + 'static,
+{
+    loop {
+        let outcome = tokio::spawn(start()).await;
+
+        health.set_subscribed(false);
+
+        let restarts = health.restarts.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if let Err(error) = outcome {
+            tracing::error!("Forwarding of incoming messages died, restart {restarts}: {error}");
+        } else {
+            tracing::error!("Forwarding of incoming messages stopped, restart {restarts}");
+        }
+
+        tokio::time::sleep(RESTART_DELAY).await;
+    }
+}
@@ -0,0 +1,39 @@
+use image::ImageResult;
+
+/// Bounds sent images are downscaled to fit in.
+#[derive(Clone, Copy)]
+pub struct Limits {
+    /// Largest width and height, in pixels.
+    pub max_dimension: u32,
+
+    /// Quality of JPEG downscaled images are re-encoded as, from 1 to 100.
+    pub quality: u8,
+}
+
+/// Whether images of content type can be downscaled.
+pub fn supported(mime_type: &str) -> bool {
+    matches!(mime_type, "image/jpeg" | "image/png")
+}
+
+/// Downscale image to fit limits, keeping its proportions, `None` if it fits already.
+pub fn downscale(bytes: &[u8], limits: Limits) -> ImageResult<Option<Vec<u8>>> {
+    use image::codecs::jpeg::JpegEncoder;
+    use image::imageops::FilterType;
+
+    let image = image::load_from_memory(bytes)?;
+
+    let max = limits.max_dimension;
+
+    if image.width() <= max && image.height() <= max {
+        return Ok(None);
+    }
+
+    // JPEG has no transparency, drop it rather than failing
+    let image = image.resize(max, max, FilterType::Lanczos3).into_rgb8();
+
+    let mut jpeg = Vec::new();
+
+    JpegEncoder::new_with_quality(&mut jpeg, limits.quality).encode_image(&image)?;
+
+    Ok(Some(jpeg))
+}
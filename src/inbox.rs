@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+use super::events::Event;
+
+/// Bounded buffer of incoming events, drained by pull-based consumers.
+pub struct Inbox {
+    capacity: usize,
+    events: Mutex<VecDeque<Event>>,
+    notify: Notify,
+}
+
+impl Inbox {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Store event, evicting the oldest one when buffer is full.
+    pub fn push(&self, event: Event) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        {
+            let mut events = self.events.lock().unwrap_or_else(PoisonError::into_inner);
+
+            if events.len() == self.capacity {
+                events.pop_front();
+            }
+
+            events.push_back(event);
+        }
+
+        self.notify.notify_waiters();
+    }
+
+    /// Take up to `max` events addressed to `account`, waiting up to `timeout` for the first one.
+    pub async fn drain(&self, account: &str, max: usize, timeout: Duration) -> Vec<Event> {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            // Register interest before checking buffer, to avoid missing a concurrent push
+            let notified = self.notify.notified();
+
+            let events = self.take(account, max);
+
+            if !events.is_empty() {
+                return events;
+            }
+
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return Vec::new();
+            }
+        }
+    }
+
+    fn take(&self, account: &str, max: usize) -> Vec<Event> {
+        let mut events = self.events.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut taken = Vec::new();
+        let mut kept = VecDeque::with_capacity(events.len());
+
+        for event in events.drain(..) {
+            if taken.len() < max && is_addressed_to(&event, account) {
+                taken.push(event);
+            } else {
+                kept.push_back(event);
+            }
+        }
+
+        *events = kept;
+
+        taken
+    }
+}
+
+/// Events without an explicit account come from a single-account daemon, and match any account.
+fn is_addressed_to(event: &Event, account: &str) -> bool {
+    event.account.as_deref().is_none_or(|a| a == account)
+}
@@ -0,0 +1,91 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+use super::client::SignalClient;
+use super::daemon::Daemon;
+use super::events::{Event, JoinRequest};
+
+/// Members requesting to join groups through their invite link, as last listed, by group.
+#[derive(Default)]
+pub struct JoinRequests(HashMap<String, HashSet<String>>);
+
+impl JoinRequests {
+    /// Events of members newly requesting to join group whose change event reports, listing
+    /// group again to tell who.
+    pub async fn check(&mut self, signal: &Daemon, event: &Event) -> Vec<Event> {
+        let Some(group) = event
+            .envelope
+            .as_ref()
+            .and_then(|e| e.data_message.as_ref())
+            .and_then(|m| m.group_info.as_ref())
+        else {
+            return Vec::new();
+        };
+
+        let Some(id) = group.group_id.clone() else {
+            return Vec::new();
+        };
+
+        let groups = match signal.list_groups(event.account.as_deref()).await {
+            Ok(groups) => groups,
+            Err(error) => {
+                tracing::warn!("Failed to list requests to join group: {error}");
+                return Vec::new();
+            }
+        };
+
+        let listed = groups
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|g| g.get("id").and_then(Value::as_str) == Some(&id));
+
+        let requesting = listed
+            .and_then(|g| g.get("requestingMembers"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let name = listed
+            .and_then(|g| g.get("name"))
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .or_else(|| group.group_name.clone());
+
+        let known = self.0.remove(&id).unwrap_or_default();
+
+        let mut current = HashSet::new();
+        let mut events = Vec::new();
+
+        for member in requesting {
+            let field = |key| member.get(key).and_then(Value::as_str).map(str::to_owned);
+
+            let (number, uuid) = (field("number"), field("uuid"));
+
+            let Some(key) = number.clone().or_else(|| uuid.clone()) else {
+                continue;
+            };
+
+            if !known.contains(&key) {
+                let request = JoinRequest {
+                    group_id: id.clone(),
+                    group_name: name.clone(),
+                    number,
+                    uuid,
+                };
+
+                events.push(Event::join_request(event.account.clone(), request));
+            }
+
+            current.insert(key);
+        }
+
+        // Forget groups once their requests are handled, for memory to stay bounded
+        if !current.is_empty() {
+            self.0.insert(id, current);
+        }
+
+        events
+    }
+}
@@ -0,0 +1,3663 @@
+//! Bidirectional HTTP bridge to `signal-cli` daemon, also usable as a library.
+//!
+//! Run the whole bridge with [`run`], or embed its API in another `poem` server with [`app`]:
+//!
+//! ```no_run
+//! use poem::listener::TcpListener;
+//! use poem::{Route, Server};
+//! use signal_http::{Args, config};
+//!
+//! #[tokio::main]
+//! async fn main() -> color_eyre::eyre::Result<()> {
+//!     let (args, settings): (Args, _) = config::parse()?;
+//!
+//!     let app = Route::new().nest("/signal", signal_http::app(args, settings).await?);
+//!
+//!     Server::new(TcpListener::bind("0.0.0.0:8080")).run(app).await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Talk to daemon directly instead through [`daemon::Daemon`], which implements the typed
+//! requests of [`client::SignalClient`], parsing what it receives with [`events::Event`].
+
+#[cfg(not(any(feature = "native", feature = "compat")))]
+compile_error!("At least one of `native` and `compat` features must be enabled");
+
+mod audit;
+mod callers;
+mod child;
+pub mod client;
+mod clients;
+pub mod codec;
+mod commands;
+#[cfg(feature = "compat")]
+mod compat;
+pub mod config;
+pub mod daemon;
+pub mod events;
+mod forwarders;
+#[cfg(feature = "images")]
+mod images;
+#[cfg(feature = "compat")]
+mod inbox;
+mod joins;
+mod listeners;
+mod listings;
+mod markdown;
+mod mock;
+mod names;
+mod numbers;
+mod outbox;
+mod privacy;
+mod queue;
+mod quiet;
+#[cfg(feature = "auto-replies")]
+mod replies;
+#[cfg(feature = "sentry")]
+mod reports;
+mod router;
+mod sessions;
+mod shedding;
+mod staging;
+#[cfg(unix)]
+mod systemd;
+#[cfg(feature = "templates")]
+mod templates;
+mod throttle;
+#[cfg(feature = "tls")]
+mod tls;
+pub mod transport;
+mod uploads;
+mod vault;
+mod verify;
+
+use core::error::Error;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+use clap::Parser;
+use color_eyre::eyre::Result;
+use poem_openapi::param::Query;
+use poem_openapi::payload::{Binary, Json};
+use poem_openapi::{ApiResponse, Enum, Object};
+
+use self::audit::Audit;
+use self::callers::{Caller, Callers};
+use self::client::SignalClient as Client;
+use self::daemon::{Daemon, RateLimited};
+#[cfg(feature = "compat")]
+use self::inbox::Inbox;
+use self::mock::Mock;
+use self::outbox::Outbox;
+use self::queue::{Overflow, Queue};
+use self::sessions::Sessions;
+use self::staging::Staging;
+use self::transport::traffic::{Recorder, Traffic};
+use self::uploads::Uploads;
+use self::vault::Vault;
+
+#[derive(Parser, Clone)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
+#[expect(clippy::struct_excessive_bools)]
+pub struct Args {
+    #[command(subcommand)]
+    action: Option<Action>,
+
+    /// TOML file of options, keyed by long name; command line and environment take precedence
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// print options in effect and where each of them is set from, then exit
+    #[arg(long)]
+    print_config: bool,
+
+    /// address of `signal-cli` daemon, as `host:port`, `tls://host:port`, `unix:/path/to/socket`, or `http://host:port`;
+    /// repeat or separate with commas to fail over between several daemons, in order
+    #[arg(
+        long,
+        required_unless_present_any = ["spawn_daemon", "mock_daemon", "replay"],
+        value_delimiter = ','
+    )]
+    daemon: Vec<String>,
+
+    /// command launching `signal-cli` daemon, restarted on exit; speaks over stdio without `--daemon`
+    #[arg(long)]
+    spawn_daemon: Option<String>,
+
+    /// serve requests from in-process mock instead of daemon, for clients to test against;
+    /// inject incoming messages with `POST /admin/mock/receive`, as administrator
+    #[arg(long)]
+    mock_daemon: bool,
+
+    /// JSON file mapping method names to `{"result": ...}` or `{"error": ...}`, replied by mock
+    /// instead of built-in results
+    #[arg(long, requires = "mock_daemon")]
+    mock_script: Option<PathBuf>,
+
+    /// directory to append JSON-RPC messages exchanged with daemon to, for `--replay` to serve;
+    /// recordings hold message bodies and phone numbers, as they are not redacted
+    #[arg(long, value_name = "DIR")]
+    record: Option<PathBuf>,
+
+    /// directory recorded with `--record`, whose responses and incoming messages are served by
+    /// mock instead of daemon, in recorded order; scripted replies of `--mock-script` take precedence
+    #[arg(long, value_name = "DIR", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+
+    /// seconds to keep retrying initial connection to daemon for, before giving up
+    #[arg(long, default_value = "0")]
+    wait_for_daemon: u64,
+
+    /// seconds requests wait for lost daemon connection to be re-established, before replying with 503
+    #[arg(long, default_value = "0")]
+    grace_period: u64,
+
+    /// seconds between health checks of daemon connection, 0 to disable
+    #[arg(long, default_value = "30")]
+    ping_interval: u64,
+
+    /// seconds to wait for daemon to answer each request, before replying with 504
+    #[arg(long, default_value = "60")]
+    request_timeout: u64,
+
+    /// seconds group and contact listings are cached for, dropped earlier when they change; 0 disables
+    #[arg(long, default_value = "300")]
+    group_cache_ttl: u64,
+
+    /// seconds to keep retrying requests daemon is rate limited on, before replying with 429
+    #[arg(long, default_value = "30")]
+    rate_limit_budget: u64,
+
+    /// messages sent per minute from each account at most, queued beyond; 0 disables
+    #[arg(long, default_value = "0")]
+    send_rate: usize,
+
+    /// country calling code of national numbers of recipients, as `49`, for them to be sent to
+    /// rather than rejected
+    #[arg(long, value_name = "CODE", value_parser = numbers::parse_country_code)]
+    default_country_code: Option<String>,
+
+    /// characters messages are split beyond, in numbered parts sent in turn and cut at word
+    /// boundaries; replies tell outcome of first part, or how many were delivered before one
+    /// failed
+    #[arg(long, value_name = "CHARS")]
+    split_messages: Option<usize>,
+
+    /// maximum size in bytes of each message from daemon
+    #[arg(long, default_value = "16777216")]
+    max_frame_length: usize,
+
+    /// most verbose level of logs to print: `error`, `warn`, `info`, `debug` or `trace`
+    #[arg(long, default_value = "info")]
+    log_level: tracing::Level,
+
+    /// format of printed logs
+    #[arg(long, value_enum, default_value = "full")]
+    log_format: LogFormat,
+
+    /// file to also write logs to, suffixed with date of each period it covers
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// period after which a new log file is started
+    #[arg(long, value_enum, default_value = "daily")]
+    log_rotation: LogRotation,
+
+    /// number of log files to keep, deleting older ones; 0 to keep all of them
+    #[arg(long, default_value = "7")]
+    log_files: usize,
+
+    /// Sentry DSN to report panics, failed requests to daemon and undelivered events to, with
+    /// phone numbers scrubbed out of them
+    #[cfg(feature = "sentry")]
+    #[arg(long, value_name = "DSN")]
+    sentry_dsn: Option<sentry::types::Dsn>,
+
+    /// log JSON-RPC messages exchanged with daemon, with bodies and phone numbers redacted
+    #[arg(long, value_name = "MODE", num_args = 0..=1, default_missing_value = "redacted")]
+    log_rpc: Option<Traffic>,
+
+    /// keep message bodies, attachments, phone numbers, account identifiers and usernames out of
+    /// logs and error responses, identifiers showing as hashes; overrides `--log-rpc verbatim`
+    #[arg(long)]
+    privacy: bool,
+
+    /// log messages and events instead of sending them, replying as if they were delivered;
+    /// bodies and phone numbers are redacted unless `--log-rpc verbatim` is set
+    #[arg(long)]
+    dry_run: bool,
+
+    /// PEM bundle of certificate authorities trusted for TLS daemon, instead of system ones
+    #[cfg(feature = "tls")]
+    #[arg(long)]
+    daemon_ca: Option<PathBuf>,
+
+    /// PEM certificate chain authenticating this bridge to TLS daemon
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "daemon_key")]
+    daemon_cert: Option<PathBuf>,
+
+    /// PEM private key of `--daemon-cert`
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "daemon_cert")]
+    daemon_key: Option<PathBuf>,
+
+    /// endpoint to forward messages to, optional if each account has one of its own
+    #[arg(long, required_unless_present = "account_webhook")]
+    webhook: Option<String>,
+
+    /// account to receive messages of, repeat to serve several; all accounts of daemon by default
+    #[arg(long)]
+    account: Vec<String>,
+
+    /// endpoint to forward messages of a given account to instead, as `number=url`; repeatable
+    #[arg(long, value_parser = parse_account_webhook)]
+    account_webhook: Vec<(String, String)>,
+
+    /// proxy to post to webhooks through, as `http://`, `https://` or `socks5://` URL; those of
+    /// `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY` are used otherwise
+    #[arg(long, value_name = "URL")]
+    webhook_proxy: Option<String>,
+
+    /// `User-Agent` header of requests to webhooks
+    #[arg(long, value_name = "AGENT", default_value = USER_AGENT)]
+    webhook_user_agent: String,
+
+    /// chat command to dispatch, as `name=url` or `name=exec:program`; repeatable
+    #[arg(long, value_name = "NAME=TARGET", value_parser = router::parse_command)]
+    chat_command: Vec<(String, router::Handler)>,
+
+    /// characters incoming messages start with to invoke chat commands
+    #[arg(long, default_value = "!")]
+    chat_command_prefix: String,
+
+    /// TOML file of `[[rule]]` tables replying to matching incoming messages
+    #[cfg(feature = "auto-replies")]
+    #[arg(long, value_name = "PATH")]
+    auto_replies: Option<PathBuf>,
+
+    /// number of incoming messages held while webhook is slower than they arrive, per account
+    #[arg(long, default_value = "1024")]
+    queue_capacity: usize,
+
+    /// what to do with incoming messages once queue to webhook is full
+    #[arg(long, value_enum, default_value = "block")]
+    overflow: Overflow,
+
+    /// directory to write incoming messages to once queue is full, with `--overflow spill`
+    #[arg(long, required_if_eq("overflow", "spill"))]
+    spill_dir: Option<PathBuf>,
+
+    /// post a challenge to webhooks on start and reload, refusing those not echoing it back
+    #[arg(long)]
+    verify_webhooks: bool,
+
+    /// forward messages sent through API to webhook too, like those sent from linked devices
+    #[arg(long)]
+    mirror_sent: bool,
+
+    /// send a note to self from each account once connected to daemon, failing to start unless
+    /// it is delivered
+    #[arg(long)]
+    self_test: bool,
+
+    /// number to message from account whose contact changed identity key, alongside the
+    /// `identity_changed` event posted to webhook
+    #[arg(long, value_name = "NUMBER")]
+    identity_alert: Option<String>,
+
+    /// directory to persist messages sent with `?queued=true` to, until they are delivered
+    #[arg(long)]
+    outbox: Option<PathBuf>,
+
+    /// hours of day in UTC, as `HH:MM-HH:MM`, messages sent during are held in outbox until
+    #[arg(long, requires = "outbox", value_parser = quiet::parse_window)]
+    quiet_hours: Option<quiet::Window>,
+
+    /// quiet hours of a given recipient instead, as `recipient=HH:MM-HH:MM`; repeatable
+    #[arg(
+        long,
+        value_name = "RECIPIENT=WINDOW",
+        requires = "outbox",
+        value_parser = quiet::parse_recipient_window
+    )]
+    recipient_quiet_hours: Vec<(String, quiet::Window)>,
+
+    /// directory shared with daemon, at the same absolute path, to decode sent attachments to
+    #[arg(long)]
+    staging_dir: Option<PathBuf>,
+
+    /// number of distinct attachments kept decoded in staging directory, by hash of their
+    /// content, for sends of the same ones not to decode and write them again
+    #[arg(long, value_name = "COUNT", requires = "staging_dir")]
+    attachment_cache: Option<usize>,
+
+    /// largest width and height of sent JPEG and PNG images, in pixels, downscaling bigger ones
+    /// and re-encoding them as JPEG
+    #[cfg(feature = "images")]
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u32).range(1..))]
+    image_max_dimension: Option<u32>,
+
+    /// quality of downscaled images, from 1 to 100
+    #[cfg(feature = "images")]
+    #[arg(long, default_value = "85", value_parser = clap::value_parser!(u8).range(1..=100))]
+    image_quality: u8,
+
+    /// token integrations authenticate with, as `Authorization: Bearer TOKEN`, given as
+    /// `name=token`; repeatable, API being open to anyone if none is set
+    #[arg(long, value_name = "NAME=TOKEN", value_parser = callers::parse_token)]
+    api_token: Vec<(String, String)>,
+
+    /// most messages token of `--api-token` can send per hour or day, as `name=count/hour` or
+    /// `name=count/day`; repeatable
+    #[arg(long, value_name = "NAME=QUOTA", value_parser = callers::parse_quota)]
+    api_quota: Vec<(String, (u64, callers::Period))>,
+
+    /// recipient token of `--api-token` is restricted to, as `name=number` or `name=group.ID`;
+    /// repeatable, tokens without any reaching everyone; tokens with recipients or quotas may
+    /// only send, react, and send receipts and typing indicators
+    #[arg(long, value_name = "NAME=RECIPIENT", value_parser = callers::parse_recipient)]
+    api_recipient: Vec<(String, String)>,
+
+    /// token `/admin` endpoints are reserved to, as `Authorization: Bearer TOKEN`; tokens of
+    /// `--api-token` without quotas nor recipients are accepted if unset, and nobody if those
+    /// are unset too
+    #[arg(long, value_name = "TOKEN")]
+    admin_token: Option<String>,
+
+    /// file to append a record of every send, reaction and receipt to, listed with
+    /// `GET /admin/audit`
+    #[arg(long, value_name = "FILE")]
+    audit_log: Option<PathBuf>,
+
+    /// seconds attachments stored with `POST /attachments` can be sent for; 0 disables uploads
+    #[arg(long, default_value = "3600")]
+    upload_ttl: u64,
+
+    /// directory to persist state of conversations kept with `/conversations/{id}/state` to
+    #[arg(long)]
+    state_dir: Option<PathBuf>,
+
+    /// base64 of 32-byte key encrypting queued messages, spilled ones and conversation state on
+    /// disk, as from `openssl rand -base64 32`
+    #[cfg(feature = "encryption")]
+    #[arg(long, conflicts_with = "storage_secret_file")]
+    storage_secret: Option<String>,
+
+    /// file holding `--storage-secret` instead, for it not to show in process list
+    #[cfg(feature = "encryption")]
+    #[arg(long)]
+    storage_secret_file: Option<PathBuf>,
+
+    /// Handlebars template sent by `POST /send/template/{name}`, as `name=template`; repeatable
+    #[cfg(feature = "templates")]
+    #[arg(long, value_name = "NAME=TEMPLATE", value_parser = templates::parse_template)]
+    template: Vec<(String, String)>,
+
+    /// external URL service can be accessed from
+    #[arg(long, default_value = "http://localhost")]
+    url: String,
+
+    /// path prefix to serve every route under, documentation included, as `/signal`
+    #[arg(long, value_name = "PATH", value_parser = parse_base_path)]
+    base_path: Option<String>,
+
+    /// address of reverse proxy trusted to tell address of clients, in `Forwarded` or
+    /// `X-Forwarded-For` headers, for logs to name them; repeatable
+    #[arg(long, value_name = "IP")]
+    trust_proxy: Vec<std::net::IpAddr>,
+
+    /// host to bind HTTP server to
+    #[arg(long, default_value = "0.0.0.0")]
+    host: String,
+
+    /// port to bind HTTP server to
+    #[arg(long, default_value = "80")]
+    port: u16,
+
+    /// address to serve on instead of `--host` and `--port`, as `host:port`, or as
+    /// `host:port=/send,/ready` to serve paths starting with those prefixes only; repeatable
+    #[arg(long, value_name = "ADDR[=PREFIXES]", value_parser = listeners::parse)]
+    listen: Vec<listeners::Listener>,
+
+    /// most requests handled at once, further ones being answered `503` until some complete;
+    /// `/ready` is answered regardless
+    #[arg(long, value_name = "COUNT")]
+    max_in_flight: Option<usize>,
+
+    /// most requests handled at once for paths starting with prefix, as `/send=4`; repeatable,
+    /// first matching prefix applying
+    #[arg(long, value_name = "PREFIX=COUNT", value_parser = shedding::parse_route_limit)]
+    route_limit: Vec<(String, usize)>,
+
+    /// domain to obtain certificate for from ACME directory, serving HTTPS on `--acme-listen`
+    /// with it, renewed before expiry; repeatable
+    #[cfg(feature = "acme")]
+    #[arg(long, requires = "acme_email")]
+    acme_domain: Vec<String>,
+
+    /// contact address registered with ACME directory, for it to warn of certificate issues
+    #[cfg(feature = "acme")]
+    #[arg(long, requires = "acme_domain")]
+    acme_email: Option<String>,
+
+    /// address to serve HTTPS on, port `443` being where ACME directory checks domain control
+    #[cfg(feature = "acme")]
+    #[arg(long, default_value = "0.0.0.0:443", value_name = "ADDR[=PREFIXES]", value_parser = listeners::parse)]
+    acme_listen: listeners::Listener,
+
+    /// directory keeping key and certificate across restarts, for them not to be requested anew
+    #[cfg(feature = "acme")]
+    #[arg(long)]
+    acme_cache: Option<PathBuf>,
+
+    /// URL of ACME directory, as Let's Encrypt staging one to try setup without rate limits
+    #[cfg(feature = "acme")]
+    #[arg(long, default_value = poem::listener::acme::LETS_ENCRYPT_PRODUCTION)]
+    acme_directory: String,
+
+    /// expose native API
+    #[cfg(feature = "native")]
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    native: bool,
+
+    /// expose API compatible with `bbernhard/signal-cli-rest-api`
+    #[cfg(feature = "compat")]
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    compat: bool,
+
+    /// number of received messages kept for pull-based consumers
+    #[cfg(feature = "compat")]
+    #[arg(long, default_value = "1024")]
+    receive_buffer: usize,
+
+    /// attachments directory of `signal-cli` data, to list and delete attachments
+    #[cfg(feature = "compat")]
+    #[arg(long)]
+    attachments: Option<PathBuf>,
+}
+
+/// Tasks run instead of serving HTTP requests.
+#[derive(clap::Subcommand, Clone)]
+enum Action {
+    /// print completion script for shell
+    Completions {
+        /// shell to complete commands of
+        shell: clap_complete::Shell,
+    },
+
+    /// print man page, in `roff` format
+    Man,
+
+    /// send a message through daemon, then exit
+    Send {
+        /// number to send message to
+        #[arg(long, required_unless_present = "group")]
+        to: Option<String>,
+
+        /// base64 identifier of group to send message to instead
+        #[arg(long, conflicts_with = "to")]
+        group: Option<String>,
+
+        /// text of message
+        #[arg(long)]
+        message: String,
+
+        /// account to send message from, when daemon serves several
+        #[arg(long)]
+        account: Option<String>,
+    },
+
+    /// check connectivity to each daemon, asking for its version, then exit
+    Check {
+        /// also post a ping payload to webhooks, expecting a successful status
+        #[arg(long)]
+        webhooks: bool,
+    },
+
+    /// print `OpenAPI` document of exposed APIs, as JSON, without serving them
+    Openapi,
+}
+
+/// Layouts of printed logs.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum LogFormat {
+    /// One line per event, with all its fields
+    Full,
+
+    /// One shorter line per event
+    Compact,
+
+    /// Several lines per event, for humans to read
+    Pretty,
+
+    /// One JSON object per line, for log aggregators to ingest
+    Json,
+}
+
+/// Periods after which a new log file is started.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+
+    /// Keep writing to a single file, without date suffix
+    Never,
+}
+
+impl LogRotation {
+    const fn rotation(self) -> tracing_appender::rolling::Rotation {
+        use tracing_appender::rolling::Rotation;
+
+        match self {
+            Self::Minutely => Rotation::MINUTELY,
+            Self::Hourly => Rotation::HOURLY,
+            Self::Daily => Rotation::DAILY,
+            Self::Weekly => Rotation::WEEKLY,
+            Self::Never => Rotation::NEVER,
+        }
+    }
+}
+
+/// Run bridge as a process would, with options parsed by [`config::parse`].
+///
+/// Prints configuration or performs subcommand if requested, serves otherwise, on a runtime of
+/// its own, logging to console and optionally to a file.
+///
+/// # Errors
+///
+/// Fails if logs cannot be set up, or if [`serve`] fails.
+pub fn run(args: Args, settings: Vec<config::Setting>) -> Result<()> {
+    if args.print_config {
+        commands::print_config(&settings);
+
+        return Ok(());
+    }
+
+    // Keep sending reports until exit, panics included
+    #[cfg(feature = "sentry")]
+    let _reports = args.sentry_dsn.clone().map(reports::init);
+
+    // Keep flushing logs to file until exit
+    let _guard = logs(&args)?;
+
+    // Create async runtime
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(serve(args, settings))
+}
+
+/// Initialize logs and traces consumers, to console and optionally to a rotated file.
+fn logs(args: &Args) -> Result<Option<tracing_appender::non_blocking::WorkerGuard>> {
+    use std::io::IsTerminal;
+
+    use tracing::Level;
+    use tracing::level_filters::LevelFilter;
+    use tracing_appender::rolling::RollingFileAppender;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    // Be verbose enough to show traffic if requested
+    let level = if args.log_rpc.is_some() {
+        args.log_level.max(Level::DEBUG)
+    } else {
+        args.log_level
+    };
+
+    // Color logs only when read by humans, escape codes would clutter files or aggregators
+    let ansi = std::io::stdout().is_terminal();
+
+    // Scrub phone numbers out of every log, whichever part of bridge writes them
+    let stdout = privacy::Scrubbing::new(std::io::stdout, args.privacy);
+
+    let mut layers = vec![log_layer(args.log_format, stdout, ansi)];
+
+    let guard = match &args.log_file {
+        None => None,
+        Some(path) => {
+            let directory = path.parent().unwrap_or_else(|| Path::new("."));
+
+            let mut builder = RollingFileAppender::builder()
+                .rotation(args.log_rotation.rotation())
+                .filename_prefix(path.file_name().unwrap_or_default().to_string_lossy());
+
+            if args.log_files > 0 {
+                builder = builder.max_log_files(args.log_files);
+            }
+
+            // Write from a dedicated thread, to keep slow disks from stalling requests
+            let (writer, guard) = tracing_appender::non_blocking(builder.build(directory)?);
+
+            let writer = privacy::Scrubbing::new(writer, args.privacy);
+
+            layers.push(log_layer(args.log_format, writer, false));
+
+            Some(guard)
+        }
+    };
+
+    // Report errors, with earlier logs as breadcrumbs
+    #[cfg(feature = "sentry")]
+    if args.sentry_dsn.is_some() {
+        layers.push(Box::new(sentry::integrations::tracing::layer()));
+    }
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(LevelFilter::from_level(level))
+        .init();
+
+    Ok(guard)
+}
+
+/// Format logs as requested, before handing them to `writer`.
+fn log_layer<W>(format: LogFormat, writer: W, ansi: bool) -> LogLayer
+where
+    W: for<'w> tracing_subscriber::fmt::MakeWriter<'w> + 'static + core::marker::Send + Sync,
+{
+    use tracing_subscriber::Layer;
+
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+
+    match format {
+        LogFormat::Full => layer.boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+/// Consumer of logs, among several ones.
+type LogLayer =
+    Box<dyn tracing_subscriber::Layer<tracing_subscriber::Registry> + core::marker::Send + Sync>;
+
+/// Perform subcommand if requested, serve HTTP API on configured address otherwise.
+///
+/// # Errors
+///
+/// Fails if subcommand does, or if [`app`] fails to start, or if address cannot be bound.
+pub async fn serve(mut args: Args, settings: Vec<config::Setting>) -> Result<()> {
+    // Run requested task instead of serving, if any
+    match args.action.take() {
+        None => {}
+        Some(Action::Completions { shell }) => {
+            commands::completions(config::command::<Args>(), shell);
+
+            return Ok(());
+        }
+        Some(Action::Man) => return commands::man(config::command::<Args>()),
+        Some(Action::Send {
+            to,
+            group,
+            message,
+            account,
+        }) => {
+            let signal = daemon(&args, mock(&args)?).await?;
+
+            let (to, group) = (to.as_deref(), group.as_deref());
+
+            return commands::send(&signal, account.as_deref(), to, group, &message).await;
+        }
+        Some(Action::Check { webhooks }) => return commands::check(&args, webhooks).await,
+        Some(Action::Openapi) => {
+            println!("{}", spec(&args)?);
+
+            return Ok(());
+        }
+    }
+
+    let mut listeners = args.listen.clone();
+
+    if listeners.is_empty() {
+        let host = if args.host.contains(':') {
+            format!("[{}]", args.host)
+        } else {
+            args.host.clone()
+        };
+
+        listeners.push(listeners::Listener {
+            addr: format!("{host}:{}", args.port),
+            routes: Vec::new(),
+        });
+    }
+
+    #[cfg(feature = "acme")]
+    let acme = acme(&args)?;
+
+    let (app, reloadable) = build(args, settings).await?;
+
+    // Reload settings on signal of service managers, left to embedders otherwise
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let hangups = signal(SignalKind::hangup())?;
+
+        tokio::spawn(reload_on_hangup(hangups, reloadable));
+    }
+
+    #[cfg(not(unix))]
+    drop(reloadable);
+
+    // Listen to HTTP requests too
+    Ok(listeners::serve(
+        app,
+        listeners,
+        #[cfg(feature = "acme")]
+        acme,
+        NAME,
+    )
+    .await?)
+}
+
+/// HTTPS listener, with certificate obtained and renewed for configured domains, if any.
+#[cfg(feature = "acme")]
+fn acme(args: &Args) -> Result<Option<(listeners::Listener, poem::listener::acme::AutoCert)>> {
+    use poem::listener::acme::{AutoCert, ChallengeType};
+
+    if args.acme_domain.is_empty() {
+        return Ok(None);
+    }
+
+    // Certificate is served with default provider, ambiguous as dependencies compile in several
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    // Prove control of domain on listener itself, without answering plain HTTP challenges
+    let mut builder = AutoCert::builder()
+        .directory_url(&args.acme_directory)
+        .challenge_type(ChallengeType::TlsAlpn01);
+
+    for domain in &args.acme_domain {
+        builder = builder.domain(domain);
+    }
+
+    if let Some(email) = &args.acme_email {
+        builder = builder.contact(format!("mailto:{email}"));
+    }
+
+    if let Some(path) = &args.acme_cache {
+        std::fs::create_dir_all(path)?;
+
+        builder = builder.cache_path(path);
+    }
+
+    Ok(Some((args.acme_listen.clone(), builder.build()?)))
+}
+
+/// Connect to daemon and start forwarding incoming messages, returning API to serve, for it to be
+/// embedded in another server.
+///
+/// Options given are reported by `GET /admin/config`, and overridden by those of `--config` file
+/// re-read on `POST /admin/reload`; reloading on `SIGHUP` is left to [`serve`].
+///
+/// # Errors
+///
+/// Fails on invalid options, if daemon cannot be reached, or if `--self-test` fails.
+pub async fn app(
+    args: Args,
+    settings: Vec<config::Setting>,
+) -> Result<poem::endpoint::BoxEndpoint<'static>> {
+    Ok(build(args, settings).await?.0)
+}
+
+/// Build API to serve, along with settings it reloads.
+async fn build(
+    args: Args,
+    settings: Vec<config::Setting>,
+) -> Result<(poem::endpoint::BoxEndpoint<'static>, Arc<Reloadable>)> {
+    use poem::EndpointExt;
+    use poem::middleware::AddData;
+
+    // Pick exposed endpoints before connecting, to fail fast on invalid selection
+    let routes = routes(&args)?;
+
+    #[cfg(feature = "templates")]
+    let templates = Arc::new(templates::Templates::new(&args.template)?);
+
+    // Settings reloaded on demand, without dropping daemon connection or in-flight requests
+    let reloadable = Arc::new(Reloadable::new(&args, settings)?);
+
+    // Load key of data stored on disk up front, for an invalid one to fail fast too
+    let vault = Arc::new(vault(&args)?);
+
+    let callers = Arc::new(Callers::new(
+        args.admin_token.clone(),
+        &args.api_token,
+        &args.api_quota,
+        &args.api_recipient,
+        args.default_country_code.as_deref(),
+    )?);
+
+    // Make sure webhooks expect messages, before any is swallowed by a mistyped URL
+    if args.verify_webhooks {
+        verify::verify_all(&reloadable.client, &reloadable.webhooks()).await?;
+    }
+
+    // Interface to communicate with `signal-cli` daemon over JSON-RPC, or with its mock
+    let mock = mock(&args)?;
+
+    let signal = daemon(&args, mock.clone()).await?;
+
+    // Catch misconfigured accounts on deploy, rather than on first message sent
+    if args.self_test {
+        self_test(&args, &signal).await?;
+    }
+
+    // Buffer incoming messages for clients polling instead of receiving webhook calls
+    #[cfg(feature = "compat")]
+    let capacity = if args.compat { args.receive_buffer } else { 0 };
+
+    #[cfg(feature = "compat")]
+    let inbox = Arc::new(Inbox::new(capacity));
+
+    // Listen to incoming messages from daemon, separately for each account if any are listed
+    let (subscriptions, queues, forwarders) = subscribe(
+        &args,
+        &reloadable,
+        &signal,
+        &vault,
+        #[cfg(feature = "compat")]
+        &inbox,
+    )?;
+
+    if args.mirror_sent {
+        tokio::spawn(mirror_sent(signal.subscribe_sent(), queues));
+    }
+
+    // Report readiness to service manager once every subscription is established
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        futures_util::future::join_all(subscriptions).await;
+
+        systemd::ready();
+    });
+
+    #[cfg(unix)]
+    tokio::spawn(systemd::watchdog());
+
+    let staging = Arc::new(staging(&args));
+
+    let uploads = Some(Duration::from_secs(args.upload_ttl))
+        .filter(|ttl| !ttl.is_zero())
+        .map(|ttl| Arc::new(Uploads::new(ttl)));
+
+    let sessions = args
+        .state_dir
+        .as_deref()
+        .map(|dir| Sessions::open(dir, Arc::clone(&vault)))
+        .transpose()?
+        .map(Arc::new);
+
+    // Resume delivery of messages accepted before a restart
+    let quiet = quiet::QuietHours::new(args.quiet_hours, &args.recipient_quiet_hours);
+
+    let outbox = args
+        .outbox
+        .as_deref()
+        .map(|dir| Outbox::open(dir, quiet, Arc::clone(&vault)))
+        .transpose()?;
+
+    // Keep records of previous runs, for log to cover every call made through API
+    let audit = args.audit_log.as_deref().map(Audit::open).transpose()?;
+
+    let identities = identities(&args);
+
+    if let Some(outbox) = &outbox {
+        let delivery = Arc::clone(outbox).deliver(
+            Arc::clone(&signal),
+            Arc::clone(&staging),
+            audit.clone(),
+            identities.clone(),
+        );
+
+        tokio::spawn(delivery);
+    }
+
+    // Store daemon connection and reloadable settings in application state
+    let app = routes
+        .with(AddData::new(signal))
+        .with(AddData::new(Arc::clone(&reloadable)))
+        .with(AddData::new(mock))
+        .with(AddData::new(staging))
+        .with(AddData::new(outbox))
+        .with(AddData::new(uploads))
+        .with(AddData::new(sessions))
+        .with(AddData::new(audit))
+        .with(AddData::new(forwarders))
+        .with(AddData::new(identities))
+        .with(AddData::new(Arc::clone(&callers)));
+
+    // Tell endpoints who calls them, turning away requests without a known token
+    let app = app.around(move |next, req| callers::authenticate(next, req, Arc::clone(&callers)));
+
+    // Compile templates of messages up front, for invalid ones to fail fast
+    #[cfg(feature = "templates")]
+    let app = app.with(AddData::new(templates));
+
+    // Share state of compatibility endpoints, attachment store of daemon if on the same machine
+    #[cfg(feature = "compat")]
+    let app = app
+        .with(AddData::new(inbox))
+        .with(AddData::new(compat::Attachments(args.attachments.clone())));
+
+    Ok((exposed(app, &args), reloadable))
+}
+
+/// Application as exposed to clients, shedding load, telling who requests come from, scrubbing
+/// errors in privacy mode, under path prefix if any.
+fn exposed(
+    app: impl poem::Endpoint + 'static,
+    args: &Args,
+) -> poem::endpoint::BoxEndpoint<'static> {
+    use poem::EndpointExt;
+
+    // Turn requests away once too many are handled, for slow ones not to starve others
+    let limits = Arc::new(shedding::Limits::new(args.max_in_flight, &args.route_limit));
+
+    let app = app.around(move |next, req| shedding::shed(next, req, Arc::clone(&limits)));
+
+    // Tell who requests come from, behind reverse proxies too
+    let proxies = Arc::new(clients::Proxies::new(args.trust_proxy.clone()));
+
+    let app = app.around(move |next, req| clients::identify(next, req, Arc::clone(&proxies)));
+
+    // Keep phone numbers out of errors too, which often echo those they are about
+    let app = if args.privacy {
+        app.around(privacy::scrub_errors).boxed()
+    } else {
+        app.map_to_response().boxed()
+    };
+
+    // Serve behind reverse proxies sharing host, endpoints seeing paths without prefix
+    match args.base_path.as_deref().filter(|base| !base.is_empty()) {
+        Some(base) => poem::Route::new().nest(base, app).boxed(),
+        None => app,
+    }
+}
+
+/// Send a note to self from each account, or from that of single-account daemon.
+async fn self_test(args: &Args, signal: &Daemon) -> Result<()> {
+    use color_eyre::eyre::WrapErr;
+
+    if args.account.is_empty() {
+        return commands::self_test(signal, None).await;
+    }
+
+    for account in &args.account {
+        commands::self_test(signal, Some(account))
+            .await
+            .wrap_err_with(|| format!("Self-test of {account} failed"))?;
+    }
+
+    Ok(())
+}
+
+/// Preparation of attachments, decoded to staging directory and downscaled as configured.
+fn staging(args: &Args) -> Staging {
+    Staging {
+        dir: args.staging_dir.clone(),
+        cache: args.attachment_cache.map(staging::Cache::new),
+        #[cfg(feature = "images")]
+        images: args
+            .image_max_dimension
+            .map(|max_dimension| images::Limits {
+                max_dimension,
+                quality: args.image_quality,
+            }),
+    }
+}
+
+/// Protection of data stored on disk, encrypting it once a storage secret is set.
+#[cfg_attr(
+    not(feature = "encryption"),
+    expect(unused_variables, clippy::unnecessary_wraps)
+)]
+fn vault(args: &Args) -> Result<Vault> {
+    #[cfg(feature = "encryption")]
+    {
+        use color_eyre::eyre::WrapErr;
+
+        let secret = match &args.storage_secret_file {
+            Some(path) => Some(
+                std::fs::read_to_string(path)
+                    .wrap_err_with(|| format!("Failed to read {}", path.display()))?,
+            ),
+            None => args.storage_secret.clone(),
+        };
+
+        if let Some(secret) = secret {
+            return Vault::new(&secret);
+        }
+    }
+
+    Ok(Vault::default())
+}
+
+/// Deliver incoming messages of each account, or of all of them, through their own queue.
+///
+/// Returns receivers notified once each subscription is established, and queues by account.
+fn subscribe(
+    args: &Args,
+    reloadable: &Arc<Reloadable>,
+    signal: &Arc<Daemon>,
+    vault: &Arc<Vault>,
+    #[cfg(feature = "compat")] inbox: &Arc<Inbox>,
+) -> Result<Subscribed> {
+    let accounts = if args.account.is_empty() {
+        vec![None]
+    } else {
+        args.account.iter().cloned().map(Some).collect()
+    };
+
+    let mut subscriptions = Vec::new();
+
+    let mut queues = Vec::new();
+
+    let mut tasks = Vec::new();
+
+    // Subscribe before any message is forwarded, for no change of identity to be missed
+    let identities = signal.subscribe_identities();
+
+    for account in accounts {
+        let (subscribed, subscription) = tokio::sync::oneshot::channel();
+
+        subscriptions.push(subscription);
+
+        // Queue messages on their way to webhook, for slow deliveries not to pile up unbounded
+        let spill = args.spill_dir.as_ref().map(|dir| {
+            let name = account.as_deref().unwrap_or("all");
+
+            dir.join(format!("{name}.jsonl"))
+        });
+
+        let queue = Arc::new(Queue::new(
+            args.queue_capacity,
+            args.overflow,
+            spill.as_deref(),
+            Arc::clone(vault),
+        )?);
+
+        tokio::spawn(deliver(
+            Arc::clone(&queue),
+            Arc::clone(reloadable),
+            Arc::clone(signal),
+        ));
+
+        queues.push((account.clone(), Arc::clone(&queue)));
+
+        let health = Arc::new(forwarders::Health::new(account.clone()));
+
+        tasks.push(Arc::clone(&health));
+
+        // Restart forwarding if it dies, telling of first subscription only
+        let (signal, mut subscribed) = (Arc::clone(signal), Some(subscribed));
+
+        #[cfg(feature = "compat")]
+        let inbox = Arc::clone(inbox);
+
+        let forward = {
+            let health = Arc::clone(&health);
+
+            move || {
+                forward_signals(
+                    Arc::clone(&queue),
+                    Arc::clone(&signal),
+                    account.clone(),
+                    subscribed.take(),
+                    Arc::clone(&health),
+                    #[cfg(feature = "compat")]
+                    Arc::clone(&inbox),
+                )
+            }
+        };
+
+        tokio::spawn(forwarders::supervise(health, forward));
+    }
+
+    tokio::spawn(alert_identities(
+        identities,
+        queues.clone(),
+        Arc::clone(signal),
+        args.identity_alert.clone(),
+    ));
+
+    let forwarders = Arc::new(forwarders::Forwarders::new(tasks));
+
+    Ok((subscriptions, queues, forwarders))
+}
+
+/// Notifications of established subscriptions, queues of accounts they deliver through, and
+/// health of tasks forwarding to them.
+type Subscribed = (
+    Vec<tokio::sync::oneshot::Receiver<()>>,
+    Vec<(Option<String>, Arc<Queue>)>,
+    Arc<forwarders::Forwarders>,
+);
+
+/// Connect to `signal-cli` daemon, spawning it first if requested, or to its mock.
+async fn daemon(args: &Args, mock: Option<Arc<Mock>>) -> Result<Arc<Daemon>> {
+    use color_eyre::eyre::bail;
+
+    let options = options(args)?;
+
+    if let Some(mock) = mock {
+        return Daemon::mock(mock, options).await;
+    }
+
+    let addrs = args.daemon.clone();
+
+    match args.spawn_daemon.clone() {
+        Some(command) => Daemon::spawn(command, addrs, options).await,
+        None if addrs.is_empty() => bail!("Either `--daemon` or `--spawn-daemon` is required"),
+        None => Daemon::connect(addrs, options).await,
+    }
+}
+
+/// Load mock daemon, if requested instead of a real one, or to replay a recording.
+fn mock(args: &Args) -> Result<Option<Arc<Mock>>> {
+    if !args.mock_daemon && args.replay.is_none() {
+        return Ok(None);
+    }
+
+    let mock = Mock::new(args.mock_script.as_deref(), args.replay.as_deref())?;
+
+    Ok(Some(mock))
+}
+
+/// Settings of connection to daemon.
+fn options(args: &Args) -> Result<daemon::Options> {
+    Ok(daemon::Options {
+        ping_interval: Some(Duration::from_secs(args.ping_interval)).filter(|d| !d.is_zero()),
+        request_timeout: Duration::from_secs(args.request_timeout),
+        max_frame_length: args.max_frame_length,
+        #[cfg(feature = "tls")]
+        tls: tls_config(args)?,
+        wait: Duration::from_secs(args.wait_for_daemon),
+        grace: Duration::from_secs(args.grace_period),
+        traffic: traffic(args),
+        record: args.record.as_deref().map(Recorder::create).transpose()?,
+        dry_run: args.dry_run,
+        group_cache_ttl: Duration::from_secs(args.group_cache_ttl),
+        rate_limit_budget: Duration::from_secs(args.rate_limit_budget),
+        send_rate: args.send_rate,
+        split: args.split_messages,
+        country_code: args.default_country_code.clone(),
+    })
+}
+
+/// How to log traffic with daemon, never verbatim in privacy mode.
+const fn traffic(args: &Args) -> Option<Traffic> {
+    match args.log_rpc {
+        Some(_) if args.privacy => Some(Traffic::Redacted),
+        traffic => traffic,
+    }
+}
+
+/// Load TLS configuration if any daemon is reached over TLS, to fail fast on invalid files.
+#[cfg(feature = "tls")]
+fn tls_config(args: &Args) -> Result<Option<Arc<tokio_rustls::rustls::ClientConfig>>> {
+    if !args.daemon.iter().any(|a| a.starts_with("tls://")) {
+        return Ok(None);
+    }
+
+    let identity = args.daemon_cert.as_deref().zip(args.daemon_key.as_deref());
+
+    Ok(Some(tls::config(args.daemon_ca.as_deref(), identity)?))
+}
+
+/// Forward received messages to provided HTTP endpoint, re-subscribing whenever subscription ends.
+async fn forward_signals(
+    queue: Arc<Queue>,
+    signal: Arc<Daemon>,
+    account: Option<String>,
+    subscribed: Option<tokio::sync::oneshot::Sender<()>>,
+    health: Arc<forwarders::Health>,
+    #[cfg(feature = "compat")] inbox: Arc<Inbox>,
+) {
+    use std::time::{Duration, Instant};
+
+    /// Delay before first subscription retry, doubled after each failure.
+    const BACKOFF_MIN: Duration = Duration::from_millis(500);
+
+    /// Upper bound on delay between subscription retries.
+    const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+    // Instant subscription was lost at, to report how long messages went unforwarded
+    let mut lost: Option<Instant> = None;
+
+    let mut backoff = BACKOFF_MIN;
+
+    // Told of first subscription only, re-subscriptions are not worth reporting
+    let mut subscribed = subscribed;
+
+    // Members requesting to join groups, for new requests to be told apart
+    let mut requests = joins::JoinRequests::default();
+
+    loop {
+        // Listen for incoming messages, fails until connection to daemon is re-established
+        let mut stream = match signal.subscribe_receive(account.as_deref()).await {
+            Ok(stream) => stream,
+            Err(error) => {
+                tracing::warn!("Failed to subscribe to incoming messages: {error}");
+                health.set_subscribed(false);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(BACKOFF_MAX);
+                continue;
+            }
+        };
+
+        backoff = BACKOFF_MIN;
+
+        health.set_subscribed(true);
+
+        if let Some(subscribed) = subscribed.take() {
+            let _ = subscribed.send(());
+        }
+
+        if let Some(lost) = lost.take() {
+            let gap = lost.elapsed().as_secs();
+            tracing::error!("Re-subscribed after {gap}s, messages may have been missed meanwhile");
+        }
+
+        // Iterate over messages as they arrive
+        while let Some(event) = stream.next().await {
+            // Keep events that do not fit the model as received, rather than dropping them
+            let mut event = match event {
+                Ok(value) => events::Event::parse(value),
+                Err(error) => {
+                    tracing::warn!("{error}");
+                    continue;
+                }
+            };
+
+            // Tell calls, receipts and typing indicators apart from messages, typed as such
+            event.type_envelope();
+
+            // Listings of groups are stale once one of them changes
+            if event.changes_group() {
+                signal.invalidate_groups(event.account.as_deref());
+
+                for request in requests.check(&signal, &event).await {
+                    queue.push(request).await;
+                }
+            }
+
+            // Alert of changed identity keys, on top of forwarding event daemon failed to decrypt
+            if let Some(change) = event.identity_change() {
+                let account = event.account.clone();
+
+                signal.report_identity(events::Event::identity_changed(account, change));
+            }
+
+            // Keep a copy for polling clients, queue event for webhook
+            #[cfg(feature = "compat")]
+            inbox.push(event.clone());
+
+            queue.push(event).await;
+        }
+
+        health.set_subscribed(false);
+
+        tracing::warn!("Subscription to incoming messages ended, re-subscribing");
+
+        lost = Some(Instant::now());
+    }
+}
+
+/// Queue messages sent through daemon for webhook of their account, as if received from it.
+async fn mirror_sent(
+    mut sent: tokio::sync::broadcast::Receiver<daemon::Outgoing>,
+    queues: Vec<(Option<String>, Arc<Queue>)>,
+) {
+    use tokio::sync::broadcast::error::RecvError;
+
+    loop {
+        let outgoing = match sent.recv().await {
+            Ok(outgoing) => outgoing,
+            Err(RecvError::Lagged(missed)) => {
+                tracing::warn!("Failed to mirror {missed} sent messages");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        for event in events::Event::sent(&outgoing.params, &outgoing.result) {
+            let queue = queues
+                .iter()
+                .find(|(account, _)| account.is_none() || *account == event.account);
+
+            if let Some((_, queue)) = queue {
+                queue.push(event).await;
+            }
+        }
+    }
+}
+
+/// Queue event for webhook of account once per new identity key of contact, looking up its
+/// safety number, and message number of `--identity-alert` of it too.
+async fn alert_identities(
+    mut identities: tokio::sync::broadcast::Receiver<events::Event>,
+    queues: Vec<(Option<String>, Arc<Queue>)>,
+    signal: Arc<Daemon>,
+    alert: Option<String>,
+) {
+    use std::collections::HashSet;
+
+    use tokio::sync::broadcast::error::RecvError;
+
+    // Identity keys alerted of already, as sends and messages keep failing until trusted
+    let mut alerted = HashSet::new();
+
+    loop {
+        let mut event = match identities.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(missed)) => {
+                tracing::warn!("Failed to alert of {missed} changed identities");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        let account = event.account.clone();
+
+        let Some(change) = event.identity.as_mut() else {
+            continue;
+        };
+
+        let Some(contact) = change.number.clone().or_else(|| change.uuid.clone()) else {
+            continue;
+        };
+
+        if change.safety_number.is_none() {
+            change.safety_number = safety_number(&signal, account.as_deref(), &contact)
+                .await
+                .unwrap_or_else(|error| {
+                    tracing::warn!("Failed to look up safety number: {error}");
+                    None
+                });
+        }
+
+        if !alerted.insert((
+            account.clone(),
+            contact.clone(),
+            change.safety_number.clone(),
+        )) {
+            continue;
+        }
+
+        tracing::warn!("Identity key of {contact} changed");
+
+        if let Some(alert) = &alert {
+            let message = format!(
+                "Identity key of {contact} changed, verify their safety number before trusting it"
+            );
+
+            let sent = signal
+                .send(account.as_deref(), Some(alert), None, &message, &[], &[])
+                .await;
+
+            if let Err(error) = sent {
+                tracing::warn!("Failed to send identity alert: {error}");
+            }
+        }
+
+        let queue = queues.iter().find(|(a, _)| a.is_none() || *a == account);
+
+        if let Some((_, queue)) = queue {
+            queue.push(event).await;
+        }
+    }
+}
+
+/// Forward queued events wholesale to their webhook, one at a time, dispatching chat commands
+/// and automatic replies.
+async fn deliver(queue: Arc<Queue>, reloadable: Arc<Reloadable>, signal: Arc<Daemon>) {
+    let client = reloadable.client.clone();
+
+    loop {
+        let event = queue.pop().await;
+
+        // Handle commands alongside, slow handlers must not hold up other messages
+        if let Some(invocation) = reloadable.route(&event) {
+            let (client, signal, event) = (client.clone(), Arc::clone(&signal), event.clone());
+
+            tokio::spawn(async move { invocation.dispatch(&client, &signal, &event).await });
+        }
+
+        #[cfg(feature = "auto-replies")]
+        if let Some(reply) = reloadable.auto_reply(&event) {
+            let (signal, event) = (Arc::clone(&signal), event.clone());
+
+            tokio::spawn(async move {
+                if let Err(error) = router::reply(&signal, &event, &reply).await {
+                    tracing::warn!("Failed to send automatic reply: {error}");
+                }
+            });
+        }
+
+        // Accounts of daemon not listed at start may have no webhook
+        let Some(webhook) = reloadable.webhook(&event) else {
+            tracing::warn!("Dropping event of account without webhook");
+            continue;
+        };
+
+        // Tell what event is about in headers too, for receivers to route without parsing it
+        let mut request = client.post(webhook).header("X-Signal-Event", event.name());
+
+        if let Some(account) = &event.account {
+            request = request.header("X-Signal-Account", account);
+        }
+
+        if let Some(timestamp) = event.envelope.as_ref().and_then(|e| e.timestamp) {
+            request = request.header("X-Signal-Timestamp", timestamp);
+        }
+
+        // Webhooks answering with errors did not take event either
+        let resp = request
+            .json(&event)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+
+        if let Err(error) = resp {
+            tracing::error!("Failed to deliver event to webhook: {error}");
+        }
+    }
+}
+
+/// Settings re-read from configuration file on `SIGHUP` or `POST /admin/reload`.
+struct Reloadable {
+    /// Options process started with, file being applied over them on reload.
+    args: Args,
+
+    webhooks: RwLock<Webhooks>,
+    router: RwLock<router::Router>,
+    #[cfg(feature = "auto-replies")]
+    replies: RwLock<replies::Replies>,
+
+    /// Options in effect, updated with reloaded ones.
+    settings: RwLock<Vec<config::Setting>>,
+
+    /// File options are read from, that changes made through API are stored in.
+    config: Option<PathBuf>,
+
+    /// Held while configuration file is changed and reloaded, for changes not to overlap.
+    editing: tokio::sync::Mutex<()>,
+
+    /// Client posting to webhooks, through `--webhook-proxy` if set.
+    client: reqwest::Client,
+}
+
+impl Reloadable {
+    /// Options taking effect on reload, others requiring a restart.
+    const OPTIONS: [&str; 6] = [
+        "webhook",
+        "account-webhook",
+        "verify-webhooks",
+        "chat-command",
+        "chat-command-prefix",
+        "auto-replies",
+    ];
+
+    fn new(args: &Args, settings: Vec<config::Setting>) -> Result<Self> {
+        let webhooks = Webhooks::new(args);
+
+        webhooks.cover(&args.account)?;
+
+        Ok(Self {
+            args: args.clone(),
+            webhooks: RwLock::new(webhooks),
+            router: RwLock::new(Self::router(args)),
+            #[cfg(feature = "auto-replies")]
+            replies: RwLock::new(replies::Replies::load(args.auto_replies.as_deref())?),
+            settings: RwLock::new(settings),
+            config: args.config.clone(),
+            editing: tokio::sync::Mutex::default(),
+            client: webhook_client(args)?,
+        })
+    }
+
+    /// Change option in configuration file, then reload it, restoring file as it was if
+    /// options it now holds are invalid.
+    async fn store(&self, key: &str, entry: Option<&str>, value: Option<&str>) -> Result<()> {
+        use color_eyre::eyre::{WrapErr, eyre};
+
+        let path = self
+            .config
+            .as_deref()
+            .ok_or_else(|| eyre!("No configuration file"))?;
+
+        let _editing = self.editing.lock().await;
+
+        let previous = tokio::fs::read(path).await?;
+
+        config::store(path, key, entry, value)?;
+
+        if let Err(error) = self.reload().await {
+            tokio::fs::write(path, previous)
+                .await
+                .wrap_err_with(|| format!("Failed to restore {}", path.display()))?;
+
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Swap settings for those currently configured, keeping previous ones if invalid, or if
+    /// their webhooks fail verification when it is required.
+    async fn reload(&self) -> Result<()> {
+        // Only file is read again, neither command line nor environment of process
+        let reloaded = match &self.config {
+            Some(path) => Some(config::reload(path, &self.settings())?),
+            None => None,
+        };
+
+        let args = reloaded.as_ref().map_or(&self.args, |(args, _)| args);
+
+        let webhooks = Webhooks::new(args);
+
+        webhooks.cover(&args.account)?;
+
+        if args.verify_webhooks {
+            verify::verify_all(&self.client, &webhooks.all()).await?;
+        }
+
+        #[cfg(feature = "auto-replies")]
+        let replies = replies::Replies::load(args.auto_replies.as_deref())?;
+
+        *self
+            .webhooks
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = webhooks;
+
+        *self.router.write().unwrap_or_else(PoisonError::into_inner) = Self::router(args);
+
+        #[cfg(feature = "auto-replies")]
+        {
+            *self.replies.write().unwrap_or_else(PoisonError::into_inner) = replies;
+        }
+
+        if let Some((_, settings)) = reloaded {
+            let mut current = self
+                .settings
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            // Report reloaded options only, others keep values they started with
+            current.retain(|s| !Self::OPTIONS.contains(&s.name.as_str()) || s.source.is_explicit());
+            current.extend(
+                settings
+                    .into_iter()
+                    .filter(|s| Self::OPTIONS.contains(&s.name.as_str())),
+            );
+
+            drop(current);
+        }
+
+        tracing::info!("Reloaded configuration");
+
+        Ok(())
+    }
+
+    /// Endpoint to forward event to, if its account has one.
+    fn webhook(&self, event: &events::Event) -> Option<String> {
+        let webhooks = self.webhooks.read().unwrap_or_else(PoisonError::into_inner);
+
+        webhooks.of(event).map(str::to_owned)
+    }
+
+    /// Webhooks by identifier, `default` or number of account.
+    fn webhooks(&self) -> Vec<(String, String)> {
+        let webhooks = self.webhooks.read().unwrap_or_else(PoisonError::into_inner);
+
+        webhooks.all()
+    }
+
+    /// Command message of event invokes, if any.
+    fn route(&self, event: &events::Event) -> Option<router::Invocation> {
+        let router = self.router.read().unwrap_or_else(PoisonError::into_inner);
+
+        router.route(event)
+    }
+
+    /// Reply of first rule message of event matches, if any.
+    #[cfg(feature = "auto-replies")]
+    fn auto_reply(&self, event: &events::Event) -> Option<String> {
+        let replies = self.replies.read().unwrap_or_else(PoisonError::into_inner);
+
+        replies.reply(event)
+    }
+
+    fn router(args: &Args) -> router::Router {
+        router::Router::new(&args.chat_command_prefix, &args.chat_command)
+    }
+
+    fn settings(&self) -> Vec<config::Setting> {
+        let settings = self.settings.read().unwrap_or_else(PoisonError::into_inner);
+
+        settings.clone()
+    }
+}
+
+/// Change webhook option in configuration file, unless there is none, or option is set where
+/// it takes precedence over file.
+async fn store_webhook(
+    reloadable: &Reloadable,
+    key: &str,
+    entry: Option<&str>,
+    url: Option<&str>,
+) -> ResultPoem {
+    use poem::http::StatusCode;
+
+    use self::config::Source;
+
+    if reloadable.config.is_none() {
+        let msg = "No configuration file to store webhooks in, see `--config`";
+
+        return Err(poem::Error::from_string(msg, StatusCode::CONFLICT));
+    }
+
+    let source = reloadable.settings().into_iter().find(|s| s.name == key);
+
+    // Options set on command line or in environment would override those stored in file
+    if let Some(source) = source.map(|s| s.source)
+        && matches!(source, Source::CommandLine | Source::Environment)
+    {
+        let msg = format!("`--{key}` is set from {}, over file", source.as_str());
+
+        return Err(poem::Error::from_string(msg, StatusCode::CONFLICT));
+    }
+
+    if let Err(error) = reloadable.store(key, entry, url).await {
+        return unprocessable(&format!("Invalid configuration: {error:#}"));
+    }
+
+    Ok(())
+}
+
+/// Reload settings whenever process receives `SIGHUP`.
+#[cfg(unix)]
+async fn reload_on_hangup(mut hangups: tokio::signal::unix::Signal, reloadable: Arc<Reloadable>) {
+    while hangups.recv().await.is_some() {
+        if let Err(error) = reloadable.reload().await {
+            tracing::warn!("Failed to reload configuration: {error:#}");
+        }
+    }
+}
+
+/// Endpoints to forward messages to, picked by account they are addressed to.
+struct Webhooks {
+    /// Endpoint of accounts without one of their own, if any.
+    default: Option<String>,
+    accounts: HashMap<String, String>,
+}
+
+impl Webhooks {
+    fn new(args: &Args) -> Self {
+        Self {
+            default: args.webhook.clone(),
+            accounts: args.account_webhook.iter().cloned().collect(),
+        }
+    }
+
+    /// Endpoint of account event is addressed to, if it has one.
+    fn of(&self, event: &events::Event) -> Option<&str> {
+        let account = event.account.as_ref();
+
+        account
+            .and_then(|a| self.accounts.get(a))
+            .or(self.default.as_ref())
+            .map(String::as_str)
+    }
+
+    /// Endpoints by identifier, `default` one first if set, then those of accounts by number.
+    fn all(&self) -> Vec<(String, String)> {
+        let mut accounts: Vec<_> = self.accounts.clone().into_iter().collect();
+
+        accounts.sort();
+
+        let default = self
+            .default
+            .clone()
+            .map(|url| (String::from("default"), url));
+
+        default.into_iter().chain(accounts).collect()
+    }
+
+    /// Fail unless each of `accounts` has an endpoint, for their messages not to be dropped.
+    fn cover(&self, accounts: &[String]) -> Result<()> {
+        use color_eyre::eyre::bail;
+
+        if self.default.is_some() {
+            return Ok(());
+        }
+
+        for account in accounts {
+            if !self.accounts.contains_key(account) {
+                bail!("Account {account} has no webhook, and no default one is set");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Split `number=url` argument into account and its endpoint.
+fn parse_account_webhook(arg: &str) -> Result<(String, String), String> {
+    let Some((account, url)) = arg.split_once('=') else {
+        return Err(String::from("expected `number=url`"));
+    };
+
+    Ok((account.to_owned(), url.to_owned()))
+}
+
+/// Client posting to webhooks with `--webhook-user-agent`, through `--webhook-proxy`, or through
+/// proxies of environment if none is given.
+///
+/// # Errors
+///
+/// Fails on invalid proxy URLs or user agents.
+fn webhook_client(args: &Args) -> Result<reqwest::Client> {
+    use color_eyre::eyre::WrapErr;
+
+    let mut builder = reqwest::Client::builder().user_agent(&args.webhook_user_agent);
+
+    if let Some(proxy) = &args.webhook_proxy {
+        let proxy = reqwest::Proxy::all(proxy).wrap_err("Invalid `--webhook-proxy`")?;
+
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Parse path prefix as `/segment`, without trailing slash, `/` alone standing for none.
+fn parse_base_path(arg: &str) -> Result<String, String> {
+    if !arg.starts_with('/') {
+        return Err(String::from("expected path starting with `/`"));
+    }
+
+    Ok(arg.trim_end_matches('/').to_owned())
+}
+
+/// Pull crate name from environment variable at compile time.
+const NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Default `User-Agent` of requests to webhooks, naming crate and its version.
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Endpoints of native API, with those of optional features enabled.
+#[cfg(all(feature = "native", feature = "templates"))]
+const NATIVE: (Api, templates::Templating) = (Api, templates::Templating);
+
+#[cfg(all(feature = "native", not(feature = "templates")))]
+const NATIVE: Api = Api;
+
+/// Associate routes of selected APIs with handler functions, without state they depend on.
+///
+/// # Errors
+///
+/// Fails if no API is selected.
+pub fn routes(args: &Args) -> Result<poem::Route> {
+    use color_eyre::eyre::bail;
+
+    let url = server_url(args);
+
+    Ok(match selected(args) {
+        #[cfg(all(feature = "native", feature = "compat"))]
+        (true, true) => documented((NATIVE, compat::Compat), url),
+        #[cfg(feature = "native")]
+        (true, false) => documented(NATIVE, url),
+        #[cfg(feature = "compat")]
+        (false, true) => documented(compat::Compat, url),
+        _ => bail!("At least one API must be exposed"),
+    })
+}
+
+/// `OpenAPI` document of selected APIs, as JSON, for clients to be generated from.
+///
+/// # Errors
+///
+/// Fails if no API is selected.
+pub fn spec(args: &Args) -> Result<String> {
+    use color_eyre::eyre::bail;
+
+    let url = server_url(args);
+
+    Ok(match selected(args) {
+        #[cfg(all(feature = "native", feature = "compat"))]
+        (true, true) => service((NATIVE, compat::Compat), url).spec(),
+        #[cfg(feature = "native")]
+        (true, false) => service(NATIVE, url).spec(),
+        #[cfg(feature = "compat")]
+        (false, true) => service(compat::Compat, url).spec(),
+        _ => bail!("At least one API must be exposed"),
+    })
+}
+
+/// External URL of service, with prefix routes are served under.
+fn server_url(args: &Args) -> String {
+    let base = args.base_path.as_deref().unwrap_or_default();
+
+    format!("{}{base}", args.url.trim_end_matches('/'))
+}
+
+/// Path of identities of compatibility API, linked to when identity keys change.
+fn identities(args: &Args) -> Identities {
+    let base = args.base_path.as_deref().unwrap_or_default();
+
+    Identities(selected(args).1.then(|| format!("{base}/v1/identities")))
+}
+
+/// Whether native and compatibility APIs are exposed, among those built in.
+const fn selected(args: &Args) -> (bool, bool) {
+    #[cfg(feature = "native")]
+    let native = args.native;
+
+    #[cfg(not(feature = "native"))]
+    let native = false;
+
+    #[cfg(feature = "compat")]
+    let compat = args.compat;
+
+    #[cfg(not(feature = "compat"))]
+    let compat = false;
+
+    (native, compat)
+}
+
+/// Describe API endpoints and webhooks, following `OpenAPI` spec.
+fn service<T: poem_openapi::OpenApi>(
+    api: T,
+    url: String,
+) -> poem_openapi::OpenApiService<T, &'static dyn events::Outgoing> {
+    poem_openapi::OpenApiService::new(api, NAME, env!("CARGO_PKG_VERSION"))
+        .webhooks::<&dyn events::Outgoing>()
+        .server(url)
+}
+
+/// Serve API routes along with their documentation.
+fn documented(api: impl 'static + poem_openapi::OpenApi, url: String) -> poem::Route {
+    let app = service(api, url);
+
+    // Host documentation on dedicated page
+    let docs = app.swagger_ui();
+
+    poem::Route::new().nest("/", app).nest("/docs", docs)
+}
+
+/// Proxy to interact with Signal service.
+type Signal<'a, 'p> = poem::web::Data<&'a Arc<Daemon>>;
+
+/// Where to decode attachments of sent messages to, if anywhere.
+type Staged<'a> = poem::web::Data<&'a Arc<Staging>>;
+
+/// State of conversations, if a directory is configured to keep it in.
+type Stored<'a> = poem::web::Data<&'a Option<Arc<Sessions>>>;
+
+/// Integration calling API, as authenticated by its token.
+type Calling<'a> = poem::web::Data<&'a Arc<Caller>>;
+
+/// Log of sends, reactions and receipts, if one is kept.
+type Audited<'a> = poem::web::Data<&'a Option<Arc<Audit>>>;
+
+/// Where changed identity keys are trusted, if anywhere.
+type Trusting<'a> = poem::web::Data<&'a Identities>;
+
+/// Path of identities of compatibility API, under prefix routes are served under, if it is exposed.
+#[derive(Clone)]
+struct Identities(Option<String>);
+
+impl Identities {
+    /// Endpoint trusting identity key of `recipient` on behalf of `account`, if exposed.
+    fn trust(&self, account: &str, recipient: &str) -> Option<String> {
+        let identities = self.0.as_deref()?;
+
+        Some(format!("{identities}/{account}/trust/{recipient}"))
+    }
+}
+
+/// Attach endpoint handlers to dummy struct to generate documentation automatically.
+struct Api;
+
+/// Empty response of fallible handler.
+type ResultPoem<T = ()> = poem::Result<T>;
+
+#[poem_openapi::OpenApi]
+impl Api {
+    /// Send or remove emoji reaction to a message.
+    #[oai(path = "/react", method = "post")]
+    async fn react(
+        &self,
+        Json(mut body): Json<React>,
+        signal: Signal<'_, '_>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+    ) -> ResultPoem {
+        let result: ResultPoem = async {
+            resolve_recipient(&mut body.recipient, body.account.as_deref(), &signal).await?;
+
+            authorize(&caller, &body.recipient)?;
+
+            let (person, group) = parse_recipient(&body.recipient)?;
+
+            // Daemon errors on other reactions are opaque, tell what is wrong up front
+            if emojis::get(&body.emoji).is_none() {
+                let emoji = &body.emoji;
+
+                return unprocessable(&format!(
+                    "Reaction `{emoji}` is not a single emoji, like `👍`"
+                ));
+            }
+
+            let remove = body.remove.unwrap_or(false);
+
+            signal
+                .react(
+                    body.account.as_deref(),
+                    person,
+                    group,
+                    &body.emoji,
+                    &body.author,
+                    body.timestamp,
+                    remove,
+                )
+                .await
+                .or_internal_server_error()?;
+
+            Ok(())
+        }
+        .await;
+
+        let outcome = result.as_ref().map(|()| 200);
+
+        audit_call(
+            audit.as_deref(),
+            &caller,
+            audit::Action::React,
+            body.account,
+            body.recipient.key(),
+            outcome,
+        )
+        .await;
+
+        result
+    }
+
+    /// Send read or viewed receipt event.
+    #[oai(path = "/receive", method = "post")]
+    async fn receive(
+        &self,
+        body: Json<Receive>,
+        signal: Signal<'_, '_>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+    ) -> ResultPoem {
+        let recipient = Recipient {
+            kind: RecipientKind::Person,
+            value: body.recipient.clone(),
+        };
+
+        let result: ResultPoem = async {
+            authorize(&caller, &recipient)?;
+
+            let kind = body.kind.unwrap_or(ReceiptKind::Read);
+
+            signal
+                .receive(
+                    body.account.as_deref(),
+                    &body.recipient,
+                    body.timestamp,
+                    kind.as_str(),
+                )
+                .await
+                .or_internal_server_error()?;
+
+            Ok(())
+        }
+        .await;
+
+        let outcome = result.as_ref().map(|()| 200);
+        let account = body.0.account;
+
+        audit_call(
+            audit.as_deref(),
+            &caller,
+            audit::Action::Receipt,
+            account,
+            recipient.key(),
+            outcome,
+        )
+        .await;
+
+        result
+    }
+
+    /// Send a message to `signal-cli` daemon, given as JSON, as form fields, or as plain text
+    /// addressed to `recipient` query parameter.
+    #[oai(path = "/send", method = "post")]
+    #[expect(clippy::too_many_arguments)]
+    async fn send(
+        &self,
+        body: SendBody,
+        recipient: Query<Option<String>>,
+        account: Query<Option<String>>,
+        queued: Query<Option<bool>>,
+        signal: Signal<'_, '_>,
+        staging: Staged<'_>,
+        outbox: poem::web::Data<&Option<Arc<Outbox>>>,
+        uploads: poem::web::Data<&Option<Arc<Uploads>>>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+        trusting: Trusting<'_>,
+    ) -> ResultPoem<Sent> {
+        let body = body.into_send(recipient.0, account.0)?;
+
+        send_audited(
+            body, queued, signal, staging, outbox, uploads, caller, audit, trusting,
+        )
+        .await
+    }
+
+    /// Send several messages at once, reporting outcome of each in order.
+    #[oai(path = "/send/batch", method = "post")]
+    #[expect(clippy::too_many_arguments)]
+    async fn send_batch(
+        &self,
+        Json(bodies): Json<Vec<Send>>,
+        queued: Query<Option<bool>>,
+        signal: Signal<'_, '_>,
+        staging: Staged<'_>,
+        outbox: poem::web::Data<&Option<Arc<Outbox>>>,
+        uploads: poem::web::Data<&Option<Arc<Uploads>>>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+        trusting: Trusting<'_>,
+    ) -> Json<Vec<BatchResult>> {
+        use futures_util::stream::{self, StreamExt};
+        use poem::web::Data;
+
+        /// Number of messages being sent at once, to avoid overwhelming daemon.
+        const CONCURRENCY: usize = 8;
+
+        let sends = bodies.into_iter().map(|body| async move {
+            let sent = send_audited(
+                body,
+                Query(queued.0),
+                Data(signal.0),
+                Data(staging.0),
+                Data(outbox.0),
+                Data(uploads.0),
+                Data(caller.0),
+                Data(audit.0),
+                Data(trusting.0),
+            );
+
+            match sent.await {
+                Ok(Sent::Delivered(Json(resp))) => BatchResult::sent(resp),
+                Ok(Sent::Untrusted(Json(identity))) => BatchResult::untrusted(identity),
+                Ok(Sent::Queued(Json(entry))) => BatchResult::queued(entry),
+                Err(error) => BatchResult::failed(&error),
+            }
+        });
+
+        Json(stream::iter(sends).buffered(CONCURRENCY).collect().await)
+    }
+
+    /// Store attachment for several sends to refer to by token, instead of uploading it each time.
+    ///
+    /// Content type is detected from bytes of attachment, unless set to another one than
+    /// `application/octet-stream`.
+    #[oai(path = "/attachments", method = "post")]
+    #[expect(clippy::unused_async)]
+    async fn upload(
+        &self,
+        Binary(bytes): Binary<Vec<u8>>,
+        #[oai(name = "Content-Type")] content_type: poem_openapi::param::Header<Option<String>>,
+        uploads: poem::web::Data<&Option<Arc<Uploads>>>,
+    ) -> ResultPoem<Json<Uploaded>> {
+        use base64::Engine;
+        use base64::engine::general_purpose::STANDARD;
+        use poem::error::NotFoundError;
+
+        let uploads = uploads.as_ref().ok_or(NotFoundError)?;
+
+        let payload = STANDARD.encode(bytes);
+
+        // Turn explicit content type into data URI, for it to be used rather than detected
+        let attachment = match content_type.0.filter(|t| t != "application/octet-stream") {
+            Some(content_type) => format!("data:{content_type};base64,{payload}"),
+            None => payload,
+        };
+
+        let (token, expires) = uploads.store(attachment);
+
+        Ok(Json(Uploaded { token, expires }))
+    }
+
+    /// Share contact card, attached as vCard for recipient to save contact from.
+    #[oai(path = "/send/contact", method = "post")]
+    #[expect(clippy::too_many_arguments)]
+    async fn send_contact(
+        &self,
+        Json(body): Json<SendContact>,
+        queued: Query<Option<bool>>,
+        signal: Signal<'_, '_>,
+        staging: Staged<'_>,
+        outbox: poem::web::Data<&Option<Arc<Outbox>>>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+        trusting: Trusting<'_>,
+    ) -> ResultPoem<Sent> {
+        use base64::Engine;
+        use base64::engine::general_purpose::STANDARD;
+        use poem::web::Data;
+
+        if body.number.is_none() && body.email.is_none() {
+            return unprocessable("Contact needs a number or an email");
+        }
+
+        let card = vcard(&body.name, body.number.as_deref(), body.email.as_deref());
+
+        let attachment = format!(
+            "data:text/vcard;filename=contact.vcf;base64,{}",
+            STANDARD.encode(card)
+        );
+
+        let body = Send {
+            account: body.account,
+            recipient: body.recipient,
+            message: body.name,
+            text_mode: None,
+            attachments: Some(vec![attachment]),
+            uploads: None,
+            urgent: body.urgent,
+        };
+
+        let uploads = Data(&None);
+
+        send_audited(
+            body,
+            queued,
+            Data(signal.0),
+            Data(staging.0),
+            outbox,
+            uploads,
+            caller,
+            audit,
+            trusting,
+        )
+        .await
+    }
+
+    /// Re-read webhook targets from configuration file, like `SIGHUP` does.
+    #[oai(path = "/admin/reload", method = "post")]
+    async fn reload(&self, reloadable: poem::web::Data<&Arc<Reloadable>>) -> ResultPoem {
+        if let Err(error) = reloadable.reload().await {
+            return unprocessable(&format!("Invalid configuration: {error:#}"));
+        }
+
+        Ok(())
+    }
+
+    /// List webhooks, `default` one first, then those of accounts, with credentials masked.
+    #[oai(path = "/admin/webhooks", method = "get")]
+    #[expect(clippy::unused_async)]
+    async fn webhooks(&self, reloadable: poem::web::Data<&Arc<Reloadable>>) -> Json<Vec<Webhook>> {
+        let webhooks = reloadable.webhooks().into_iter().map(|(id, url)| Webhook {
+            id,
+            url: config::mask("webhook", &url),
+        });
+
+        Json(webhooks.collect())
+    }
+
+    /// Set webhook of an account, or replace default one, storing it in configuration file.
+    #[oai(path = "/admin/webhooks", method = "post")]
+    async fn add_webhook(
+        &self,
+        Json(body): Json<NewWebhook>,
+        reloadable: poem::web::Data<&Arc<Reloadable>>,
+    ) -> ResultPoem {
+        if let Err(error) = reqwest::Url::parse(&body.url) {
+            return unprocessable(&format!("Invalid URL `{}`: {error}", body.url));
+        }
+
+        let url = Some(body.url.as_str());
+
+        match body.account.as_deref() {
+            None => store_webhook(&reloadable, "webhook", None, url).await,
+            Some(account) => {
+                store_webhook(&reloadable, "account-webhook", Some(account), url).await
+            }
+        }
+    }
+
+    /// Remove webhook of an account, forwarding its messages to default one again.
+    #[oai(path = "/admin/webhooks/:id", method = "delete")]
+    async fn delete_webhook(
+        &self,
+        poem_openapi::param::Path(id): poem_openapi::param::Path<String>,
+        reloadable: poem::web::Data<&Arc<Reloadable>>,
+    ) -> ResultPoem {
+        use poem::error::NotFoundError;
+
+        if id == "default" {
+            return unprocessable("Default webhook cannot be removed, only replaced");
+        }
+
+        if !reloadable.webhooks().iter().any(|(i, _)| *i == id) {
+            return Err(NotFoundError.into());
+        }
+
+        store_webhook(&reloadable, "account-webhook", Some(&id), None).await
+    }
+
+    /// Post challenge to webhook, `default` one or that of an account, expecting it echoed back.
+    #[oai(path = "/admin/webhooks/:id/verify", method = "post")]
+    async fn verify_webhook(
+        &self,
+        poem_openapi::param::Path(id): poem_openapi::param::Path<String>,
+        reloadable: poem::web::Data<&Arc<Reloadable>>,
+    ) -> ResultPoem {
+        use poem::error::NotFoundError;
+
+        let webhooks = reloadable.webhooks();
+
+        let (_, url) = webhooks
+            .iter()
+            .find(|(i, _)| *i == id)
+            .ok_or(NotFoundError)?;
+
+        if let Err(error) = verify::verify(&reloadable.client, url).await {
+            return unprocessable(&format!("Webhook failed verification: {error}"));
+        }
+
+        Ok(())
+    }
+
+    /// Report how many messages each API token sent over current hour or day, against its quotas.
+    #[oai(path = "/admin/quotas", method = "get")]
+    #[expect(clippy::unused_async)]
+    async fn quotas(&self, callers: poem::web::Data<&Arc<Callers>>) -> Json<Vec<callers::Usage>> {
+        Json(callers.usage())
+    }
+
+    /// Report whether forwarding of incoming messages of each account is subscribed, and how many
+    /// times it died and was restarted.
+    #[oai(path = "/admin/forwarders", method = "get")]
+    #[expect(clippy::unused_async)]
+    async fn forwarders(
+        &self,
+        forwarders: poem::web::Data<&Arc<forwarders::Forwarders>>,
+    ) -> Json<Vec<forwarders::Forwarder>> {
+        Json(forwarders.report())
+    }
+
+    /// List sends, reactions and receipts recorded to audit log, oldest first, keeping `limit`
+    /// latest ones matching query.
+    #[oai(path = "/admin/audit", method = "get")]
+    #[expect(clippy::too_many_arguments)]
+    async fn audit(
+        &self,
+        caller: Query<Option<String>>,
+        recipient: Query<Option<String>>,
+        action: Query<Option<audit::Action>>,
+        since: Query<Option<u64>>,
+        until: Query<Option<u64>>,
+        limit: Query<Option<usize>>,
+        audit: Audited<'_>,
+    ) -> ResultPoem<Json<Vec<audit::Record>>> {
+        use poem::error::NotFoundError;
+
+        let audit = audit.as_ref().ok_or(NotFoundError)?;
+
+        let filter = audit::Filter {
+            caller: caller.0,
+            recipient: recipient.0,
+            action: action.0,
+            since: since.0,
+            until: until.0,
+            limit: limit.0.unwrap_or(audit::LIMIT),
+        };
+
+        Ok(Json(audit.query(&filter).await.or_internal_server_error()?))
+    }
+
+    /// List options in effect, where each of them is set from, and values of those not secret.
+    #[oai(path = "/admin/config", method = "get")]
+    #[expect(clippy::unused_async)]
+    async fn config(
+        &self,
+        reloadable: poem::web::Data<&Arc<Reloadable>>,
+    ) -> Json<Vec<config::Setting>> {
+        Json(reloadable.settings())
+    }
+
+    /// List messages accepted with `?queued=true` and not delivered yet, oldest first.
+    #[oai(path = "/outbox", method = "get")]
+    #[expect(clippy::unused_async)]
+    async fn outbox(
+        &self,
+        outbox: poem::web::Data<&Option<Arc<Outbox>>>,
+    ) -> ResultPoem<Json<Vec<outbox::Entry>>> {
+        use poem::error::NotFoundError;
+
+        let outbox = outbox.as_ref().ok_or(NotFoundError)?;
+
+        Ok(Json(outbox.entries()))
+    }
+
+    /// Get state stored for conversation with a number or group identifier.
+    #[oai(path = "/conversations/:id/state", method = "get")]
+    async fn get_state(
+        &self,
+        poem_openapi::param::Path(id): poem_openapi::param::Path<String>,
+        sessions: Stored<'_>,
+    ) -> ResultPoem<Json<serde_json::Value>> {
+        use poem::error::NotFoundError;
+
+        let sessions = sessions.as_ref().ok_or(NotFoundError)?;
+
+        let state = sessions.get(&id).await.or_internal_server_error()?;
+
+        Ok(Json(state.ok_or(NotFoundError)?))
+    }
+
+    /// Replace state stored for conversation with arbitrary JSON.
+    #[oai(path = "/conversations/:id/state", method = "put")]
+    async fn put_state(
+        &self,
+        poem_openapi::param::Path(id): poem_openapi::param::Path<String>,
+        Json(state): Json<serde_json::Value>,
+        sessions: Stored<'_>,
+    ) -> ResultPoem {
+        use poem::error::NotFoundError;
+
+        let sessions = sessions.as_ref().ok_or(NotFoundError)?;
+
+        sessions.put(&id, &state).await.or_internal_server_error()
+    }
+
+    /// Forget state stored for conversation, once dialogue is over.
+    #[oai(path = "/conversations/:id/state", method = "delete")]
+    async fn delete_state(
+        &self,
+        poem_openapi::param::Path(id): poem_openapi::param::Path<String>,
+        sessions: Stored<'_>,
+    ) -> ResultPoem {
+        use poem::error::NotFoundError;
+
+        let sessions = sessions.as_ref().ok_or(NotFoundError)?;
+
+        if !sessions.delete(&id).await.or_internal_server_error()? {
+            return Err(NotFoundError.into());
+        }
+
+        Ok(())
+    }
+
+    /// Deliver incoming event from mock daemon, as if it had been received.
+    #[oai(path = "/admin/mock/receive", method = "post")]
+    #[expect(clippy::unused_async)]
+    async fn inject(
+        &self,
+        Json(event): Json<events::Event>,
+        mock: poem::web::Data<&Option<Arc<Mock>>>,
+    ) -> ResultPoem<Json<Injected>> {
+        use poem::error::NotFoundError;
+
+        let mock = mock.as_ref().ok_or(NotFoundError)?;
+
+        let event = serde_json::to_value(event).or_internal_server_error()?;
+
+        Ok(Json(Injected {
+            subscriptions: mock.inject(event),
+        }))
+    }
+
+    /// Report whether connection to daemon is up and answering requests, and incoming messages
+    /// are being forwarded.
+    #[oai(path = "/ready", method = "get")]
+    #[expect(clippy::unused_async)]
+    async fn ready(
+        &self,
+        signal: Signal<'_, '_>,
+        forwarders: poem::web::Data<&Arc<forwarders::Forwarders>>,
+    ) -> Readiness {
+        if signal.is_ready() && forwarders.is_healthy() {
+            Readiness::Ready
+        } else {
+            Readiness::NotReady
+        }
+    }
+
+    /// Send a message to `signal-cli` daemon.
+    #[oai(path = "/typing", method = "post")]
+    async fn typing(
+        &self,
+        Json(mut b): Json<Typing>,
+        signal: Signal<'_, '_>,
+        caller: Calling<'_>,
+    ) -> ResultPoem {
+        resolve_recipient(&mut b.recipient, b.account.as_deref(), &signal).await?;
+
+        authorize(&caller, &b.recipient)?;
+
+        let (person, group) = parse_recipient(&b.recipient)?;
+
+        signal
+            .send_typing(b.account.as_deref(), person, group, b.stop)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(())
+    }
+
+    /// Toggle read receipts, typing indicators and link previews of account, settings left out
+    /// staying as they are.
+    #[oai(path = "/configuration", method = "put")]
+    async fn configure(&self, Json(b): Json<Configuration>, signal: Signal<'_, '_>) -> ResultPoem {
+        signal
+            .update_configuration(
+                b.account.as_deref(),
+                b.read_receipts,
+                b.unidentified_delivery_indicators,
+                b.typing_indicators,
+                b.link_previews,
+            )
+            .await
+            .or_internal_server_error()?;
+
+        Ok(())
+    }
+
+    /// Install public sticker pack from its `https://signal.art/addstickers/` link.
+    #[oai(path = "/sticker-packs/install", method = "post")]
+    async fn install_sticker_pack(
+        &self,
+        Json(b): Json<StickerPack>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem {
+        if !is_sticker_pack(&b.url) {
+            return unprocessable(
+                "Expected `https://signal.art/addstickers/#pack_id=...&pack_key=...`",
+            );
+        }
+
+        signal
+            .add_sticker_pack(b.account.as_deref(), &b.url)
+            .await
+            .or_internal_server_error()?;
+
+        Ok(())
+    }
+
+    /// Get profile picture of a contact, by number or identifier.
+    #[oai(path = "/contacts/:number/avatar", method = "get")]
+    async fn contact_avatar(
+        &self,
+        poem_openapi::param::Path(number): poem_openapi::param::Path<String>,
+        account: Query<Option<String>>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Avatar> {
+        let recipient = Recipient {
+            kind: RecipientKind::Person,
+            value: number,
+        };
+
+        avatar(recipient, account.as_deref(), &signal).await
+    }
+
+    /// Get picture of a group, by identifier.
+    #[oai(path = "/groups/:id/avatar", method = "get")]
+    async fn group_avatar(
+        &self,
+        poem_openapi::param::Path(id): poem_openapi::param::Path<String>,
+        account: Query<Option<String>>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem<Avatar> {
+        let recipient = Recipient {
+            kind: RecipientKind::Group,
+            value: id,
+        };
+
+        avatar(recipient, account.as_deref(), &signal).await
+    }
+
+    /// Let in member who requested to join group through its invite link.
+    #[oai(path = "/groups/:id/requests/:number/approve", method = "post")]
+    async fn approve_request(
+        &self,
+        poem_openapi::param::Path(id): poem_openapi::param::Path<String>,
+        poem_openapi::param::Path(number): poem_openapi::param::Path<String>,
+        account: Query<Option<String>>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem {
+        let member = requesting_member(number, account.as_deref(), &signal).await?;
+
+        signal
+            .add_group_members(account.as_deref(), &id, &[member])
+            .await
+            .or_internal_server_error()?;
+
+        Ok(())
+    }
+
+    /// Turn down member who requested to join group through its invite link.
+    #[oai(path = "/groups/:id/requests/:number/deny", method = "post")]
+    async fn deny_request(
+        &self,
+        poem_openapi::param::Path(id): poem_openapi::param::Path<String>,
+        poem_openapi::param::Path(number): poem_openapi::param::Path<String>,
+        account: Query<Option<String>>,
+        signal: Signal<'_, '_>,
+    ) -> ResultPoem {
+        let member = requesting_member(number, account.as_deref(), &signal).await?;
+
+        signal
+            .remove_group_members(account.as_deref(), &id, &[member])
+            .await
+            .or_internal_server_error()?;
+
+        Ok(())
+    }
+}
+
+/// Number of member requesting to join group in E.164 form, or their identifier.
+async fn requesting_member(
+    number: String,
+    account: Option<&str>,
+    signal: &Daemon,
+) -> ResultPoem<String> {
+    let mut member = Recipient {
+        kind: RecipientKind::Person,
+        value: number,
+    };
+
+    resolve_recipient(&mut member, account, signal).await?;
+
+    Ok(member.value)
+}
+
+/// Avatar daemon stores for recipient, with content type detected from image itself.
+async fn avatar(
+    mut recipient: Recipient,
+    account: Option<&str>,
+    signal: &Daemon,
+) -> ResultPoem<Avatar> {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use jsonrpsee::core::client::Error as ErrorRpc;
+    use poem::error::NotFoundError;
+
+    resolve_recipient(&mut recipient, account, signal).await?;
+
+    let (person, group) = parse_recipient(&recipient)?;
+
+    // Daemon fails on unknown recipients and on those without avatar alike
+    let value = match signal.get_avatar(account, person, group).await {
+        Err(ErrorRpc::Call(_)) => return Err(NotFoundError.into()),
+        result => result.or_internal_server_error()?,
+    };
+
+    let data = value.get("data").and_then(serde_json::Value::as_str);
+
+    let bytes = STANDARD
+        .decode(data.ok_or(NotFoundError)?)
+        .or_internal_server_error()?;
+
+    let mime_type = infer::get(&bytes).map_or(staging::UNKNOWN, |t| t.mime_type());
+
+    Ok(Avatar::Image(Binary(bytes), mime_type.to_owned()))
+}
+
+/// Extract results of a send that failed for all recipients, which daemon attaches to its error.
+fn failed_send(error: &jsonrpsee::core::client::Error) -> Option<SendResp> {
+    use jsonrpsee::core::client::Error as ErrorRpc;
+
+    #[derive(serde::Deserialize)]
+    struct Failed {
+        response: SendResp,
+    }
+
+    let ErrorRpc::Call(error) = error else {
+        return None;
+    };
+
+    let failed: Failed = serde_json::from_str(error.data()?.get()).ok()?;
+
+    Some(failed.response)
+}
+
+/// Describe send of message in parts that failed midway, with how many of them were delivered.
+fn failed_parts(error: &jsonrpsee::core::client::Error) -> Option<String> {
+    use jsonrpsee::core::client::Error as ErrorRpc;
+    use serde::de::IgnoredAny;
+
+    #[derive(serde::Deserialize)]
+    struct Failed {
+        parts: usize,
+        delivered: Vec<IgnoredAny>,
+    }
+
+    let ErrorRpc::Call(call) = error else {
+        return None;
+    };
+
+    let Failed { parts, delivered } = serde_json::from_str(call.data()?.get()).ok()?;
+
+    let delivered = delivered.len();
+
+    Some(format!(
+        "Delivered {delivered} of {parts} parts of message, then failed: {}",
+        call.message()
+    ))
+}
+
+/// Describe identity that changed, with what is needed to trust it again.
+async fn untrusted(
+    signal: &Signal<'_, '_>,
+    trusting: &Trusting<'_>,
+    account: Option<&str>,
+    recipient: &str,
+) -> ResultPoem<Sent> {
+    use self::events::{Event, IdentityChange, Noticed};
+
+    let safety_number = safety_number(signal, account, recipient)
+        .await
+        .or_internal_server_error()?;
+
+    let (number, uuid) = if numbers::is_uuid(recipient) {
+        (None, Some(recipient.to_owned()))
+    } else {
+        (Some(recipient.to_owned()), None)
+    };
+
+    let change = IdentityChange {
+        number,
+        uuid,
+        safety_number: safety_number.clone(),
+        noticed: Noticed::Send,
+    };
+
+    signal.report_identity(Event::identity_changed(account.map(str::to_owned), change));
+
+    Ok(Sent::Untrusted(Json(UntrustedIdentity {
+        recipient: recipient.to_owned(),
+        safety_number,
+        trust: account.and_then(|a| trusting.trust(a, recipient)),
+    })))
+}
+
+/// Safety number of identity key daemon knows for recipient, if any.
+async fn safety_number(
+    signal: &Daemon,
+    account: Option<&str>,
+    recipient: &str,
+) -> Result<Option<String>, jsonrpsee::core::client::Error> {
+    let value = signal.list_identities(account, Some(recipient)).await?;
+
+    let identities: Vec<Identity> = serde_json::from_value(value)?;
+
+    Ok(identities.into_iter().find_map(|i| i.safety_number))
+}
+
+/// Resolve name to recipient it stands for, and bring number of person to E.164 form, failing
+/// on names standing for no recipient or several ones, and on invalid numbers.
+async fn resolve_recipient(
+    recipient: &mut Recipient,
+    account: Option<&str>,
+    signal: &Daemon,
+) -> ResultPoem<()> {
+    use poem::http::StatusCode;
+
+    use self::names::Resolved;
+
+    if matches!(recipient.kind, RecipientKind::Name) {
+        let name = &recipient.value;
+
+        (recipient.kind, recipient.value) = match names::resolve(signal, account, name)
+            .await
+            .or_internal_server_error()?
+        {
+            Resolved::Person(person) => (RecipientKind::Person, person),
+            Resolved::Group(group) => (RecipientKind::Group, group),
+            Resolved::Unresolved(0) => {
+                return unprocessable(&format!("No contact or group is named `{name}`"));
+            }
+            Resolved::Unresolved(count) => {
+                let msg = format!("{count} contacts and groups are named `{name}`, pick one by id");
+
+                return Err(poem::Error::from_string(msg, StatusCode::CONFLICT));
+            }
+        };
+    }
+
+    if !matches!(recipient.kind, RecipientKind::Person) {
+        return Ok(());
+    }
+
+    match numbers::e164(&recipient.value, signal.country_code()) {
+        Ok(Some(number)) => recipient.value = number,
+        Ok(None) => {}
+        Err(error) => {
+            return unprocessable(&format!("Invalid number `{}`: {error}", recipient.value));
+        }
+    }
+
+    Ok(())
+}
+
+/// Send message, recording outcome to audit log.
+#[expect(clippy::too_many_arguments)]
+async fn send_audited(
+    mut body: Send,
+    queued: Query<Option<bool>>,
+    signal: Signal<'_, '_>,
+    staging: Staged<'_>,
+    outbox: poem::web::Data<&Option<Arc<Outbox>>>,
+    uploads: poem::web::Data<&Option<Arc<Uploads>>>,
+    caller: Calling<'_>,
+    audit: Audited<'_>,
+    trusting: Trusting<'_>,
+) -> ResultPoem<Sent> {
+    use poem::web::Data;
+
+    // Store and match number of recipient in the form daemon reports it
+    let resolved = resolve_recipient(&mut body.recipient, body.account.as_deref(), &signal).await;
+
+    let account = body.account.clone();
+    let recipient = body.recipient.key();
+
+    let result = match resolved {
+        Ok(()) => {
+            send_resolved(
+                body,
+                queued,
+                signal,
+                staging,
+                outbox,
+                uploads,
+                Data(caller.0),
+                trusting,
+            )
+            .await
+        }
+        Err(error) => Err(error),
+    };
+
+    let outcome = result.as_ref().map(Sent::status);
+
+    audit_call(
+        audit.as_deref(),
+        &caller,
+        audit::Action::Send,
+        account,
+        recipient,
+        outcome,
+    )
+    .await;
+
+    result
+}
+
+/// Send message to recipient resolved already, turning away those caller may not reach.
+#[expect(clippy::too_many_arguments)]
+async fn send_resolved(
+    mut body: Send,
+    queued: Query<Option<bool>>,
+    signal: Signal<'_, '_>,
+    staging: Staged<'_>,
+    outbox: poem::web::Data<&Option<Arc<Outbox>>>,
+    uploads: poem::web::Data<&Option<Arc<Uploads>>>,
+    caller: Calling<'_>,
+    trusting: Trusting<'_>,
+) -> ResultPoem<Sent> {
+    use serde_json::from_value;
+
+    authorize(&caller, &body.recipient)?;
+
+    let (person, group) = parse_recipient(&body.recipient)?;
+
+    // Swap tokens of uploads for attachments they stand for, to be kept even if queued
+    for token in body.uploads.take().unwrap_or_default() {
+        let Some(attachment) = uploads.as_ref().and_then(|u| u.get(&token)) else {
+            return unprocessable(&format!("Unknown or expired upload `{token}`"));
+        };
+
+        body.attachments.get_or_insert_default().push(attachment);
+    }
+
+    // Count message against quotas of caller once it is known to be valid
+    if let Err(retry_after) = caller.spend() {
+        return Err(quota_exceeded(retry_after));
+    }
+
+    // Hold messages until quiet hours of recipient are over, unless they are urgent
+    let held = outbox
+        .as_ref()
+        .filter(|_| !body.urgent.unwrap_or_default())
+        .and_then(|outbox| outbox.quiet_until(&body.recipient.value));
+
+    // Accept message right away if requested, stored to be sent even across restarts
+    if queued.0.unwrap_or_default() || held.is_some() {
+        let Some(outbox) = outbox.as_ref() else {
+            return unprocessable("Queued sends require `--outbox`");
+        };
+
+        let entry = outbox
+            .push(body, caller.name.clone(), held)
+            .await
+            .or_internal_server_error()?;
+
+        return Ok(Sent::Queued(Json(entry)));
+    }
+
+    // Hand payloads over rather than copying them, attachments can be large
+    let attachments = body.attachments.take().unwrap_or_default();
+
+    let (attachments, _staged) = match staging.stage(attachments).await {
+        Ok(staged) => staged,
+        Err(error) if error.kind() == std::io::ErrorKind::InvalidData => {
+            return unprocessable(&format!("Invalid attachment: {error}"));
+        }
+        Err(error) => return Err(error).or_internal_server_error(),
+    };
+
+    // Render Markdown as text styles, rather than showing its delimiters
+    let (message, styles) = match body.text_mode {
+        Some(TextMode::Markdown) => markdown::styled(&body.message),
+        Some(TextMode::Plain) | None => (core::mem::take(&mut body.message), Vec::new()),
+    };
+
+    let account = body.account.as_deref();
+
+    let resp: SendResp = match signal
+        .send(account, person, group, &message, &attachments, &styles)
+        .await
+    {
+        Ok(value) => from_value(value).or_internal_server_error()?,
+        Err(error) => match failed_send(&error) {
+            Some(resp) if resp.untrusted().is_some() => resp,
+            _ => match failed_parts(&error) {
+                Some(msg) => {
+                    let status = poem::http::StatusCode::INTERNAL_SERVER_ERROR;
+
+                    return Err(poem::Error::from_string(msg, status));
+                }
+                None => return Err(error).or_internal_server_error(),
+            },
+        },
+    };
+
+    // Changed identity keys require action from client, make them stand out
+    if let Some(recipient) = resp.untrusted() {
+        return untrusted(&signal, &trusting, account, recipient).await;
+    }
+
+    Ok(Sent::Delivered(Json(resp)))
+}
+
+/// Append call to audit log if one is kept, with status it was answered with.
+async fn audit_call(
+    audit: Option<&Audit>,
+    caller: &Caller,
+    action: audit::Action,
+    account: Option<String>,
+    recipient: String,
+    outcome: Result<u16, &poem::Error>,
+) {
+    let Some(audit) = audit else {
+        return;
+    };
+
+    let (status, error) = match outcome {
+        Ok(status) => (status, None),
+        Err(error) => (error.status().as_u16(), Some(error.to_string())),
+    };
+
+    let record = audit::Record {
+        timestamp: outbox::now(),
+        caller: caller.name.clone(),
+        action,
+        account,
+        recipient,
+        status,
+        error,
+    };
+
+    audit.record(&record).await;
+}
+
+/// Fail with forbidden status if caller is restricted to other recipients.
+#[expect(clippy::result_large_err)]
+fn authorize(caller: &Caller, recipient: &Recipient) -> ResultPoem<()> {
+    use poem::http::StatusCode;
+
+    let key = recipient.key();
+
+    if caller.may_reach(&key) {
+        return Ok(());
+    }
+
+    let name = caller.name.as_deref().unwrap_or_default();
+    let msg = format!("API token `{name}` may not reach `{key}`");
+
+    Err(poem::Error::from_string(msg, StatusCode::FORBIDDEN))
+}
+
+#[expect(clippy::result_large_err)]
+fn parse_recipient(recipient: &Recipient) -> ResultPoem<(Option<&str>, Option<&str>)> {
+    match recipient.kind {
+        RecipientKind::Person => Ok((Some(&recipient.value), None)),
+        RecipientKind::Name => unprocessable("Names of recipients must be resolved first"),
+        RecipientKind::Group => {
+            check_group_id(&recipient.value)?;
+
+            Ok((None, Some(&recipient.value)))
+        }
+    }
+}
+
+/// Contact card in vCard 3.0 format, with fields escaped.
+fn vcard(name: &str, number: Option<&str>, email: Option<&str>) -> String {
+    use core::fmt::Write;
+
+    let escape = |value: &str| {
+        value
+            .replace('\\', "\\\\")
+            .replace(',', "\\,")
+            .replace(';', "\\;")
+            .replace('\n', "\\n")
+    };
+
+    let name = escape(name);
+
+    // Structured name is required too, whole name standing as given name
+    let mut card = format!("BEGIN:VCARD\r\nVERSION:3.0\r\nN:;{name};;;\r\nFN:{name}\r\n");
+
+    if let Some(number) = number {
+        let _ = write!(card, "TEL;TYPE=CELL:{}\r\n", escape(number));
+    }
+
+    if let Some(email) = email {
+        let _ = write!(card, "EMAIL:{}\r\n", escape(email));
+    }
+
+    card.push_str("END:VCARD\r\n");
+
+    card
+}
+
+#[expect(clippy::result_large_err)]
+fn unprocessable<T>(msg: &str) -> ResultPoem<T> {
+    use poem::error::Error;
+    use poem::http::StatusCode;
+
+    Err(Error::from_string(msg, StatusCode::UNPROCESSABLE_ENTITY))
+}
+
+/// Tell client daemon is unreachable, and when it is worth trying again.
+fn unavailable(error: &impl Error) -> poem::Error {
+    use poem::Response;
+    use poem::http::StatusCode;
+    use poem::http::header::RETRY_AFTER;
+
+    /// Seconds client should wait for, while connection is being re-established.
+    const RETRY_AFTER_SECS: u64 = 5;
+
+    let resp = Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header(RETRY_AFTER, RETRY_AFTER_SECS)
+        .body(format!("Daemon is unavailable: {error}"));
+
+    poem::Error::from_response(resp)
+}
+
+/// Tell client its quota of messages is used up, and when it has room again.
+fn quota_exceeded(retry_after: Duration) -> poem::Error {
+    use poem::Response;
+    use poem::http::StatusCode;
+    use poem::http::header::RETRY_AFTER;
+
+    let resp = Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header(RETRY_AFTER, retry_after.as_secs() + 1)
+        .body("Quota of messages of API token is used up");
+
+    poem::Error::from_response(resp)
+}
+
+/// Tell client daemon is rate limited, and when it is worth trying again.
+fn too_many_requests(error: &impl Error, retry_after: Option<Duration>) -> poem::Error {
+    use poem::Response;
+    use poem::http::StatusCode;
+    use poem::http::header::RETRY_AFTER;
+
+    /// Seconds client should wait for, when daemon does not tell.
+    const RETRY_AFTER_SECS: u64 = 60;
+
+    let retry_after = retry_after.map_or(RETRY_AFTER_SECS, |d| d.as_secs());
+
+    let resp = Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header(RETRY_AFTER, retry_after)
+        .body(format!("Daemon is rate limited: {error}"));
+
+    poem::Error::from_response(resp)
+}
+
+#[derive(Object)]
+struct Webhook {
+    /// Either `default`, or number of account whose messages are forwarded to webhook.
+    id: String,
+    url: String,
+}
+
+#[derive(Object)]
+struct NewWebhook {
+    /// Account whose messages to forward to webhook, replacing default one when absent.
+    account: Option<String>,
+    url: String,
+}
+
+#[derive(Object)]
+struct Injected {
+    /// Number of subscriptions event was delivered to.
+    subscriptions: usize,
+}
+
+#[derive(ApiResponse)]
+enum Avatar {
+    /// Image, as JPEG or PNG usually.
+    #[oai(status = 200)]
+    Image(Binary<Vec<u8>>, #[oai(header = "Content-Type")] String),
+}
+
+#[derive(ApiResponse)]
+enum Readiness {
+    /// Daemon is connected and answering requests.
+    #[oai(status = 204)]
+    Ready,
+
+    /// Connection to daemon or subscription to incoming messages is down or being re-established.
+    #[oai(status = 503)]
+    NotReady,
+}
+
+#[derive(Object)]
+struct React {
+    /// Account to act as, when daemon serves several.
+    account: Option<String>,
+    recipient: Recipient,
+
+    /// Single emoji, skin tone and other variations included.
+    emoji: String,
+    author: String,
+    timestamp: u64,
+    remove: Option<bool>,
+}
+
+#[derive(Object)]
+struct Receive {
+    /// Account to act as, when daemon serves several.
+    account: Option<String>,
+    recipient: String,
+    timestamp: u64,
+    kind: Option<ReceiptKind>,
+}
+
+#[derive(Enum, Clone, Copy)]
+#[oai(rename_all(lowercase))]
+enum ReceiptKind {
+    Read,
+    Viewed,
+}
+
+impl ReceiptKind {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Viewed => "viewed",
+        }
+    }
+}
+
+#[derive(Object, serde::Deserialize, serde::Serialize)]
+struct Send {
+    /// Account to act as, when daemon serves several.
+    account: Option<String>,
+    recipient: Recipient,
+    message: String,
+
+    /// Syntax of message, plain text by default.
+    text_mode: Option<TextMode>,
+
+    /// Base64 payloads, or data URIs to set content type rather than have it detected.
+    attachments: Option<Vec<String>>,
+
+    /// Tokens of attachments stored with `POST /attachments`, sent after other attachments.
+    uploads: Option<Vec<String>>,
+
+    /// Send even during quiet hours of recipient.
+    urgent: Option<bool>,
+}
+
+/// Message to send, in whichever form client is able to produce.
+#[derive(poem_openapi::ApiRequest)]
+enum SendBody {
+    Json(Json<Send>),
+    Form(poem_openapi::payload::Form<SendForm>),
+
+    /// Message, sent to `recipient` query parameter, from `account` one if given.
+    Text(poem_openapi::payload::PlainText<String>),
+}
+
+impl SendBody {
+    /// Message to send, addressed with query parameters if body is plain text.
+    #[expect(clippy::result_large_err)]
+    fn into_send(self, recipient: Option<String>, account: Option<String>) -> ResultPoem<Send> {
+        let (account, recipient, message, urgent) = match self {
+            Self::Json(Json(send)) => return Ok(send),
+            Self::Form(form) => {
+                let SendForm {
+                    account,
+                    recipient,
+                    message,
+                    urgent,
+                } = form.0;
+
+                (account, recipient, message, urgent)
+            }
+            Self::Text(text) => {
+                let Some(recipient) = recipient else {
+                    return unprocessable("Missing `recipient` query parameter");
+                };
+
+                (account, recipient, text.0, None)
+            }
+        };
+
+        // Clients leaving `+` of numbers unencoded have it decoded as a space
+        let recipient = match recipient.strip_prefix(' ') {
+            Some(number) => format!("+{number}"),
+            None => recipient,
+        };
+
+        Ok(Send {
+            account,
+            recipient: RecipientForm::Prefixed(recipient).into(),
+            message,
+            text_mode: None,
+            attachments: None,
+            uploads: None,
+            urgent,
+        })
+    }
+}
+
+/// Message given as form fields, for clients unable to produce JSON.
+#[derive(Object, serde::Deserialize)]
+struct SendForm {
+    /// Account to act as, when daemon serves several.
+    account: Option<String>,
+
+    /// Number, identifier or username of person, identifier of group after `group.`, or name
+    /// of contact or title of group.
+    recipient: String,
+    message: String,
+
+    /// Send even during quiet hours of recipient.
+    urgent: Option<bool>,
+}
+
+/// Attachment stored for later sends.
+#[derive(Object)]
+struct Uploaded {
+    /// Identifier to list in `uploads` of sends.
+    token: String,
+
+    /// Milliseconds since Unix epoch after which token is no longer valid.
+    expires: u64,
+}
+
+/// Contact to share, with at least one way to reach it.
+#[derive(Object)]
+struct SendContact {
+    /// Account to act as, when daemon serves several.
+    account: Option<String>,
+    recipient: Recipient,
+
+    /// Full name of contact, also sent as text of message.
+    name: String,
+    number: Option<String>,
+    email: Option<String>,
+
+    /// Send even during quiet hours of recipient.
+    urgent: Option<bool>,
+}
+
+#[derive(ApiResponse)]
+enum Sent {
+    /// Message was sent, possibly failing for some recipients.
+    #[oai(status = 200)]
+    Delivered(Json<SendResp>),
+
+    /// Identity key of recipient changed, and must be trusted before sending again.
+    #[oai(status = 409)]
+    Untrusted(Json<UntrustedIdentity>),
+
+    /// Message was stored in outbox, to be sent in the background.
+    #[oai(status = 202)]
+    Queued(Json<outbox::Entry>),
+}
+
+impl Sent {
+    const fn status(&self) -> u16 {
+        match self {
+            Self::Delivered(_) => 200,
+            Self::Untrusted(_) => 409,
+            Self::Queued(_) => 202,
+        }
+    }
+}
+
+/// Outcome of a single message of a batch, with status code it would have been sent alone.
+#[derive(Object, Default)]
+struct BatchResult {
+    status: u16,
+    sent: Option<SendResp>,
+    untrusted: Option<UntrustedIdentity>,
+    queued: Option<outbox::Entry>,
+    error: Option<String>,
+}
+
+impl BatchResult {
+    fn sent(resp: SendResp) -> Self {
+        Self {
+            status: 200,
+            sent: Some(resp),
+            ..Self::default()
+        }
+    }
+
+    fn untrusted(identity: UntrustedIdentity) -> Self {
+        Self {
+            status: 409,
+            untrusted: Some(identity),
+            ..Self::default()
+        }
+    }
+
+    fn queued(entry: outbox::Entry) -> Self {
+        Self {
+            status: 202,
+            queued: Some(entry),
+            ..Self::default()
+        }
+    }
+
+    fn failed(error: &poem::Error) -> Self {
+        Self {
+            status: error.status().as_u16(),
+            error: Some(error.to_string()),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Object)]
+struct UntrustedIdentity {
+    recipient: String,
+
+    /// New safety number to verify with recipient, out of band.
+    safety_number: Option<String>,
+
+    /// Endpoint of compatibility API trusting new identity key, with `PUT`, if it is exposed.
+    trust: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct Identity {
+    #[serde(rename = "safetyNumber")]
+    safety_number: Option<String>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct SendResp {
+    timestamp: u64,
+
+    /// Outcome of delivery to each recipient.
+    #[serde(default)]
+    results: Vec<SendResult>,
+}
+
+impl SendResp {
+    /// Number, or identifier otherwise, of first recipient whose identity key changed.
+    fn untrusted(&self) -> Option<&str> {
+        let mut results = self.results.iter();
+
+        let result = results.find(|r| r.status == DeliveryStatus::IdentityFailure)?;
+
+        let Address { number, uuid } = &result.recipient;
+
+        number.as_deref().or(uuid.as_deref())
+    }
+}
+
+#[derive(Object, serde::Deserialize)]
+struct SendResult {
+    #[serde(rename = "recipientAddress")]
+    recipient: Address,
+
+    #[serde(rename = "type")]
+    status: DeliveryStatus,
+
+    /// Seconds to wait for before sending again, when rate limited.
+    #[serde(rename = "retryAfterSeconds")]
+    retry_after: Option<u64>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct Address {
+    number: Option<String>,
+    uuid: Option<String>,
+}
+
+#[derive(Enum, serde::Deserialize, PartialEq, Eq)]
+#[oai(rename_all = "snake_case")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum DeliveryStatus {
+    Success,
+    NetworkFailure,
+    UnregisteredFailure,
+    IdentityFailure,
+    RateLimitFailure,
+    ProofRequiredFailure,
+
+    /// Failure kind introduced by a newer daemon.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Object)]
+struct Typing {
+    /// Account to act as, when daemon serves several.
+    account: Option<String>,
+    recipient: Recipient,
+    stop: bool,
+}
+
+#[derive(Object)]
+struct Configuration {
+    /// Account to act as, when daemon serves several.
+    account: Option<String>,
+
+    /// Whether to tell senders their messages were read.
+    read_receipts: Option<bool>,
+
+    /// Whether to show sealed sender indicators on messages.
+    unidentified_delivery_indicators: Option<bool>,
+
+    /// Whether to tell others when account is typing, and be told when they are.
+    typing_indicators: Option<bool>,
+
+    /// Whether to generate previews of links in messages sent.
+    link_previews: Option<bool>,
+}
+
+#[derive(Object)]
+struct StickerPack {
+    /// Account to act as, when daemon serves several.
+    account: Option<String>,
+
+    /// Link to pack, as `https://signal.art/addstickers/#pack_id=...&pack_key=...`.
+    url: String,
+}
+
+/// Person or group to send to, whichever form it was given in.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct Recipient {
+    kind: RecipientKind,
+    value: String,
+}
+
+impl Recipient {
+    /// Number, identifier or username of person, or identifier of group after `group.`.
+    fn key(&self) -> String {
+        match self.kind {
+            RecipientKind::Group => format!("group.{}", self.value),
+            RecipientKind::Person | RecipientKind::Name => self.value.clone(),
+        }
+    }
+}
+
+/// Person or group, given as `{kind, value}` or as a single string, `group.` prefixing groups.
+#[derive(poem_openapi::Union)]
+#[oai(rename = "Recipient", one_of)]
+enum RecipientForm {
+    Structured(StructuredRecipient),
+
+    /// Number, identifier or username of person, identifier of group after `group.`, or name
+    /// of contact or title of group
+    Prefixed(String),
+}
+
+#[derive(Object)]
+struct StructuredRecipient {
+    kind: RecipientKind,
+    value: String,
+}
+
+impl From<RecipientForm> for Recipient {
+    fn from(form: RecipientForm) -> Self {
+        let (kind, value) = match form {
+            RecipientForm::Structured(StructuredRecipient { kind, value }) => (kind, value),
+            RecipientForm::Prefixed(value) => match value.strip_prefix("group.") {
+                None if is_username(&value) => (RecipientKind::Person, format!("u:{value}")),
+                None if is_name(&value) => (RecipientKind::Name, value),
+                None => (RecipientKind::Person, value),
+                Some(id) => (RecipientKind::Group, unwrap_group_id(id)),
+            },
+        };
+
+        Self { kind, value }
+    }
+}
+
+/// Identifier of group given after `group.`, checked as [`parse_recipient`] does.
+#[expect(clippy::result_large_err)]
+fn group_id(prefixed: &str) -> ResultPoem<String> {
+    let Some(id) = prefixed.strip_prefix("group.") else {
+        return unprocessable("Group id must start with `group.`");
+    };
+
+    let id = unwrap_group_id(id);
+
+    check_group_id(&id)?;
+
+    Ok(id)
+}
+
+/// Identifier of group, decoded once if encoded once more as upstream API does, unlike signal-cli.
+fn unwrap_group_id(id: &str) -> String {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    let decoded = STANDARD
+        .decode(id)
+        .ok()
+        .and_then(|b| String::from_utf8(b).ok());
+
+    decoded
+        .filter(|d| check_group_id(d).is_ok())
+        .unwrap_or_else(|| id.to_owned())
+}
+
+/// Check identifier of group is base64 encoding of 32 bytes.
+#[expect(clippy::result_large_err)]
+fn check_group_id(id: &str) -> ResultPoem<()> {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    let Ok(bytes) = STANDARD.decode(id) else {
+        return unprocessable("Group id is not valid base64");
+    };
+
+    if bytes.len() != 32 {
+        return unprocessable("Invalid group id");
+    }
+
+    Ok(())
+}
+
+/// Whether value is neither number, identifier nor username of person, but a name to resolve.
+fn is_name(value: &str) -> bool {
+    value.contains(char::is_alphabetic) && !numbers::is_uuid(value) && !value.starts_with("u:")
+}
+
+/// Whether URL links to a sticker pack, naming both its identifier and key.
+fn is_sticker_pack(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+
+    let fields: Vec<_> = url
+        .fragment()
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|field| field.split_once('='))
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(key, _)| key)
+        .collect();
+
+    url.scheme() == "https"
+        && url.host_str() == Some("signal.art")
+        && url.path() == "/addstickers/"
+        && fields.contains(&"pack_id")
+        && fields.contains(&"pack_key")
+}
+
+/// Whether value is a username, as nickname then `.` and digits, which daemon takes after `u:`.
+fn is_username(value: &str) -> bool {
+    let Some((nickname, discriminator)) = value.rsplit_once('.') else {
+        return false;
+    };
+
+    let starts_with_letter = nickname.chars().next().is_some_and(char::is_alphabetic);
+
+    starts_with_letter
+        && nickname.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && discriminator.len() >= 2
+        && discriminator.chars().all(|c| c.is_ascii_digit())
+}
+
+// Parse recipients from any of their forms, and describe them as such
+
+impl poem_openapi::types::Type for Recipient {
+    const IS_REQUIRED: bool = true;
+
+    type RawValueType = Self;
+
+    type RawElementValueType = Self;
+
+    fn name() -> std::borrow::Cow<'static, str> {
+        RecipientForm::name()
+    }
+
+    fn schema_ref() -> poem_openapi::registry::MetaSchemaRef {
+        RecipientForm::schema_ref()
+    }
+
+    fn register(registry: &mut poem_openapi::registry::Registry) {
+        RecipientForm::register(registry);
+    }
+
+    fn as_raw_value(&self) -> Option<&Self::RawValueType> {
+        Some(self)
+    }
+
+    fn raw_element_iter<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = &'a Self::RawElementValueType> + 'a> {
+        Box::new(self.as_raw_value().into_iter())
+    }
+}
+
+impl poem_openapi::types::ParseFromJSON for Recipient {
+    fn parse_from_json(value: Option<serde_json::Value>) -> poem_openapi::types::ParseResult<Self> {
+        use poem_openapi::types::ParseError;
+
+        let form = RecipientForm::parse_from_json(value).map_err(ParseError::propagate)?;
+
+        Ok(form.into())
+    }
+}
+
+impl poem_openapi::types::ToJSON for Recipient {
+    fn to_json(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self).ok()
+    }
+}
+
+/// Syntax messages are written in.
+#[derive(Enum, Clone, Copy, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all(lowercase))]
+#[serde(rename_all = "lowercase")]
+enum TextMode {
+    /// Text shown as it is
+    Plain,
+
+    /// Bold, italic, strikethrough, code and `||spoiler||` spans, shown as text styles
+    Markdown,
+}
+
+#[derive(Enum, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all(lowercase))]
+#[serde(rename_all = "lowercase")]
+enum RecipientKind {
+    Person,
+    Group,
+    /// Name of contact or title of group, resolved among those of account
+    Name,
+}
+
+trait OrInternalServerError<T> {
+    #[expect(clippy::result_large_err)]
+    fn or_internal_server_error(self) -> ResultPoem<T>;
+}
+
+impl<T, E: 'static + core::marker::Send + Sync + Error> OrInternalServerError<T> for Result<T, E> {
+    fn or_internal_server_error(self) -> ResultPoem<T> {
+        use core::any::Any;
+
+        use jsonrpsee::core::client::Error as ErrorRpc;
+
+        self.map_err(|error| {
+            let rpc = (&error as &dyn Any).downcast_ref();
+
+            if let Some(limited) = rpc.and_then(RateLimited::of) {
+                return too_many_requests(&error, limited.retry_after);
+            }
+
+            // Daemon failing to answer in time is not an error of ours
+            match rpc {
+                Some(ErrorRpc::RequestTimeout) => poem::error::GatewayTimeout(error),
+                Some(ErrorRpc::RestartNeeded(_)) => unavailable(&error),
+                _ => poem::error::InternalServerError(error),
+            }
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+/// Address to accept HTTP requests on, and routes served there.
+#[derive(Clone)]
+pub struct Listener {
+    /// Address to bind to, as `host:port`.
+    pub addr: String,
+
+    /// Prefixes of paths served, all of them if empty.
+    pub routes: Vec<String>,
+}
+
+/// Parse listener as `host:port`, or as `host:port=/prefix,/prefix` to serve those paths only.
+pub fn parse(arg: &str) -> Result<Listener, String> {
+    let (addr, routes) = match arg.split_once('=') {
+        Some((addr, routes)) => (addr, routes.split(',').map(str::to_owned).collect()),
+        None => (arg, Vec::new()),
+    };
+
+    let port = addr.rsplit_once(':').map(|(_, port)| port.parse::<u16>());
+
+    if !matches!(port, Some(Ok(_))) {
+        return Err(format!("expected `host:port`, got `{addr}`"));
+    }
+
+    if let Some(route) = routes.iter().find(|r: &&String| !r.starts_with('/')) {
+        return Err(format!(
+            "expected path prefix starting with `/`, got `{route}`"
+        ));
+    }
+
+    Ok(Listener {
+        addr: addr.to_owned(),
+        routes,
+    })
+}
+
+/// Serve app on each listener at once, and over HTTPS on secure one if any, until one of them
+/// fails.
+pub async fn serve(
+    app: impl poem::Endpoint + 'static,
+    listeners: Vec<Listener>,
+    #[cfg(feature = "acme")] secure: Option<(Listener, poem::listener::acme::AutoCert)>,
+    name: &str,
+) -> std::io::Result<()> {
+    use futures_util::future::try_join_all;
+    use poem::listener::{Listener as _, TcpListener};
+    use poem::{EndpointExt, Server};
+
+    let app = Arc::new(app.map_to_response());
+
+    // Plain listeners, then secure one with certificate handled for it
+    #[cfg(feature = "acme")]
+    let secure = secure.map(|(listener, cert)| {
+        let acceptor = TcpListener::bind(listener.addr).acme(cert).boxed();
+
+        (acceptor, listener.routes)
+    });
+
+    #[cfg(not(feature = "acme"))]
+    let secure = None;
+
+    let bound = listeners
+        .into_iter()
+        .map(|listener| (TcpListener::bind(listener.addr).boxed(), listener.routes))
+        .chain(secure);
+
+    let servers = bound.map(|(listener, routes)| {
+        let routes = Arc::new(routes);
+        let app = Arc::clone(&app);
+
+        let app = app.around(move |next, req| restrict(next, req, Arc::clone(&routes)));
+
+        Server::new(listener).name(name).run(app)
+    });
+
+    try_join_all(servers).await?;
+
+    Ok(())
+}
+
+/// Answer not found to requests for paths listener does not serve.
+async fn restrict<E: poem::Endpoint>(
+    next: E,
+    req: poem::Request,
+    routes: Arc<Vec<String>>,
+) -> poem::Result<poem::Response> {
+    use poem::IntoResponse;
+    use poem::error::NotFoundError;
+
+    let path = req.uri().path();
+
+    if !routes.is_empty() && !routes.iter().any(|r| path.starts_with(r.as_str())) {
+        return Err(NotFoundError.into());
+    }
+
+    Ok(next.call(req).await?.into_response())
+}
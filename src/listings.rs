@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// Groups or contacts of each account as listed by daemon, kept until stale or changed.
+pub struct Listings {
+    /// Duration listings are served from cache for, caching is disabled when zero.
+    ttl: Duration,
+
+    /// Listings by account, `None` standing for default account of daemon.
+    entries: Mutex<HashMap<Option<String>, (Instant, Value)>>,
+}
+
+impl Listings {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Listing of account, if cached recently enough.
+    pub fn get(&self, account: Option<&str>) -> Option<Value> {
+        let (at, listing) = self
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&account.map(str::to_owned))
+            .cloned()?;
+
+        (at.elapsed() < self.ttl).then_some(listing)
+    }
+
+    pub fn insert(&self, account: Option<&str>, listing: Value) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        entries.insert(account.map(str::to_owned), (Instant::now(), listing));
+    }
+
+    /// Forget listing of account, and that of default account since it may be the same one.
+    pub fn invalidate(&self, account: Option<&str>) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match account {
+            None => entries.clear(),
+            Some(account) => {
+                entries.remove(&Some(account.to_owned()));
+                entries.remove(&None);
+            }
+        }
+    }
+}
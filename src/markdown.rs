@@ -0,0 +1,167 @@
+/// Delimiters of Markdown spans, longest first for them to be recognized before their prefixes.
+const DELIMITERS: [(&str, &str); 8] = [
+    ("```", "MONOSPACE"),
+    ("**", "BOLD"),
+    ("__", "BOLD"),
+    ("~~", "STRIKETHROUGH"),
+    ("||", "SPOILER"),
+    ("`", "MONOSPACE"),
+    ("*", "ITALIC"),
+    ("_", "ITALIC"),
+];
+
+/// Text of basic Markdown without its delimiters, with style ranges as daemon takes them.
+///
+/// Ranges are `start:length:STYLE`, counted in UTF-16 code units like Signal clients do. Code is
+/// kept verbatim, and delimiters without a closing one are kept as they are.
+pub fn styled(markdown: &str) -> (String, Vec<String>) {
+    let mut text = String::with_capacity(markdown.len());
+
+    let mut styles = Vec::new();
+
+    parse(markdown, &mut text, &mut styles);
+
+    (text, styles)
+}
+
+/// Append text of Markdown to `text`, and ranges of its styles to `styles`.
+fn parse(markdown: &str, text: &mut String, styles: &mut Vec<String>) {
+    let mut rest = markdown;
+
+    while let Some(c) = rest.chars().next() {
+        // Keep escaped characters as they are, delimiters included
+        if c == '\\'
+            && let Some(escaped) = rest[1..].chars().next().filter(char::is_ascii_punctuation)
+        {
+            text.push(escaped);
+            rest = &rest[1 + escaped.len_utf8()..];
+            continue;
+        }
+
+        if let Some((inner, after, style)) = span(rest, text) {
+            let start = text.encode_utf16().count();
+
+            if style == "MONOSPACE" {
+                text.push_str(inner);
+            } else {
+                parse(inner, text, styles);
+            }
+
+            let length = text.encode_utf16().count() - start;
+
+            styles.push(format!("{start}:{length}:{style}"));
+
+            rest = after;
+            continue;
+        }
+
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+}
+
+/// Content of span opening at start of Markdown, what follows it, and its style, if it is closed.
+fn span<'a>(markdown: &'a str, text: &str) -> Option<(&'a str, &'a str, &'static str)> {
+    let (delimiter, style) = DELIMITERS.iter().find(|(d, _)| markdown.starts_with(d))?;
+
+    // Underscores within words are part of them, as in identifiers
+    let intraword = text.chars().next_back().is_some_and(char::is_alphanumeric);
+
+    if delimiter.starts_with('_') && intraword {
+        return None;
+    }
+
+    let after = &markdown[delimiter.len()..];
+
+    let end = after.find(delimiter).filter(|&end| end > 0)?;
+
+    let inner = &after[..end];
+
+    // Code blocks hold whole lines, without line breaks around them
+    if *delimiter == "```" {
+        return Some((inner.trim_matches('\n'), &after[end + 3..], style));
+    }
+
+    // Spans neither start nor end with whitespace, for lone delimiters to stay as they are
+    if inner.starts_with(char::is_whitespace) || inner.ends_with(char::is_whitespace) {
+        return None;
+    }
+
+    Some((inner, &after[end + delimiter.len()..], style))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::styled;
+
+    fn assert_styled(markdown: &str, text: &str, styles: &[&str]) {
+        let (actual, ranges) = styled(markdown);
+
+        assert_eq!(actual, text);
+        assert_eq!(ranges, styles);
+    }
+
+    #[test]
+    fn spans_are_ranges_of_text_without_delimiters() {
+        assert_styled(
+            "**bold** and *it* or ~~no~~ ||spoiler||",
+            "bold and it or no spoiler",
+            &[
+                "0:4:BOLD",
+                "9:2:ITALIC",
+                "15:2:STRIKETHROUGH",
+                "18:7:SPOILER",
+            ],
+        );
+    }
+
+    #[test]
+    fn ranges_count_utf16_code_units() {
+        // Emoji outside of basic plane takes two units, accented letter one
+        assert_styled("😀 é **hi**", "😀 é hi", &["5:2:BOLD"]);
+        assert_styled("*é😀*", "é😀", &["0:3:ITALIC"]);
+    }
+
+    #[test]
+    fn nested_spans_come_before_enclosing_ones() {
+        assert_styled(
+            "**bold _both_** __also__",
+            "bold both also",
+            &["5:4:ITALIC", "0:9:BOLD", "10:4:BOLD"],
+        );
+    }
+
+    #[test]
+    fn underscores_within_words_are_kept() {
+        assert_styled("snake_case_name", "snake_case_name", &[]);
+        assert_styled(
+            "call my_fn or _this_",
+            "call my_fn or this",
+            &["14:4:ITALIC"],
+        );
+    }
+
+    #[test]
+    fn escaped_delimiters_are_kept() {
+        assert_styled(r"\*not italic\*", "*not italic*", &[]);
+        assert_styled(r"**a\*b**", "a*b", &["0:3:BOLD"]);
+        assert_styled(r"C:\path", r"C:\path", &[]);
+    }
+
+    #[test]
+    fn unclosed_delimiters_are_kept() {
+        assert_styled("**open", "**open", &[]);
+        assert_styled("a * b *", "a * b *", &[]);
+        assert_styled("`tick", "`tick", &[]);
+    }
+
+    #[test]
+    fn code_is_kept_verbatim() {
+        assert_styled("`*a*`", "*a*", &["0:3:MONOSPACE"]);
+        assert_styled(
+            "see:\n```\nlet *x* = 1;\n```\nend",
+            "see:\nlet *x* = 1;\nend",
+            &["5:12:MONOSPACE"],
+        );
+    }
+}
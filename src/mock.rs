@@ -0,0 +1,348 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use color_eyre::eyre::Result;
+use serde_json::{Value, json};
+use tokio::io::DuplexStream;
+use tokio::sync::broadcast;
+
+/// Number of injected events kept for connections lagging behind.
+const EVENTS_CAPACITY: usize = 64;
+
+/// Size in bytes of each in-memory pipe between bridge and mock.
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+/// In-process stand-in for `signal-cli` daemon, for clients to test against without an account.
+pub struct Mock {
+    /// Scripted replies to methods, by name, taking precedence over built-in ones.
+    replies: HashMap<String, Reply>,
+
+    /// Traffic recorded with `--record`, served after scripted replies.
+    recording: Mutex<Recording>,
+
+    /// Injected incoming events, forwarded to every subscription.
+    events: broadcast::Sender<Value>,
+}
+
+/// Outcome of a scripted method call.
+#[derive(serde::Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+enum Reply {
+    Result(Value),
+
+    /// JSON-RPC error object, with `code` and `message` fields.
+    Error(Value),
+}
+
+/// Responses and incoming events of recorded traffic, served in recorded order.
+#[derive(Default)]
+struct Recording {
+    /// Replies to methods, by name, the last one repeated once earlier ones are used up.
+    replies: HashMap<String, VecDeque<Reply>>,
+
+    /// Incoming events, by account subscribed to, delivered to first subscription of account.
+    events: HashMap<Option<String>, Vec<Value>>,
+}
+
+impl Mock {
+    /// Load replies from JSON object mapping method names to `{"result": ...}` or `{"error": ...}`,
+    /// and traffic from directory recorded with `--record`, to replay.
+    pub fn new(script: Option<&Path>, replay: Option<&Path>) -> Result<Arc<Self>> {
+        let replies = match script {
+            None => HashMap::new(),
+            Some(path) => serde_json::from_slice(&std::fs::read(path)?)?,
+        };
+
+        let recording = match replay {
+            None => Recording::default(),
+            Some(dir) => Recording::load(dir)?,
+        };
+
+        let (events, _) = broadcast::channel(EVENTS_CAPACITY);
+
+        Ok(Arc::new(Self {
+            replies,
+            recording: Mutex::new(recording),
+            events,
+        }))
+    }
+
+    /// Open connection to mock, served in the background until dropped.
+    pub fn connect(self: &Arc<Self>) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(PIPE_CAPACITY);
+
+        tokio::spawn(Arc::clone(self).serve(server));
+
+        client
+    }
+
+    /// Deliver event to subscriptions as if it had been received, returning how many got it.
+    pub fn inject(&self, event: Value) -> usize {
+        self.events.send(event).unwrap_or_default()
+    }
+
+    async fn serve(self: Arc<Self>, io: DuplexStream) {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (reader, mut writer) = tokio::io::split(io);
+
+        let mut lines = BufReader::new(reader).lines();
+
+        let mut events = self.events.subscribe();
+
+        // Identifiers of active subscriptions, incremented for each new one
+        let mut subscriptions = Vec::new();
+        let mut next = 0_u64;
+
+        loop {
+            let msg = tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) => self.answer(&line, &mut subscriptions, &mut next),
+                    Ok(None) | Err(_) => return,
+                },
+                event = events.recv() => match event {
+                    Ok(event) => notifications(&subscriptions, &event),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::warn!("Mock daemon dropped {missed} injected events");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+            };
+
+            if writer.write_all(msg.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Reply to request line, as newline-terminated JSON.
+    fn answer(&self, line: &str, subscriptions: &mut Vec<u64>, next: &mut u64) -> String {
+        let Ok(request) = serde_json::from_str::<Value>(line) else {
+            return String::new();
+        };
+
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+
+        let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        // Recorded events of account subscribed to, following confirmation of subscription
+        let mut replayed = String::new();
+
+        let reply = match method {
+            "subscribeReceive" => {
+                *next += 1;
+                subscriptions.push(*next);
+
+                let account = params.get("account").and_then(Value::as_str);
+
+                replayed = self.replayed(account, *next);
+
+                Reply::Result(json!(*next))
+            }
+            "unsubscribeReceive" => {
+                let subscription = params.get("subscription").and_then(Value::as_u64);
+
+                subscriptions.retain(|s| Some(*s) != subscription);
+
+                Reply::Result(json!(true))
+            }
+            method => self
+                .replies
+                .get(method)
+                .cloned()
+                .or_else(|| self.recorded(method))
+                .unwrap_or_else(|| Reply::Result(default(method, &params))),
+        };
+
+        let resp = match reply {
+            Reply::Result(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Reply::Error(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+        };
+
+        format!("{resp}\n{replayed}")
+    }
+
+    /// Next recorded reply to method, if any was recorded.
+    fn recorded(&self, method: &str) -> Option<Reply> {
+        self.recording
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .reply(method)
+    }
+
+    /// Notifications of events recorded for account to new subscription, only replayed once.
+    fn replayed(&self, account: Option<&str>, subscription: u64) -> String {
+        let events = self
+            .recording
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .events
+            .remove(&account.map(str::to_owned))
+            .unwrap_or_default();
+
+        events
+            .iter()
+            .map(|event| notifications(&[subscription], event))
+            .collect()
+    }
+}
+
+impl Recording {
+    /// Pair recorded requests with their responses, and subscriptions with their events.
+    fn load(dir: &Path) -> Result<Self> {
+        use color_eyre::eyre::WrapErr;
+
+        use super::transport::traffic::RECORDING;
+
+        let path = dir.join(RECORDING);
+
+        let file = std::fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Failed to read recording {}", path.display()))?;
+
+        let mut recording = Self::default();
+
+        // Requests awaiting response, by identifier, and accounts of subscriptions
+        let mut pending = HashMap::new();
+        let mut accounts = HashMap::new();
+
+        // Skip lines cut short, as when bridge was stopped while recording
+        let lines = file
+            .lines()
+            .filter_map(|l| serde_json::from_str::<Value>(l).ok());
+
+        for line in lines {
+            if let Some(sent) = line.get("sent") {
+                for request in batch(sent) {
+                    if let Some(id) = request.get("id").filter(|id| !id.is_null()) {
+                        pending.insert(id.to_string(), request.clone());
+                    }
+                }
+            }
+
+            if let Some(received) = line.get("received") {
+                for msg in batch(received) {
+                    recording.receive(msg, &mut pending, &mut accounts);
+                }
+            }
+        }
+
+        Ok(recording)
+    }
+
+    /// Next reply to method, the last one being kept for later calls.
+    fn reply(&mut self, method: &str) -> Option<Reply> {
+        let replies = self.replies.get_mut(method)?;
+
+        if replies.len() > 1 {
+            replies.pop_front()
+        } else {
+            replies.front().cloned()
+        }
+    }
+
+    /// Keep response to pending request as reply to its method, or event as one of its account.
+    fn receive(
+        &mut self,
+        msg: &Value,
+        pending: &mut HashMap<String, Value>,
+        accounts: &mut HashMap<String, Option<String>>,
+    ) {
+        let params = msg.get("params").unwrap_or(&Value::Null);
+
+        if msg.get("method").and_then(Value::as_str) == Some("receive") {
+            let subscription = params.get("subscription").map(Value::to_string);
+
+            let account = subscription.and_then(|s| accounts.get(&s).cloned());
+
+            if let (Some(account), Some(event)) = (account, params.get("result")) {
+                self.events.entry(account).or_default().push(event.clone());
+            }
+
+            return;
+        }
+
+        let Some(request) = msg.get("id").and_then(|id| pending.remove(&id.to_string())) else {
+            return;
+        };
+
+        let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+
+        let reply = match msg.get("error") {
+            Some(error) => Reply::Error(error.clone()),
+            None => Reply::Result(msg.get("result").cloned().unwrap_or(Value::Null)),
+        };
+
+        if method == "subscribeReceive" {
+            if let Reply::Result(subscription) = &reply {
+                let account = request["params"].get("account").and_then(Value::as_str);
+
+                accounts.insert(subscription.to_string(), account.map(str::to_owned));
+            }
+
+            return;
+        }
+
+        let replies = self.replies.entry(method.to_owned()).or_default();
+
+        replies.push_back(reply);
+    }
+}
+
+/// Messages of JSON-RPC batch, or single message.
+fn batch(msg: &Value) -> &[Value] {
+    match msg {
+        Value::Array(msgs) => msgs,
+        msg => core::slice::from_ref(msg),
+    }
+}
+
+/// Notify each subscription of incoming event, as newline-terminated JSON.
+fn notifications(subscriptions: &[u64], event: &Value) -> String {
+    use core::fmt::Write;
+
+    subscriptions.iter().fold(String::new(), |mut msg, s| {
+        let params = json!({ "subscription": s, "result": event });
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "receive", "params": params });
+
+        let _ = writeln!(msg, "{notification}");
+
+        msg
+    })
+}
+
+/// Plausible result of unscripted method, successful for every recipient.
+fn default(method: &str, params: &Value) -> Value {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let timestamp = u64::try_from(elapsed.as_millis()).unwrap_or_default();
+
+    match method {
+        "version" => json!({ "version": "mock" }),
+        "send" => {
+            let recipients = match params.get("recipient") {
+                Some(Value::Array(recipients)) => recipients.clone(),
+                Some(recipient) if !recipient.is_null() => vec![recipient.clone()],
+                _ => Vec::new(),
+            };
+
+            let results: Vec<_> = recipients
+                .into_iter()
+                .map(|r| json!({ "recipientAddress": { "number": r }, "type": "SUCCESS" }))
+                .collect();
+
+            json!({ "timestamp": timestamp, "results": results })
+        }
+        "sendReaction" | "sendReceipt" | "sendTyping" => json!({ "timestamp": timestamp }),
+        m if m.starts_with("list") => json!([]),
+        _ => json!({}),
+    }
+}
@@ -0,0 +1,86 @@
+use jsonrpsee::core::client::Error as ErrorRpc;
+use serde_json::Value;
+
+use super::client::SignalClient;
+use super::daemon::Daemon;
+
+/// Recipient a name stands for, among contacts and groups of account.
+pub enum Resolved {
+    /// Number, or identifier if unknown, of only contact named so.
+    Person(String),
+
+    /// Identifier of only group titled so.
+    Group(String),
+
+    /// Number of contacts and groups named so, none or several.
+    Unresolved(usize),
+}
+
+/// Find contact named or group titled `name`, ignoring case, from listings daemon caches.
+pub async fn resolve(
+    signal: &Daemon,
+    account: Option<&str>,
+    name: &str,
+) -> Result<Resolved, ErrorRpc> {
+    let name = name.trim().to_lowercase();
+
+    let contacts = signal.list_contacts(account).await?;
+    let groups = signal.list_groups(account).await?;
+
+    let mut people: Vec<_> = listed(&contacts)
+        .filter(|c| names(c).iter().any(|n| n.to_lowercase() == name))
+        .filter_map(|c| string(c, "number").or_else(|| string(c, "uuid")))
+        .collect();
+
+    // Names may be repeated across fields of the same contact
+    people.sort_unstable();
+    people.dedup();
+
+    let titled: Vec<_> = listed(&groups)
+        .filter(|g| string(g, "name").is_some_and(|n| n.to_lowercase() == name))
+        .filter_map(|g| string(g, "id"))
+        .collect();
+
+    Ok(match (people.as_slice(), titled.as_slice()) {
+        ([person], []) => Resolved::Person(person.clone()),
+        ([], [group]) => Resolved::Group(group.clone()),
+        _ => Resolved::Unresolved(people.len() + titled.len()),
+    })
+}
+
+/// Entries of listing, if it is a list.
+fn listed(listing: &Value) -> impl Iterator<Item = &Value> {
+    listing.as_array().into_iter().flatten()
+}
+
+/// Names contact is known by, as set by account or by contact itself in its profile.
+fn names(contact: &Value) -> Vec<String> {
+    let joined = |given: Option<String>, family: Option<String>| {
+        let name = format!(
+            "{} {}",
+            given.unwrap_or_default(),
+            family.unwrap_or_default()
+        );
+
+        Some(name.trim().to_owned()).filter(|n| !n.is_empty())
+    };
+
+    let profile = contact.get("profile").unwrap_or(&Value::Null);
+
+    [
+        string(contact, "name"),
+        string(contact, "nickName"),
+        joined(string(contact, "givenName"), string(contact, "familyName")),
+        joined(string(profile, "givenName"), string(profile, "familyName")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Non-empty string field of listing entry.
+fn string(entry: &Value, field: &str) -> Option<String> {
+    let value = entry.get(field)?.as_str()?;
+
+    (!value.is_empty()).then(|| value.to_owned())
+}
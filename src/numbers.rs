@@ -0,0 +1,121 @@
+/// Separators people write phone numbers with, dropped from them.
+const SEPARATORS: [char; 5] = [' ', '-', '.', '(', ')'];
+
+/// Phone number in E.164 form, as `+` and up to 15 digits, `None` if value is not a phone number.
+///
+/// Numbers may start with `+` or `00` followed by country code, or be national numbers, with or
+/// without trunk prefix `0`, assumed to be in country of `country_code` if given. Identifiers of
+/// accounts and usernames are left for daemon to resolve.
+pub fn e164(value: &str, country_code: Option<&str>) -> Result<Option<String>, String> {
+    // Identifiers of accounts are hexadecimal, usernames hold letters
+    let is_number = value
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '+' || SEPARATORS.contains(&c));
+
+    if !is_number || is_uuid(value) {
+        return Ok(None);
+    }
+
+    let number: String = value.chars().filter(|c| !SEPARATORS.contains(c)).collect();
+
+    let digits = if let Some(international) = number.strip_prefix('+') {
+        international.to_owned()
+    } else if let Some(international) = number.strip_prefix("00") {
+        international.to_owned()
+    } else {
+        let Some(country_code) = country_code else {
+            return Err(String::from("expected country code, as `+49` or `0049`"));
+        };
+
+        let national = number.strip_prefix('0').unwrap_or(&number);
+
+        format!("{country_code}{national}")
+    };
+
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(String::from("expected digits only after `+`"));
+    }
+
+    if digits.starts_with('0') || !(7..=15).contains(&digits.len()) {
+        return Err(String::from("expected country code and 7 to 15 digits"));
+    }
+
+    Ok(Some(format!("+{digits}")))
+}
+
+/// Whether value is shaped like an account identifier, which may consist of digits only.
+pub fn is_uuid(value: &str) -> bool {
+    let groups: Vec<_> = value.split('-').map(str::len).collect();
+
+    groups == [8, 4, 4, 4, 12]
+}
+
+/// Take country calling code as digits, with or without leading `+`.
+pub fn parse_country_code(arg: &str) -> Result<String, String> {
+    let code = arg.strip_prefix('+').unwrap_or(arg);
+
+    if code.is_empty() || code.len() > 3 || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(String::from("expected 1 to 3 digits, as `49`"));
+    }
+
+    Ok(code.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{e164, parse_country_code};
+
+    #[test]
+    fn international_numbers_drop_separators() {
+        for value in [
+            "+49301234567",
+            "0049 30 1234567",
+            "+49 (30) 123-4567",
+            "0049.30.1234567",
+        ] {
+            assert_eq!(e164(value, None).unwrap().unwrap(), "+49301234567");
+        }
+    }
+
+    #[test]
+    fn national_numbers_take_country_code() {
+        for value in ["030 1234567", "301234567"] {
+            assert_eq!(e164(value, Some("49")).unwrap().unwrap(), "+49301234567");
+        }
+
+        assert!(e164("030 1234567", None).is_err());
+    }
+
+    #[test]
+    fn identifiers_and_usernames_are_not_numbers() {
+        let uuid = "12345678-1234-1234-1234-123456789012";
+
+        assert_eq!(e164(uuid, Some("49")).unwrap(), None);
+        assert_eq!(e164("alice.01", Some("49")).unwrap(), None);
+    }
+
+    #[test]
+    fn numbers_hold_7_to_15_digits() {
+        assert_eq!(e164("+1234567", None).unwrap().unwrap(), "+1234567");
+        assert!(e164("+123456", None).is_err());
+
+        let longest = "+123456789012345";
+
+        assert_eq!(e164(longest, None).unwrap().unwrap(), longest);
+        assert!(e164("+1234567890123456", None).is_err());
+
+        assert!(e164("+0049301234567", None).is_err());
+        assert!(e164("++49301234567", None).is_err());
+    }
+
+    #[test]
+    fn country_codes_are_1_to_3_digits() {
+        assert_eq!(parse_country_code("+49").unwrap(), "49");
+        assert_eq!(parse_country_code("1").unwrap(), "1");
+        assert_eq!(parse_country_code("358").unwrap(), "358");
+
+        for arg in ["", "+", "1234", "4a", "++49"] {
+            assert!(parse_country_code(arg).is_err());
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use color_eyre::eyre::{Result, WrapErr};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use super::audit::{self, Audit};
+use super::callers::Caller;
+use super::daemon::Daemon;
+use super::quiet::QuietHours;
+use super::staging::Staging;
+use super::vault::Vault;
+use super::{Identities, Send, Sent, audit_call, send_audited};
+
+/// Delay before first delivery retry, doubled after each failure.
+const BACKOFF_MIN: Duration = Duration::from_secs(1);
+
+/// Upper bound on delay between delivery retries.
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Extension of files holding queued messages.
+const EXTENSION: &str = "json";
+
+/// Messages accepted for later delivery, persisted to a directory until sent.
+pub struct Outbox {
+    dir: PathBuf,
+
+    /// Messages not delivered yet, oldest first.
+    pending: Mutex<VecDeque<Entry>>,
+
+    /// Woken when a message is queued.
+    notify: Notify,
+
+    /// Sequence number of next message, telling apart those queued in the same millisecond.
+    next: AtomicU64,
+
+    /// Windows messages accepted during are delivered once they end.
+    quiet: QuietHours,
+
+    /// Encryption of stored messages, if enabled.
+    vault: Arc<Vault>,
+}
+
+/// Message waiting in outbox.
+#[derive(Object, Serialize, Deserialize, Clone)]
+pub struct Entry {
+    /// Identifier of message in outbox, ordered like messages are delivered.
+    pub id: String,
+
+    /// Milliseconds since Unix epoch message was accepted at.
+    pub accepted_at: u64,
+
+    /// Account message is sent as, when daemon serves several.
+    pub account: Option<String>,
+
+    /// Number or group identifier message is sent to.
+    pub recipient: String,
+
+    /// Name of token message was accepted from, if API is not open.
+    #[serde(default)]
+    pub caller: Option<String>,
+
+    /// Milliseconds since Unix epoch message is held until, if accepted during quiet hours.
+    #[serde(default)]
+    pub not_before: Option<u64>,
+}
+
+/// Content of file of queued message.
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    entry: Entry,
+    message: Send,
+}
+
+impl Outbox {
+    /// Open outbox at `dir`, picking up messages a previous run left undelivered.
+    pub fn open(dir: &Path, quiet: QuietHours, vault: Arc<Vault>) -> Result<Arc<Self>> {
+        std::fs::create_dir_all(dir)
+            .wrap_err_with(|| format!("Failed to create {}", dir.display()))?;
+
+        let mut pending = Vec::new();
+
+        for file in std::fs::read_dir(dir)? {
+            let path = file?.path();
+
+            // Files being written when process stopped are incomplete, and were never accepted
+            if path.extension().is_some_and(|e| e == "tmp") {
+                std::fs::remove_file(&path)?;
+                continue;
+            }
+
+            if path.extension().is_none_or(|e| e != EXTENSION) {
+                continue;
+            }
+
+            match read(&path, &vault) {
+                Ok(stored) => pending.push(stored.entry),
+                Err(error) => tracing::warn!("Skipping queued message: {error:#}"),
+            }
+        }
+
+        pending.sort_by(|a, b| a.id.cmp(&b.id));
+
+        if !pending.is_empty() {
+            tracing::info!("Resuming delivery of {} queued messages", pending.len());
+        }
+
+        Ok(Arc::new(Self {
+            dir: dir.to_owned(),
+            pending: Mutex::new(pending.into()),
+            notify: Notify::new(),
+            next: AtomicU64::new(0),
+            quiet,
+            vault,
+        }))
+    }
+
+    /// Instant messages to recipient accepted now would be held until, if in quiet hours.
+    pub fn quiet_until(&self, recipient: &str) -> Option<u64> {
+        self.quiet.until(recipient, now())
+    }
+
+    /// Persist message for delivery in the background, once earlier ones are sent and no sooner
+    /// than `not_before`.
+    pub async fn push(
+        &self,
+        message: Send,
+        caller: Option<String>,
+        not_before: Option<u64>,
+    ) -> std::io::Result<Entry> {
+        let accepted_at = now();
+
+        let n = self.next.fetch_add(1, Ordering::Relaxed);
+
+        let entry = Entry {
+            id: format!("{accepted_at:013}-{n:06}"),
+            accepted_at,
+            account: message.account.clone(),
+            recipient: message.recipient.value.clone(),
+            caller,
+            not_before,
+        };
+
+        let stored = Stored {
+            entry: entry.clone(),
+            message,
+        };
+
+        // Write under temporary name first, for message to be accepted only once fully stored
+        let path = self.path(&entry.id);
+        let tmp = path.with_extension("tmp");
+
+        let bytes = self.vault.seal(serde_json::to_vec(&stored)?)?;
+
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+
+        self.lock().push_back(entry.clone());
+
+        self.notify.notify_one();
+
+        Ok(entry)
+    }
+
+    /// Messages waiting for delivery, oldest first.
+    pub fn entries(&self) -> Vec<Entry> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Send queued messages one by one, in order, retrying those daemon could not take yet, and
+    /// recording outcome of each to audit log once final.
+    pub async fn deliver(
+        self: Arc<Self>,
+        signal: Arc<Daemon>,
+        staging: Arc<Staging>,
+        audit: Option<Arc<Audit>>,
+        identities: Identities,
+    ) {
+        use poem::http::StatusCode;
+        use poem::web::Data;
+        use poem_openapi::param::Query;
+
+        /// Statuses of failures worth trying again, as they do not depend on message itself.
+        const TRANSIENT: [StatusCode; 3] = [
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::GATEWAY_TIMEOUT,
+        ];
+
+        let mut backoff = BACKOFF_MIN;
+
+        loop {
+            let entry = self.next().await;
+
+            // Messages were counted against quotas of their caller when accepted
+            let caller = Arc::new(Caller::named(entry.caller.clone()));
+
+            let path = self.path(&entry.id);
+
+            let mut message = match read(&path, &self.vault) {
+                Ok(stored) => stored.message,
+                Err(error) => {
+                    tracing::error!("Dropping queued message {}: {error:#}", entry.id);
+                    self.remove(&entry.id);
+                    continue;
+                }
+            };
+
+            // Quiet hours were applied when message was accepted, it must not be held again
+            message.urgent = Some(true);
+
+            // Uploads were swapped for their attachments before message was stored
+
+            let (account, recipient) = (message.account.clone(), message.recipient.key());
+
+            // Attempts are not recorded, only final outcome
+            let sent = send_audited(
+                message,
+                Query(None),
+                Data(&signal),
+                Data(&staging),
+                Data(&None),
+                Data(&None),
+                Data(&caller),
+                Data(&None),
+                Data(&identities),
+            )
+            .await;
+
+            let outcome = match &sent {
+                Err(error) if TRANSIENT.contains(&error.status()) => None,
+                sent => Some(sent.as_ref().map(Sent::status)),
+            };
+
+            if let Some(outcome) = outcome {
+                let action = audit::Action::Send;
+
+                audit_call(
+                    audit.as_deref(),
+                    &caller,
+                    action,
+                    account,
+                    recipient,
+                    outcome,
+                )
+                .await;
+            }
+
+            match sent {
+                Ok(Sent::Delivered(_)) => tracing::debug!("Delivered queued message {}", entry.id),
+                Ok(Sent::Untrusted(_)) => {
+                    tracing::error!("Dropping queued message {}: untrusted identity", entry.id);
+                }
+                Ok(Sent::Queued(_)) => {}
+                Err(error) if TRANSIENT.contains(&error.status()) => {
+                    tracing::warn!("Failed to deliver queued message {}: {error}", entry.id);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(BACKOFF_MAX);
+                    continue;
+                }
+                Err(error) => tracing::error!("Dropping queued message {}: {error}", entry.id),
+            }
+
+            backoff = BACKOFF_MIN;
+
+            self.remove(&entry.id);
+        }
+    }
+
+    /// Oldest message due for delivery, waiting for one if none is.
+    async fn next(&self) -> Entry {
+        loop {
+            let notified = self.notify.notified();
+
+            let now = now();
+
+            let (due, held) = {
+                let pending = self.lock();
+
+                let due = pending
+                    .iter()
+                    .find(|e| e.not_before.is_none_or(|at| at <= now))
+                    .cloned();
+
+                (due, pending.iter().filter_map(|e| e.not_before).min())
+            };
+
+            if let Some(entry) = due {
+                return entry;
+            }
+
+            // Wake up when earliest held message is due, unless another one is queued first
+            match held {
+                Some(at) => {
+                    let _ = tokio::time::timeout(Duration::from_millis(at - now), notified).await;
+                }
+                None => notified.await,
+            }
+        }
+    }
+
+    fn remove(&self, id: &str) {
+        self.lock().retain(|e| e.id != id);
+
+        if let Err(error) = std::fs::remove_file(self.path(id)) {
+            tracing::warn!("Failed to remove queued message {id}: {error}");
+        }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(id).with_extension(EXTENSION)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Entry>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn read(path: &Path, vault: &Vault) -> Result<Stored> {
+    let bytes =
+        std::fs::read(path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+
+    let bytes = vault
+        .open(bytes)
+        .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// Milliseconds elapsed since Unix epoch.
+pub fn now() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    u64::try_from(elapsed.as_millis()).unwrap_or_default()
+}
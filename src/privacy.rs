@@ -0,0 +1,257 @@
+use std::borrow::Cow;
+use std::hash::{BuildHasher, RandomState};
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Fewest digits after `+` taken for a phone number, low to catch invalid ones echoed back too.
+const MIN_DIGITS: usize = 3;
+
+/// Fewest digits of phone numbers written without `+` nor `00` taken for one, as few as national
+/// ones may have, but more than counts and such usually do.
+const MIN_NATIONAL_DIGITS: usize = 6;
+
+/// Separators people write phone numbers with, taken as part of them between digits.
+const SEPARATORS: [char; 5] = [' ', '-', '.', '(', ')'];
+
+/// Stand-in for phone number, same for the same number within a run of process.
+///
+/// Hashes are keyed with a secret picked at start, for numbers not to be recovered by hashing
+/// every possible one.
+pub fn hashed(number: &str) -> String {
+    hash("number", number)
+}
+
+/// Stand-in for identifier of `kind`, keyed as that of phone numbers.
+fn hash(kind: &str, value: &str) -> String {
+    static KEY: OnceLock<RandomState> = OnceLock::new();
+
+    let hash = KEY.get_or_init(RandomState::new).hash_one(value);
+
+    format!("{kind}#{hash:016x}")
+}
+
+/// Text with phone numbers, whether international or national, identifiers of accounts and
+/// usernames swapped for their hashes.
+pub fn scrub(text: &str) -> Cow<'_, str> {
+    let uuids = swap(
+        text,
+        |c| c.is_ascii_hexdigit() || c == '-',
+        crate::numbers::is_uuid,
+        "uuid",
+    );
+
+    let usernames = swap(
+        &uuids,
+        |c| c.is_alphanumeric() || c == '_' || c == '.',
+        crate::is_username,
+        "username",
+    );
+
+    let scrubbed = numbers(&usernames);
+
+    if *scrubbed == *text {
+        return Cow::Borrowed(text);
+    }
+
+    Cow::Owned(scrubbed.into_owned())
+}
+
+/// Text with runs of characters `part` accepts swapped for their hashes as `kind`, for runs of
+/// `shape` standing on their own.
+fn swap<'a>(
+    text: &'a str,
+    part: impl Fn(char) -> bool,
+    shape: impl Fn(&str) -> bool,
+    kind: &str,
+) -> Cow<'a, str> {
+    let mut scrubbed = String::new();
+
+    // Start of text not copied to scrubbed one yet
+    let mut copied = 0;
+
+    let mut rest = text;
+
+    while let Some(at) = rest.find(&part) {
+        let start = text.len() - rest.len() + at;
+
+        let end = text[start..]
+            .find(|c| !part(c))
+            .map_or(text.len(), |len| start + len);
+
+        // Run ending a sentence
+        let token = text[start..end].trim_end_matches('.');
+
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+
+        // Runs within a word are not identifiers of their own
+        let attached =
+            before.is_some_and(char::is_alphanumeric) || after.is_some_and(char::is_alphanumeric);
+
+        if shape(token) && !attached {
+            scrubbed.push_str(&text[copied..start]);
+            scrubbed.push_str(&hash(kind, token));
+
+            copied = start + token.len();
+        }
+
+        rest = &text[end..];
+    }
+
+    if copied == 0 {
+        return Cow::Borrowed(text);
+    }
+
+    scrubbed.push_str(&text[copied..]);
+
+    Cow::Owned(scrubbed)
+}
+
+/// Text with phone numbers swapped for their hashes.
+fn numbers(text: &str) -> Cow<'_, str> {
+    let mut scrubbed = String::new();
+
+    // Start of text not copied to scrubbed one yet
+    let mut copied = 0;
+
+    let mut rest = text;
+
+    while let Some(at) = rest.find(|c: char| c == '+' || c.is_ascii_digit()) {
+        let start = text.len() - rest.len() + at;
+
+        let (digits, end) = number_at(text, start);
+
+        let number = &text[start..end];
+
+        // Digits running on from a word or number are not a phone number of their own
+        let attached = text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+
+        // Nor are ones without `+` running into a word, as in dates of logs
+        let runs_on = text[end..]
+            .chars()
+            .next()
+            .is_some_and(char::is_alphanumeric);
+
+        let is_number = if number.starts_with('+') {
+            digits >= MIN_DIGITS
+        } else if number.starts_with("00") {
+            digits >= MIN_DIGITS + 2 && !runs_on
+        } else {
+            digits >= MIN_NATIONAL_DIGITS
+                && !runs_on
+                && number.parse::<std::net::Ipv4Addr>().is_err()
+        };
+
+        if is_number && !attached {
+            scrubbed.push_str(&text[copied..start]);
+            scrubbed.push_str(&hashed(number));
+
+            copied = end;
+        }
+
+        rest = &text[end..];
+    }
+
+    if copied == 0 {
+        return Cow::Borrowed(text);
+    }
+
+    scrubbed.push_str(&text[copied..]);
+
+    Cow::Owned(scrubbed)
+}
+
+/// Number of digits of phone number starting at `start` of text, with `+` or a digit, and where
+/// its last one ends.
+fn number_at(text: &str, start: usize) -> (usize, usize) {
+    let skip = usize::from(text[start..].starts_with('+'));
+
+    let mut digits = 0;
+    let mut end = start + skip;
+
+    for (i, c) in text[start + skip..].char_indices() {
+        if c.is_ascii_digit() {
+            digits += 1;
+            end = start + skip + i + 1;
+        } else if !(SEPARATORS.contains(&c) && digits > 0) {
+            break;
+        }
+    }
+
+    (digits, end)
+}
+
+/// Writers of logs scrubbing phone numbers and other identifiers out of them, when enabled.
+pub struct Scrubbing<M> {
+    inner: M,
+    enabled: bool,
+}
+
+impl<M> Scrubbing<M> {
+    pub const fn new(inner: M, enabled: bool) -> Self {
+        Self { inner, enabled }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Scrubbing<M> {
+    type Writer = Scrubber<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Scrubber {
+            inner: self.inner.make_writer(),
+            enabled: self.enabled,
+        }
+    }
+}
+
+/// Writer of a log entry, written whole at once by formatters, scrubbing it when enabled.
+pub struct Scrubber<W> {
+    inner: W,
+    enabled: bool,
+}
+
+impl<W: Write> Write for Scrubber<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.enabled {
+            return self.inner.write(buf);
+        }
+
+        let text = String::from_utf8_lossy(buf);
+
+        self.inner.write_all(scrub(&text).as_bytes())?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Scrub identifiers out of bodies of error responses, which often echo what was wrong.
+pub async fn scrub_errors<E: poem::Endpoint>(
+    next: E,
+    req: poem::Request,
+) -> poem::Result<poem::Response> {
+    use poem::IntoResponse;
+
+    let mut resp = match next.call(req).await {
+        Ok(resp) => resp.into_response(),
+        Err(error) => error.into_response(),
+    };
+
+    if resp.status().is_success() {
+        return Ok(resp);
+    }
+
+    let body = resp.take_body().into_string().await?;
+
+    resp.set_body(scrub(&body).into_owned());
+
+    Ok(resp)
+}
@@ -0,0 +1,344 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use color_eyre::eyre::{Result, bail};
+use tokio::sync::Notify;
+
+use super::events::Event;
+use super::vault::Vault;
+
+/// What to do with incoming events once queue to webhook is full.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Overflow {
+    /// Stop reading subscription until webhook catches up.
+    Block,
+
+    /// Discard the oldest queued event to make room.
+    DropOldest,
+
+    /// Write further events to a file, delivered once queue has room again.
+    Spill,
+}
+
+/// Bounded queue of incoming events, between daemon subscription and webhook delivery.
+pub struct Queue {
+    capacity: usize,
+    overflow: Overflow,
+    state: Mutex<State>,
+
+    /// Woken when an event is queued.
+    pushed: Notify,
+
+    /// Woken when an event is taken.
+    popped: Notify,
+}
+
+struct State {
+    events: VecDeque<Event>,
+
+    /// Events that did not fit in memory, in order, only when spilling.
+    spill: Option<Spill>,
+}
+
+impl Queue {
+    pub fn new(
+        capacity: usize,
+        overflow: Overflow,
+        spill: Option<&Path>,
+        vault: Arc<Vault>,
+    ) -> Result<Self> {
+        let spill = match (overflow, spill) {
+            (Overflow::Spill, Some(path)) => Some(Spill::create(path, vault)?),
+            (Overflow::Spill, None) => bail!("Spilling events requires `--spill-dir`"),
+            _ => None,
+        };
+
+        let state = State {
+            events: VecDeque::with_capacity(capacity),
+            spill,
+        };
+
+        Ok(Self {
+            capacity: capacity.max(1),
+            overflow,
+            state: Mutex::new(state),
+            pushed: Notify::new(),
+            popped: Notify::new(),
+        })
+    }
+
+    /// Queue event, applying overflow policy when full.
+    pub async fn push(&self, event: Event) {
+        loop {
+            // Register interest before checking queue, to avoid missing a concurrent pop
+            let popped = self.popped.notified();
+
+            if self.try_push(&event) {
+                return self.pushed.notify_one();
+            }
+
+            popped.await;
+        }
+    }
+
+    /// Take oldest event, waiting for one if queue is empty.
+    pub async fn pop(&self) -> Event {
+        loop {
+            let pushed = self.pushed.notified();
+
+            if let Some(event) = self.try_pop() {
+                self.popped.notify_one();
+
+                return event;
+            }
+
+            pushed.await;
+        }
+    }
+
+    fn try_push(&self, event: &Event) -> bool {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(event, self.capacity, self.overflow)
+    }
+
+    fn try_pop(&self) -> Option<Event> {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+    }
+}
+
+impl State {
+    /// Store event unless it has to wait for room, telling whether it was handled.
+    fn push(&mut self, event: &Event, capacity: usize, overflow: Overflow) -> bool {
+        // Keep spilled events ahead of new ones, to deliver them in order
+        let spilling = self.spill.as_ref().is_some_and(|s| !s.is_empty());
+
+        if self.events.len() < capacity && !spilling {
+            self.events.push_back(event.clone());
+
+            return true;
+        }
+
+        match (overflow, &mut self.spill) {
+            (Overflow::DropOldest, _) => {
+                self.events.pop_front();
+                self.events.push_back(event.clone());
+
+                tracing::warn!("Webhook is lagging behind, dropped oldest incoming message");
+            }
+            (Overflow::Spill, Some(spill)) => {
+                if let Err(error) = spill.push(event) {
+                    tracing::warn!("Failed to spill incoming message, dropping it: {error}");
+                }
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn pop(&mut self) -> Option<Event> {
+        let event = self.events.pop_front();
+
+        // Refill from disk, one event per event taken keeps memory full while spill drains
+        if let Some(spill) = &mut self.spill {
+            loop {
+                match spill.pop() {
+                    Ok(Some(spilled)) => self.events.push_back(spilled),
+                    Ok(None) => {}
+
+                    // Skip unreadable events, for memory not to run dry while spill holds some
+                    Err(error) => {
+                        tracing::warn!(
+                            "Failed to read spilled incoming message, dropping it: {error}"
+                        );
+                        continue;
+                    }
+                }
+
+                break;
+            }
+        }
+
+        // Memory is only empty with events spilled if those taken before failed to be read back
+        event.or_else(|| self.events.pop_front())
+    }
+}
+
+/// File of events, one JSON line each, read from the front and appended to at the back.
+///
+/// Lines are encrypted when vault is, lengths telling them apart rather than line breaks.
+struct Spill {
+    file: File,
+    vault: Arc<Vault>,
+
+    /// Offset of oldest event in file.
+    read: u64,
+
+    /// Length of each event in file, oldest first.
+    lengths: VecDeque<usize>,
+}
+
+impl Spill {
+    /// Start spilling to file at `path`, discarding what previous runs left there.
+    fn create(path: &Path, vault: Arc<Vault>) -> Result<Self> {
+        use color_eyre::eyre::WrapErr;
+
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+
+        Ok(Self {
+            file,
+            vault,
+            read: 0,
+            lengths: VecDeque::new(),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lengths.is_empty()
+    }
+
+    fn push(&mut self, event: &Event) -> io::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+
+        let line = self.vault.seal(line)?;
+
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&line)?;
+
+        self.lengths.push_back(line.len());
+
+        Ok(())
+    }
+
+    fn pop(&mut self) -> io::Result<Option<Event>> {
+        let Some(length) = self.lengths.pop_front() else {
+            return Ok(None);
+        };
+
+        let mut line = vec![0; length];
+
+        self.file.seek(SeekFrom::Start(self.read))?;
+        self.file.read_exact(&mut line)?;
+
+        self.read += length as u64;
+
+        // Reclaim disk space once every spilled event is read back
+        if self.lengths.is_empty() {
+            self.file.set_len(0)?;
+            self.read = 0;
+        }
+
+        Ok(Some(serde_json::from_slice(&self.vault.open(line)?)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use super::{Event, Overflow, Queue};
+
+    fn event(n: u64) -> Event {
+        serde_json::from_value(serde_json::json!({ "account": n.to_string() })).unwrap()
+    }
+
+    fn drain(queue: &Queue) -> Vec<String> {
+        std::iter::from_fn(|| queue.try_pop())
+            .map(|e| e.account.unwrap())
+            .collect()
+    }
+
+    /// Spill file of test `name`, removed once dropped.
+    struct File(PathBuf);
+
+    impl File {
+        fn new(name: &str) -> Self {
+            let name = format!("signal-http-{name}-{}.spill", std::process::id());
+
+            Self(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for File {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn block_refuses_events_once_full() {
+        let queue = Queue::new(2, Overflow::Block, None, Arc::default()).unwrap();
+
+        assert!(queue.try_push(&event(1)));
+        assert!(queue.try_push(&event(2)));
+        assert!(!queue.try_push(&event(3)));
+
+        assert_eq!(drain(&queue), ["1", "2"]);
+    }
+
+    #[test]
+    fn drop_oldest_keeps_latest_events() {
+        let queue = Queue::new(2, Overflow::DropOldest, None, Arc::default()).unwrap();
+
+        for n in 1..=4 {
+            assert!(queue.try_push(&event(n)));
+        }
+
+        assert_eq!(drain(&queue), ["3", "4"]);
+    }
+
+    #[test]
+    fn spill_keeps_order_of_events() {
+        let file = File::new("order");
+
+        let queue = Queue::new(2, Overflow::Spill, Some(&file.0), Arc::default()).unwrap();
+
+        for n in 1..=4 {
+            assert!(queue.try_push(&event(n)));
+        }
+
+        assert_eq!(queue.try_pop().unwrap().account.unwrap(), "1");
+
+        // Spilled events stay ahead of new ones, even with room in memory
+        assert!(queue.try_push(&event(5)));
+
+        assert_eq!(drain(&queue), ["2", "3", "4", "5"]);
+    }
+
+    #[test]
+    fn spill_skips_events_failing_to_be_read_back() {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let file = File::new("unreadable");
+
+        let queue = Queue::new(1, Overflow::Spill, Some(&file.0), Arc::default()).unwrap();
+
+        for n in 1..=3 {
+            assert!(queue.try_push(&event(n)));
+        }
+
+        // Break first spilled event
+        let mut spilled = std::fs::File::options().write(true).open(&file.0).unwrap();
+
+        spilled.seek(SeekFrom::Start(0)).unwrap();
+        spilled.write_all(b"#").unwrap();
+
+        assert_eq!(drain(&queue), ["1", "3"]);
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+/// Milliseconds in a day.
+const DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// Milliseconds in a minute.
+const MINUTE: u64 = 60 * 1000;
+
+/// Times of day messages are held back until, globally or for given recipients.
+#[derive(Default)]
+pub struct QuietHours {
+    global: Option<Window>,
+
+    /// Windows of recipients by number or group identifier, in place of global one.
+    recipients: HashMap<String, Window>,
+}
+
+/// Span of day from `start` until `end` in minutes past midnight UTC, wrapping around midnight
+/// if it ends earlier than it starts.
+#[derive(Clone, Copy)]
+pub struct Window {
+    start: u64,
+    end: u64,
+}
+
+impl QuietHours {
+    pub fn new(global: Option<Window>, recipients: &[(String, Window)]) -> Self {
+        Self {
+            global,
+            recipients: recipients.iter().cloned().collect(),
+        }
+    }
+
+    /// Milliseconds since Unix epoch messages to recipient sent at `now` are held until, if any.
+    pub fn until(&self, recipient: &str, now: u64) -> Option<u64> {
+        self.recipients
+            .get(recipient)
+            .or(self.global.as_ref())?
+            .until(now)
+    }
+}
+
+impl Window {
+    /// End of window `now` falls into, if any.
+    const fn until(self, now: u64) -> Option<u64> {
+        let midnight = now - now % DAY;
+
+        let minute = (now - midnight) / MINUTE;
+
+        let inside = if self.start <= self.end {
+            self.start <= minute && minute < self.end
+        } else {
+            self.start <= minute || minute < self.end
+        };
+
+        if !inside {
+            return None;
+        }
+
+        let end = midnight + self.end * MINUTE;
+
+        Some(if end <= now { end + DAY } else { end })
+    }
+}
+
+/// Parse `HH:MM-HH:MM` window, in UTC.
+pub fn parse_window(arg: &str) -> Result<Window, String> {
+    let minutes = |time: &str| {
+        let (hours, minutes) = time.split_once(':')?;
+
+        let (hours, minutes) = (hours.parse::<u64>().ok()?, minutes.parse::<u64>().ok()?);
+
+        (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+    };
+
+    let window = arg
+        .split_once('-')
+        .and_then(|(start, end)| Some((minutes(start)?, minutes(end)?)));
+
+    match window {
+        Some((start, end)) => Ok(Window { start, end }),
+        None => Err(String::from("expected `HH:MM-HH:MM`")),
+    }
+}
+
+/// Split `recipient=HH:MM-HH:MM` argument into recipient and its window.
+pub fn parse_recipient_window(arg: &str) -> Result<(String, Window), String> {
+    // Group identifiers may end with padding, windows never contain any
+    let Some((recipient, window)) = arg.rsplit_once('=') else {
+        return Err(String::from("expected `recipient=HH:MM-HH:MM`"));
+    };
+
+    Ok((recipient.to_owned(), parse_window(window)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DAY, MINUTE, parse_recipient_window, parse_window};
+
+    /// Some midnight, far from Unix epoch.
+    const MIDNIGHT: u64 = 20_000 * DAY;
+
+    /// Milliseconds since Unix epoch at given time of day after `MIDNIGHT`.
+    const fn at(hours: u64, minutes: u64) -> u64 {
+        MIDNIGHT + (hours * 60 + minutes) * MINUTE
+    }
+
+    #[test]
+    fn windows_hold_until_their_end() {
+        let window = parse_window("09:00-17:00").unwrap();
+
+        assert_eq!(window.until(at(9, 0)), Some(at(17, 0)));
+        assert_eq!(window.until(at(16, 59) + 30_000), Some(at(17, 0)));
+
+        assert_eq!(window.until(at(8, 59)), None);
+        assert_eq!(window.until(at(17, 0)), None);
+    }
+
+    #[test]
+    fn windows_wrap_past_midnight() {
+        let window = parse_window("22:00-07:00").unwrap();
+
+        assert_eq!(window.until(at(22, 0)), Some(at(7, 0) + DAY));
+        assert_eq!(window.until(at(23, 59)), Some(at(7, 0) + DAY));
+        assert_eq!(window.until(at(0, 0)), Some(at(7, 0)));
+        assert_eq!(window.until(at(6, 59)), Some(at(7, 0)));
+
+        assert_eq!(window.until(at(7, 0)), None);
+        assert_eq!(window.until(at(21, 59)), None);
+    }
+
+    #[test]
+    fn windows_ending_as_they_start_are_empty() {
+        let window = parse_window("09:00-09:00").unwrap();
+
+        for now in [at(9, 0), at(8, 59), at(12, 0), at(0, 0)] {
+            assert_eq!(window.until(now), None);
+        }
+    }
+
+    #[test]
+    fn windows_are_times_of_day() {
+        let window = parse_window("00:00-23:59").unwrap();
+
+        assert_eq!((window.start, window.end), (0, 23 * 60 + 59));
+
+        for arg in [
+            "24:00-07:00",
+            "22:60-07:00",
+            "22:00",
+            "22-07",
+            "22:00-07:00:00",
+        ] {
+            assert!(parse_window(arg).is_err());
+        }
+    }
+
+    #[test]
+    fn recipients_may_end_with_padding() {
+        let (recipient, window) = parse_recipient_window("group.Z3JvdXA==22:00-07:00").unwrap();
+
+        assert_eq!(recipient, "group.Z3JvdXA=");
+        assert_eq!((window.start, window.end), (22 * 60, 7 * 60));
+    }
+}
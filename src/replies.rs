@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{Result, WrapErr};
+use handlebars::Handlebars;
+use regex::Regex;
+use serde_json::{Map, Value, json};
+
+use super::events::Event;
+
+/// Rules replying to incoming messages on their own, checked in order, first match winning.
+pub struct Replies {
+    rules: Vec<Rule>,
+
+    /// Reply template of each rule, named after its position.
+    templates: Handlebars<'static>,
+}
+
+/// Content of rules file.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    #[serde(default)]
+    rule: Vec<Spec>,
+}
+
+/// Rule as written in file, every condition given having to hold.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    /// Pattern number or identifier of sender must match.
+    sender: Option<String>,
+
+    /// Pattern identifier of group must match, rules without one only apply to direct messages.
+    group: Option<String>,
+
+    /// Pattern body must match, its captures available to reply as `captures`.
+    body: Option<String>,
+
+    /// Words body must contain one of, regardless of case.
+    #[serde(default)]
+    keywords: Vec<String>,
+
+    /// Handlebars template of reply, given `sender`, `name`, `group`, `body` and `captures`.
+    reply: String,
+
+    /// Seconds to wait for before replying to the same conversation again.
+    #[serde(default)]
+    cooldown: u64,
+}
+
+struct Rule {
+    sender: Option<Regex>,
+    group: Option<Regex>,
+    body: Option<Regex>,
+    keywords: Vec<String>,
+    cooldown: Duration,
+
+    /// Instant of last reply to each conversation.
+    replied: Mutex<HashMap<String, Instant>>,
+}
+
+impl Replies {
+    /// Load rules from TOML file of `[[rule]]` tables, none if no file is given.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut templates = Handlebars::new();
+
+        // Replies are plain text, not HTML
+        templates.register_escape_fn(handlebars::no_escape);
+
+        let Some(path) = path else {
+            return Ok(Self {
+                rules: Vec::new(),
+                templates,
+            });
+        };
+
+        let file: File = toml::from_str(
+            &std::fs::read_to_string(path)
+                .wrap_err_with(|| format!("Failed to read {}", path.display()))?,
+        )?;
+
+        let pattern = |p: Option<String>| p.as_deref().map(Regex::new).transpose();
+
+        let mut rules = Vec::new();
+
+        for (index, spec) in file.rule.into_iter().enumerate() {
+            templates
+                .register_template_string(&index.to_string(), &spec.reply)
+                .wrap_err_with(|| format!("Invalid reply of rule {index}"))?;
+
+            rules.push(Rule {
+                sender: pattern(spec.sender)?,
+                group: pattern(spec.group)?,
+                body: pattern(spec.body)?,
+                keywords: spec.keywords.iter().map(|k| k.to_lowercase()).collect(),
+                cooldown: Duration::from_secs(spec.cooldown),
+                replied: Mutex::new(HashMap::new()),
+            });
+        }
+
+        Ok(Self { rules, templates })
+    }
+
+    /// Reply to incoming message of event, from first rule matching it.
+    pub fn reply(&self, event: &Event) -> Option<String> {
+        let envelope = event.envelope.as_ref()?;
+
+        let msg = envelope.data_message.as_ref()?;
+
+        let body = msg.message.as_deref()?;
+
+        let sender = envelope
+            .source_number
+            .as_deref()
+            .or(envelope.source.as_deref())
+            .unwrap_or_default();
+
+        let group = msg.group_info.as_ref().and_then(|g| g.group_id.as_deref());
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            let Some(captures) = rule.matches(sender, group, body) else {
+                continue;
+            };
+
+            let conversation = group.unwrap_or(sender);
+
+            if !rule.cool(conversation) {
+                return None;
+            }
+
+            let data = json!({
+                "sender": sender,
+                "name": envelope.source_name,
+                "group": group,
+                "body": body,
+                "captures": captures,
+            });
+
+            return match self.templates.render(&index.to_string(), &data) {
+                Ok(reply) => Some(reply),
+                Err(error) => {
+                    tracing::warn!("Failed to render reply of rule {index}: {error}");
+                    None
+                }
+            };
+        }
+
+        None
+    }
+}
+
+impl Rule {
+    /// Captures of body pattern, by position and name, if message matches rule.
+    fn matches(&self, sender: &str, group: Option<&str>, body: &str) -> Option<Map<String, Value>> {
+        let found =
+            |pattern: &Option<Regex>, s: &str| pattern.as_ref().is_none_or(|p| p.is_match(s));
+
+        if !found(&self.sender, sender) {
+            return None;
+        }
+
+        match (&self.group, group) {
+            (None, None) => {}
+            (Some(pattern), Some(group)) if pattern.is_match(group) => {}
+            _ => return None,
+        }
+
+        let lowercase = body.to_lowercase();
+
+        if !self.keywords.is_empty() && !self.keywords.iter().any(|k| lowercase.contains(k)) {
+            return None;
+        }
+
+        let mut captures = Map::new();
+
+        if let Some(pattern) = &self.body {
+            let found = pattern.captures(body)?;
+
+            for (index, name) in pattern.capture_names().enumerate() {
+                let Some(capture) = found.get(index) else {
+                    continue;
+                };
+
+                captures.insert(index.to_string(), Value::from(capture.as_str()));
+
+                if let Some(name) = name {
+                    captures.insert(name.to_owned(), Value::from(capture.as_str()));
+                }
+            }
+        }
+
+        Some(captures)
+    }
+
+    /// Record reply to conversation, unless rule replied to it too recently.
+    fn cool(&self, conversation: &str) -> bool {
+        let mut replied = self.replied.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let now = Instant::now();
+
+        if let Some(at) = replied.get(conversation)
+            && now.duration_since(*at) < self.cooldown
+        {
+            return false;
+        }
+
+        replied.insert(conversation.to_owned(), now);
+
+        drop(replied);
+
+        true
+    }
+}
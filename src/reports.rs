@@ -0,0 +1,37 @@
+use serde_json::Value;
+
+/// Start reporting panics and logged errors to Sentry, until guard is dropped.
+pub fn init(dsn: sentry::types::Dsn) -> sentry::ClientInitGuard {
+    // Reports are sent with default provider, ambiguous as dependencies compile in several
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let options = sentry::ClientOptions::new()
+        .maybe_release(sentry::release_name!())
+        .before_send(|event| redact(&event));
+
+    sentry::init((dsn, options))
+}
+
+/// Event with identifiers scrubbed out of every text in it, context and breadcrumbs included;
+/// dropped if that cannot be done.
+fn redact(event: &sentry::protocol::Event<'static>) -> Option<sentry::protocol::Event<'static>> {
+    let mut value = serde_json::to_value(event).ok()?;
+
+    scrub(&mut value);
+
+    serde_json::from_value(value).ok()
+}
+
+/// Scrub identifiers out of strings of value, however deep they are nested.
+fn scrub(value: &mut Value) {
+    match value {
+        Value::String(text) => {
+            if let std::borrow::Cow::Owned(scrubbed) = crate::privacy::scrub(text) {
+                *text = scrubbed;
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(scrub),
+        Value::Object(fields) => fields.values_mut().for_each(scrub),
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use color_eyre::eyre::{Result, bail};
+use serde_json::json;
+
+use super::daemon::Daemon;
+use super::events::Event;
+
+/// Upper bound on time for handler programs to run, before being killed.
+const EXEC_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Scheme of handlers run as programs rather than called over HTTP.
+const EXEC: &str = "exec:";
+
+/// Where to dispatch a chat command to.
+#[derive(Clone)]
+pub enum Handler {
+    /// Endpoint posted the command, its arguments and the triggering event.
+    Webhook(String),
+
+    /// Program run with arguments of command, event on standard input, output sent as reply.
+    Exec(String),
+}
+
+/// Chat commands of bots, recognized in incoming messages by the prefix of their name.
+pub struct Router {
+    prefix: String,
+    commands: HashMap<String, Handler>,
+}
+
+/// Command found in a message, with what to dispatch it to.
+pub struct Invocation {
+    pub name: String,
+    pub args: Vec<String>,
+    pub handler: Handler,
+}
+
+impl Router {
+    pub fn new(prefix: &str, commands: &[(String, Handler)]) -> Self {
+        Self {
+            prefix: prefix.to_owned(),
+            commands: commands.iter().cloned().collect(),
+        }
+    }
+
+    /// Command message of event invokes, if it starts with a configured one.
+    pub fn route(&self, event: &Event) -> Option<Invocation> {
+        let envelope = event.envelope.as_ref()?;
+
+        let text = envelope.data_message.as_ref()?.message.as_deref()?;
+
+        let mut words = text.strip_prefix(&self.prefix)?.split_whitespace();
+
+        let name = words.next()?;
+
+        Some(Invocation {
+            name: name.to_owned(),
+            args: words.map(str::to_owned).collect(),
+            handler: self.commands.get(name)?.clone(),
+        })
+    }
+}
+
+impl Invocation {
+    /// Run handler, replying to conversation command came from with output of programs.
+    pub async fn dispatch(self, client: &reqwest::Client, signal: &Daemon, event: &Event) {
+        let outcome = match &self.handler {
+            Handler::Webhook(url) => self.post(client, url, event).await,
+            Handler::Exec(program) => match self.exec(program, event).await {
+                Ok(output) if output.trim().is_empty() => Ok(()),
+                Ok(output) => reply(signal, event, output.trim_end()).await,
+                Err(error) => Err(error),
+            },
+        };
+
+        if let Err(error) = outcome {
+            tracing::warn!("Failed to handle command `{}`: {error}", self.name);
+        }
+    }
+
+    async fn post(&self, client: &reqwest::Client, url: &str, event: &Event) -> Result<()> {
+        let payload = json!({ "command": self.name, "args": self.args, "event": event });
+
+        client
+            .post(url)
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn exec(&self, program: &str, event: &Event) -> Result<String> {
+        use std::process::Stdio;
+
+        use tokio::io::AsyncWriteExt;
+
+        let mut child = tokio::process::Command::new(program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&serde_json::to_vec(event)?).await?;
+        }
+
+        let output = tokio::time::timeout(EXEC_TIMEOUT, child.wait_with_output()).await??;
+
+        if !output.status.success() {
+            bail!("`{program}` exited with {}", output.status);
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Send message to group event comes from, or to its sender otherwise.
+pub async fn reply(signal: &Daemon, event: &Event, message: &str) -> Result<()> {
+    use super::client::SignalClient;
+
+    let Some(envelope) = &event.envelope else {
+        return Ok(());
+    };
+
+    let group = envelope
+        .data_message
+        .as_ref()
+        .and_then(|m| m.group_info.as_ref())
+        .and_then(|g| g.group_id.as_deref());
+
+    let sender = envelope
+        .source_number
+        .as_deref()
+        .or(envelope.source.as_deref());
+
+    let recipient = if group.is_some() { None } else { sender };
+
+    let account = event.account.as_deref();
+
+    signal
+        .send(account, recipient, group, message, &[], &[])
+        .await?;
+
+    Ok(())
+}
+
+/// Split `name=target` argument into command and its handler, programs prefixed with `exec:`.
+pub fn parse_command(arg: &str) -> Result<(String, Handler), String> {
+    let Some((name, target)) = arg.split_once('=') else {
+        return Err(String::from("expected `name=url` or `name=exec:program`"));
+    };
+
+    let handler = match target.strip_prefix(EXEC) {
+        Some(program) => Handler::Exec(program.to_owned()),
+        None => Handler::Webhook(target.to_owned()),
+    };
+
+    Ok((name.to_owned(), handler))
+}
@@ -0,0 +1,89 @@
+use std::fmt::Write;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use color_eyre::eyre::{Result, WrapErr};
+use serde_json::Value;
+
+use super::vault::Vault;
+
+/// State webhook backends keep about each conversation, persisted to a directory.
+pub struct Sessions {
+    dir: PathBuf,
+
+    /// Sequence number of next write, telling apart temporary files of concurrent ones.
+    next: AtomicU64,
+
+    /// Encryption of stored state, if enabled.
+    vault: Arc<Vault>,
+}
+
+impl Sessions {
+    pub fn open(dir: &Path, vault: Arc<Vault>) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .wrap_err_with(|| format!("Failed to create {}", dir.display()))?;
+
+        // Files being written when process stopped are incomplete, and never replaced any state
+        for file in std::fs::read_dir(dir)? {
+            let path = file?.path();
+
+            if path.extension().is_some_and(|e| e == "tmp") {
+                std::fs::remove_file(&path)?;
+            }
+        }
+
+        Ok(Self {
+            dir: dir.to_owned(),
+            next: AtomicU64::new(0),
+            vault,
+        })
+    }
+
+    /// State of conversation, `None` if nothing was stored for it.
+    pub async fn get(&self, conversation: &str) -> io::Result<Option<Value>> {
+        match tokio::fs::read(self.path(conversation)).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&self.vault.open(bytes)?)?)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Replace state of conversation, readers seeing either previous state or new one in full.
+    pub async fn put(&self, conversation: &str, state: &Value) -> io::Result<()> {
+        let path = self.path(conversation);
+
+        let n = self.next.fetch_add(1, Ordering::Relaxed);
+
+        let tmp = path.with_extension(format!("{n}.tmp"));
+
+        let bytes = self.vault.seal(serde_json::to_vec(state)?)?;
+
+        tokio::fs::write(&tmp, bytes).await?;
+
+        tokio::fs::rename(&tmp, &path).await
+    }
+
+    /// Forget state of conversation, returning whether there was any.
+    pub async fn delete(&self, conversation: &str) -> io::Result<bool> {
+        match tokio::fs::remove_file(self.path(conversation)).await {
+            Ok(()) => Ok(true),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// File of conversation, named after hexadecimal bytes of its identifier to be a valid name.
+    fn path(&self, conversation: &str) -> PathBuf {
+        let mut name = String::with_capacity(conversation.len() * 2 + 5);
+
+        for byte in conversation.bytes() {
+            let _ = write!(name, "{byte:02x}");
+        }
+
+        name.push_str(".json");
+
+        self.dir.join(name)
+    }
+}
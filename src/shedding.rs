@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+
+/// Path of readiness probe, answered whatever the load for services not to be restarted under it.
+const PROBE: &str = "/ready";
+
+/// Most requests handled at once, overall and for paths starting with given prefixes.
+pub struct Limits {
+    global: Option<Arc<Semaphore>>,
+
+    /// Limits of routes by path prefix, first matching one applying.
+    routes: Vec<(String, Arc<Semaphore>)>,
+}
+
+impl Limits {
+    pub fn new(global: Option<usize>, routes: &[(String, usize)]) -> Self {
+        Self {
+            global: global.map(|n| Arc::new(Semaphore::new(n))),
+            routes: routes
+                .iter()
+                .map(|(prefix, n)| (prefix.clone(), Arc::new(Semaphore::new(*n))))
+                .collect(),
+        }
+    }
+}
+
+/// Parse limit of route as `/prefix=count`.
+pub fn parse_route_limit(arg: &str) -> Result<(String, usize), String> {
+    let Some((prefix, count)) = arg.split_once('=') else {
+        return Err(String::from("expected `/prefix=count`"));
+    };
+
+    if !prefix.starts_with('/') {
+        return Err(format!(
+            "expected path prefix starting with `/`, got `{prefix}`"
+        ));
+    }
+
+    match count.parse() {
+        Ok(count) if count > 0 => Ok((prefix.to_owned(), count)),
+        _ => Err(format!("expected positive count, got `{count}`")),
+    }
+}
+
+/// Turn requests away with service unavailable status while as many as allowed are handled,
+/// rather than letting them queue up.
+pub async fn shed<E: poem::Endpoint>(
+    next: E,
+    req: poem::Request,
+    limits: Arc<Limits>,
+) -> poem::Result<poem::Response> {
+    use poem::IntoResponse;
+
+    let path = req.uri().path();
+
+    if path == PROBE {
+        return Ok(next.call(req).await?.into_response());
+    }
+
+    let route = limits
+        .routes
+        .iter()
+        .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+        .map(|(_, semaphore)| semaphore);
+
+    // Held until response is ready, released on drop
+    let mut permits = Vec::with_capacity(2);
+
+    for semaphore in limits.global.iter().chain(route) {
+        match Arc::clone(semaphore).try_acquire_owned() {
+            Ok(permit) => permits.push(permit),
+            Err(_) => return Ok(saturated()),
+        }
+    }
+
+    let resp = next.call(req).await?.into_response();
+
+    drop(permits);
+
+    Ok(resp)
+}
+
+/// Response telling client to try again shortly.
+fn saturated() -> poem::Response {
+    use poem::http::StatusCode;
+    use poem::http::header::RETRY_AFTER;
+
+    poem::Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header(RETRY_AFTER, 1)
+        .body("Too many requests being handled, try again shortly")
+}
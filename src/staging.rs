@@ -0,0 +1,252 @@
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Content type of attachments whose bytes are not recognized.
+pub const UNKNOWN: &str = "application/octet-stream";
+
+/// Length of base64 prefix of payloads decoded to detect their type, enough for known formats.
+const SNIFFED: usize = 1024;
+
+/// Preparation of attachments before sending them.
+pub struct Staging {
+    /// Directory shared with daemon to decode attachments to, if any.
+    pub dir: Option<PathBuf>,
+
+    /// Attachments kept decoded in staging directory by content, if any.
+    pub cache: Option<Cache>,
+
+    /// Bounds images are downscaled to fit in, if any.
+    #[cfg(feature = "images")]
+    pub images: Option<super::images::Limits>,
+}
+
+/// Decoded attachments held by a send, removed from staging directory once dropped unless
+/// cache or other sends still hold them.
+#[derive(Default)]
+pub struct Cleanup(Vec<Arc<Decoded>>);
+
+/// Attachment decoded to staging directory, removed once dropped.
+struct Decoded(PathBuf);
+
+/// Attachments decoded last, reused by sends of the same content instead of being decoded again.
+pub struct Cache {
+    /// Number of attachments kept, oldest ones being removed first.
+    capacity: usize,
+
+    /// Hashes of payloads and files they were decoded to, most recently used last.
+    kept: Mutex<VecDeque<(String, Arc<Decoded>)>>,
+}
+
+impl Staging {
+    /// Turn base64 payloads into attachments daemon accepts, without extra copies of them.
+    ///
+    /// Payloads are decoded to files daemon reads by path if staging directory is configured,
+    /// and reused in place as data URIs otherwise. Files last as long as returned guard.
+    ///
+    /// Content type is detected from decoded bytes, unless payloads are data URIs already, as
+    /// `data:application/pdf;base64,...`, which are passed on untouched. Images too large are
+    /// downscaled, if limits are configured.
+    ///
+    /// Files of payloads sent recently are reused as they are, if cache is configured.
+    pub async fn stage(&self, attachments: Vec<String>) -> io::Result<(Vec<String>, Cleanup)> {
+        let mut staged = Cleanup::default();
+
+        let mut staged_attachments = Vec::with_capacity(attachments.len());
+
+        for attachment in attachments {
+            if attachment.starts_with("data:") {
+                staged_attachments.push(attachment);
+                continue;
+            }
+
+            // Hash payload as given, for repeated images not to be downscaled again either
+            let cached = self.cache.as_ref().map(|cache| (cache, hash(&attachment)));
+
+            // Hold file for send to outlast its eviction from cache
+            if let Some(decoded) = cached.as_ref().and_then(|(cache, hash)| cache.get(hash)) {
+                staged_attachments.push(decoded.0.to_string_lossy().into_owned());
+                staged.0.push(decoded);
+                continue;
+            }
+
+            let kind = sniff(&attachment);
+
+            #[cfg(feature = "images")]
+            let (attachment, kind) = self.downscale(attachment, kind).await?;
+
+            let Some(dir) = &self.dir else {
+                let mime_type = kind.map_or(UNKNOWN, |k| k.mime_type());
+
+                let mut uri = attachment;
+
+                uri.insert_str(0, &format!("data:{mime_type};base64,"));
+                staged_attachments.push(uri);
+                continue;
+            };
+
+            // Daemon tells type of files from their extension, when their bytes are not enough
+            let path = match kind {
+                Some(kind) => dir.join(format!("{}.{}", name(), kind.extension())),
+                None => dir.join(name()),
+            };
+
+            // Track file before writing it, to remove it even if decoding fails midway
+            let decoded = Arc::new(Decoded(path.clone()));
+
+            staged.0.push(Arc::clone(&decoded));
+
+            staged_attachments.push(path.to_string_lossy().into_owned());
+
+            tokio::task::spawn_blocking(move || decode(&attachment, &path)).await??;
+
+            // Keep file for later sends too, once fully written
+            if let Some((cache, hash)) = cached {
+                cache.insert(hash, decoded);
+            }
+        }
+
+        Ok((staged_attachments, staged))
+    }
+
+    /// Payload of image downscaled to fit limits as JPEG, and its type, others left as they are.
+    #[cfg(feature = "images")]
+    async fn downscale(
+        &self,
+        payload: String,
+        kind: Option<infer::Type>,
+    ) -> io::Result<(String, Option<infer::Type>)> {
+        use base64::Engine;
+        use base64::engine::general_purpose::STANDARD;
+
+        let supported = kind.is_some_and(|k| super::images::supported(k.mime_type()));
+
+        let Some(limits) = self.images.filter(|_| supported) else {
+            return Ok((payload, kind));
+        };
+
+        // Decoding and encoding images is costly, keep it off executor threads
+        tokio::task::spawn_blocking(move || {
+            let invalid = |error| io::Error::new(io::ErrorKind::InvalidData, error);
+
+            let bytes = STANDARD
+                .decode(&payload)
+                .map_err(|e| invalid(e.to_string()))?;
+
+            match super::images::downscale(&bytes, limits) {
+                Ok(Some(jpeg)) => Ok((STANDARD.encode(&jpeg), infer::get(&jpeg))),
+                Ok(None) => Ok((payload, kind)),
+                Err(error) => Err(invalid(error.to_string())),
+            }
+        })
+        .await?
+    }
+}
+
+impl Cache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            kept: Mutex::default(),
+        }
+    }
+
+    /// File payload of hash was decoded to, marked as used last, if it is still kept.
+    fn get(&self, hash: &str) -> Option<Arc<Decoded>> {
+        let mut kept = self.kept.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let index = kept.iter().position(|(h, _)| h == hash)?;
+
+        let entry = kept.remove(index)?;
+        let decoded = Arc::clone(&entry.1);
+
+        kept.push_back(entry);
+
+        drop(kept);
+
+        Some(decoded)
+    }
+
+    /// Keep file payload of hash was decoded to, letting go of oldest ones beyond capacity,
+    /// unless a concurrent send of the same payload had it kept first.
+    fn insert(&self, hash: String, decoded: Arc<Decoded>) {
+        let mut kept = self.kept.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if kept.iter().any(|(h, _)| *h == hash) {
+            return;
+        }
+
+        kept.push_back((hash, decoded));
+
+        let evicted = kept.len().saturating_sub(self.capacity);
+        let evicted: Vec<_> = kept.drain(..evicted).collect();
+
+        // Files are removed outside of lock, once sends holding them are done
+        drop(kept);
+
+        drop(evicted);
+    }
+}
+
+impl Drop for Decoded {
+    fn drop(&mut self) {
+        match std::fs::remove_file(&self.0) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                tracing::warn!("Failed to remove {}: {error}", self.0.display());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Name of staged file unique across requests, and across processes sharing directory.
+fn name() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    format!("signal-http-{}-{n}", std::process::id())
+}
+
+/// Hash of payload, in hexadecimal.
+fn hash(payload: &str) -> String {
+    use core::fmt::Write;
+
+    use sha2::{Digest, Sha256};
+
+    let digest = Sha256::digest(payload.as_bytes());
+
+    digest.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Type of payload, detected from magic bytes at start of it, if recognized.
+fn sniff(payload: &str) -> Option<infer::Type> {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    // Cut at a whole number of base64 blocks, padding being only ever at the end
+    let end = payload.len().min(SNIFFED) / 4 * 4;
+
+    let prefix = STANDARD.decode(payload.get(..end)?).ok()?;
+
+    infer::get(&prefix)
+}
+
+/// Decode payload to file chunk by chunk, never holding decoded attachment in memory whole.
+fn decode(payload: &str, path: &Path) -> io::Result<()> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::read::DecoderReader;
+
+    let mut decoder = DecoderReader::new(payload.as_bytes(), &STANDARD);
+
+    let mut file = std::fs::File::create(path)?;
+
+    io::copy(&mut decoder, &mut file)?;
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use sd_notify::NotifyState;
+
+/// Tell service manager bridge is ready, a no-op unless run as a `Type=notify` unit.
+pub fn ready() {
+    if let Err(error) = sd_notify::notify(&[NotifyState::Ready]) {
+        tracing::warn!("Failed to notify service manager of readiness: {error}");
+    }
+}
+
+/// Keep telling service manager bridge is responsive, if it asked to with `WatchdogSec=`.
+///
+/// Pings come from the async runtime, so they stop whenever it wedges, getting bridge restarted.
+pub async fn watchdog() {
+    let Some(timeout) = sd_notify::watchdog_enabled() else {
+        return;
+    };
+
+    // Ping twice per period, for a late tick not to be mistaken for a hang
+    let mut interval = tokio::time::interval(timeout / 2);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = sd_notify::notify(&[NotifyState::Watchdog]) {
+            tracing::warn!("Failed to ping service manager watchdog: {error}");
+        }
+    }
+}
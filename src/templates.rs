@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::{Result, WrapErr};
+use handlebars::{Handlebars, RenderError};
+use poem::web::Data;
+use poem_openapi::Object;
+use poem_openapi::param::{Path, Query};
+use poem_openapi::payload::Json;
+use serde_json::Value;
+
+use super::outbox::Outbox;
+use super::uploads::Uploads;
+use super::{
+    Api, Audited, BatchResult, Calling, Recipient, ResultPoem, Send, Sent, Signal, Staged,
+    TextMode, Trusting, send_audited, unprocessable,
+};
+
+/// Messages producers send by name, filling them with their own variables.
+pub struct Templates(Handlebars<'static>);
+
+/// Endpoints sending messages from templates, part of native API.
+pub struct Templating;
+
+/// Message to send, rendered from template with variables, missing ones being an error.
+#[derive(Object)]
+struct SendTemplate {
+    /// Account to act as, when daemon serves several.
+    account: Option<String>,
+    recipient: Recipient,
+    variables: Option<Value>,
+
+    /// Syntax of rendered message, plain text by default.
+    text_mode: Option<TextMode>,
+
+    /// Base64 payloads, or data URIs to set content type rather than have it detected.
+    attachments: Option<Vec<String>>,
+
+    /// Tokens of attachments stored with `POST /attachments`, sent after other attachments.
+    uploads: Option<Vec<String>>,
+
+    /// Send even during quiet hours of recipient.
+    urgent: Option<bool>,
+}
+
+/// Message rendered from template with variables of each recipient, to send to all of them.
+#[derive(Object)]
+struct Broadcast {
+    /// Account to act as, when daemon serves several.
+    account: Option<String>,
+
+    /// Handlebars template of message, missing variables being an error for their recipient.
+    template: String,
+    recipients: Vec<Personalized>,
+
+    /// Syntax of rendered messages, plain text by default.
+    text_mode: Option<TextMode>,
+
+    /// Base64 payloads, or data URIs to set content type rather than have it detected.
+    attachments: Option<Vec<String>>,
+
+    /// Tokens of attachments stored with `POST /attachments`, better than payloads sent to many.
+    uploads: Option<Vec<String>>,
+
+    /// Send even during quiet hours of recipients.
+    urgent: Option<bool>,
+}
+
+/// Recipient of broadcast, with variables to fill message for them with.
+#[derive(Object)]
+struct Personalized {
+    recipient: Recipient,
+    variables: Option<Value>,
+}
+
+#[poem_openapi::OpenApi]
+impl Templating {
+    /// Send message of configured template, filled with given variables.
+    #[oai(path = "/send/template/:name", method = "post")]
+    #[expect(clippy::too_many_arguments)]
+    async fn send_template(
+        &self,
+        Path(name): Path<String>,
+        Json(body): Json<SendTemplate>,
+        queued: Query<Option<bool>>,
+        signal: Signal<'_, '_>,
+        staging: Staged<'_>,
+        outbox: Data<&Option<Arc<Outbox>>>,
+        uploads: Data<&Option<Arc<Uploads>>>,
+        templates: Data<&Arc<Templates>>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+        trusting: Trusting<'_>,
+    ) -> ResultPoem<Sent> {
+        use poem::error::NotFoundError;
+
+        let variables = body.variables.unwrap_or_else(|| serde_json::json!({}));
+
+        let message = match templates.render(&name, &variables).ok_or(NotFoundError)? {
+            Ok(message) => message,
+            Err(error) => return unprocessable(&format!("Failed to render `{name}`: {error}")),
+        };
+
+        let body = Send {
+            account: body.account,
+            recipient: body.recipient,
+            message,
+            text_mode: body.text_mode,
+            attachments: body.attachments,
+            uploads: body.uploads,
+            urgent: body.urgent,
+        };
+
+        let (signal, staging) = (Data(signal.0), Data(staging.0));
+
+        send_audited(
+            body, queued, signal, staging, outbox, uploads, caller, audit, trusting,
+        )
+        .await
+    }
+
+    /// Send message of template to each recipient, filled with their own variables, a few at
+    /// once, reporting outcome for each recipient in order.
+    #[oai(path = "/broadcast", method = "post")]
+    #[expect(clippy::too_many_arguments)]
+    async fn broadcast(
+        &self,
+        Json(body): Json<Broadcast>,
+        queued: Query<Option<bool>>,
+        signal: Signal<'_, '_>,
+        staging: Staged<'_>,
+        outbox: Data<&Option<Arc<Outbox>>>,
+        uploads: Data<&Option<Arc<Uploads>>>,
+        caller: Calling<'_>,
+        audit: Audited<'_>,
+        trusting: Trusting<'_>,
+    ) -> ResultPoem<Json<Vec<BatchResult>>> {
+        use poem::http::StatusCode;
+
+        /// Name template of broadcast is registered under.
+        const NAME: &str = "broadcast";
+
+        let mut registry = Templates::registry();
+
+        if let Err(error) = registry.register_template_string(NAME, &body.template) {
+            return unprocessable(&format!("Invalid template: {error}"));
+        }
+
+        let mut sends = Vec::new();
+        let mut failures = Vec::new();
+
+        // Recipients whose variables do not fit the template fail alone
+        for (i, personalized) in body.recipients.into_iter().enumerate() {
+            let variables = personalized
+                .variables
+                .unwrap_or_else(|| serde_json::json!({}));
+
+            let message = match registry.render(NAME, &variables) {
+                Ok(message) => message,
+                Err(error) => {
+                    let msg = format!("Failed to render template: {error}");
+                    let error = poem::Error::from_string(msg, StatusCode::UNPROCESSABLE_ENTITY);
+
+                    failures.push((i, BatchResult::failed(&error)));
+                    continue;
+                }
+            };
+
+            sends.push(Send {
+                account: body.account.clone(),
+                recipient: personalized.recipient,
+                message,
+                text_mode: body.text_mode,
+                attachments: body.attachments.clone(),
+                uploads: body.uploads.clone(),
+                urgent: body.urgent,
+            });
+        }
+
+        let (signal, staging) = (Data(signal.0), Data(staging.0));
+
+        let Json(mut results) = Api
+            .send_batch(
+                Json(sends),
+                queued,
+                signal,
+                staging,
+                outbox,
+                uploads,
+                caller,
+                audit,
+                trusting,
+            )
+            .await;
+
+        // Put failures back where their recipients were, in increasing order for indices to hold
+        for (i, failure) in failures {
+            results.insert(i, failure);
+        }
+
+        Ok(Json(results))
+    }
+}
+
+impl Templates {
+    /// Compile Handlebars templates given as `(name, template)`, failing on invalid ones.
+    pub fn new(templates: &[(String, String)]) -> Result<Self> {
+        let mut registry = Self::registry();
+
+        for (name, template) in templates {
+            registry
+                .register_template_string(name, template)
+                .wrap_err_with(|| format!("Invalid template `{name}`"))?;
+        }
+
+        Ok(Self(registry))
+    }
+
+    /// Empty registry, rendering templates the way configured ones are.
+    fn registry() -> Handlebars<'static> {
+        let mut registry = Handlebars::new();
+
+        // Messages are plain text, not HTML, and missing variables are mistakes of producers
+        registry.register_escape_fn(handlebars::no_escape);
+        registry.set_strict_mode(true);
+
+        registry
+    }
+
+    /// Message of template filled with variables, `None` if there is no such template.
+    pub fn render(&self, name: &str, variables: &Value) -> Option<Result<String, RenderError>> {
+        self.0
+            .has_template(name)
+            .then(|| self.0.render(name, variables))
+    }
+}
+
+/// Split `name=template` argument into its parts.
+pub fn parse_template(arg: &str) -> Result<(String, String), String> {
+    let Some((name, template)) = arg.split_once('=') else {
+        return Err(String::from("expected `name=template`"));
+    };
+
+    Ok((name.to_owned(), template.to_owned()))
+}
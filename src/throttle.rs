@@ -0,0 +1,133 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::sync::OwnedMutexGuard;
+use tokio::time::Instant;
+
+/// Window over which messages sent are counted against ceiling.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Pacing of sends, one at a time per conversation, and of messages, at most so many per minute
+/// per account.
+pub struct Throttle {
+    /// Messages allowed per minute from each account, unlimited when zero.
+    per_minute: usize,
+
+    /// Lock of each conversation with sends in progress, held for the duration of each send.
+    conversations: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+
+    /// Instants messages of each account were scheduled at, over the last window, in order.
+    scheduled: Mutex<HashMap<Option<String>, VecDeque<Instant>>>,
+}
+
+impl Throttle {
+    pub fn new(per_minute: usize) -> Self {
+        Self {
+            per_minute,
+            conversations: Mutex::new(HashMap::new()),
+            scheduled: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Hold conversation of send described by `params`, once earlier sends to it are done, for
+    /// sends paced while turn is held to go through in a row.
+    pub async fn turn(&self, params: Option<&Value>) -> Turn<'_> {
+        let account = field(params, "account");
+
+        let recipient = field(params, "groupId").or_else(|| field(params, "recipient"));
+
+        let key = format!(
+            "{:?} {}",
+            account.and_then(Value::as_str),
+            recipient.unwrap_or(&Value::Null)
+        );
+
+        let conversation = Arc::clone(
+            self.conversations
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(key.clone())
+                .or_default(),
+        );
+
+        // Locks of Tokio are fair, so sends to a conversation go through in arrival order
+        Turn {
+            throttle: self,
+            key,
+            held: conversation.lock_owned().await,
+        }
+    }
+
+    /// Wait until ceiling of account of message described by `params` allows it.
+    pub async fn pace(&self, params: Option<&Value>) {
+        let account = field(params, "account")
+            .and_then(Value::as_str)
+            .map(str::to_owned);
+
+        tokio::time::sleep_until(self.schedule(account)).await;
+    }
+
+    /// Reserve earliest instant account can send at without exceeding ceiling.
+    fn schedule(&self, account: Option<String>) -> Instant {
+        let now = Instant::now();
+
+        if self.per_minute == 0 {
+            return now;
+        }
+
+        let mut scheduled = self
+            .scheduled
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let instants = scheduled.entry(account).or_default();
+
+        while instants.front().is_some_and(|at| *at + WINDOW <= now) {
+            instants.pop_front();
+        }
+
+        // Wait for message that many messages ago to leave window, after those reserved already
+        let mut at = now.max(instants.back().copied().unwrap_or(now));
+
+        if let Some(index) = instants.len().checked_sub(self.per_minute) {
+            at = at.max(instants[index] + WINDOW);
+        }
+
+        instants.push_back(at);
+
+        drop(scheduled);
+
+        at
+    }
+}
+
+/// Conversation held by a send, released once dropped.
+pub struct Turn<'a> {
+    throttle: &'a Throttle,
+    key: String,
+    held: OwnedMutexGuard<()>,
+}
+
+impl Drop for Turn<'_> {
+    fn drop(&mut self) {
+        let conversation = OwnedMutexGuard::mutex(&self.held);
+
+        // Forget conversation once nothing is waiting on it anymore
+        let mut conversations = self
+            .throttle
+            .conversations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if Arc::strong_count(conversation) == 2 {
+            conversations.remove(&self.key);
+        }
+    }
+}
+
+/// Field of parameters of send, if set.
+fn field<'a>(params: Option<&'a Value>, key: &str) -> Option<&'a Value> {
+    params.and_then(|p| p.get(key)).filter(|v| !v.is_null())
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use color_eyre::eyre::{Result, eyre};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::ClientConfig;
+
+/// Build client configuration, trusting system roots unless a CA bundle is provided.
+///
+/// When `identity` is provided, its certificate chain and private key authenticate the client.
+pub fn config(ca: Option<&Path>, identity: Option<(&Path, &Path)>) -> Result<Arc<ClientConfig>> {
+    use tokio_rustls::rustls::RootCertStore;
+    use tokio_rustls::rustls::crypto::ring;
+    use tokio_rustls::rustls::pki_types::pem::PemObject;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+    let mut roots = RootCertStore::empty();
+
+    if let Some(ca) = ca {
+        for cert in CertificateDer::pem_file_iter(ca)? {
+            roots.add(cert?)?;
+        }
+    } else {
+        let native = rustls_native_certs::load_native_certs();
+
+        for error in native.errors {
+            tracing::warn!("Failed to load system root certificates: {error}");
+        }
+
+        roots.add_parsable_certificates(native.certs);
+    }
+
+    // Select provider explicitly, since several may be compiled in by dependencies
+    let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots);
+
+    let config = match identity {
+        None => builder.with_no_client_auth(),
+        Some((cert, key)) => {
+            let chain = CertificateDer::pem_file_iter(cert)?.collect::<Result<_, _>>()?;
+
+            builder.with_client_auth_cert(chain, PrivateKeyDer::from_pem_file(key)?)?
+        }
+    };
+
+    Ok(Arc::new(config))
+}
+
+/// Open TCP connection to `host:port`, then perform TLS handshake, verifying server as `host`.
+pub async fn connect(config: Arc<ClientConfig>, addr: &str) -> Result<TlsStream<TcpStream>> {
+    use tokio_rustls::rustls::pki_types::ServerName;
+
+    let (host, _) = addr
+        .rsplit_once(':')
+        .ok_or_else(|| eyre!("Missing port in TLS address: {addr}"))?;
+
+    // Bracketed IPv6 literals are not part of the server name
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let name = ServerName::try_from(host.to_owned())?;
+
+    let tcp = TcpStream::connect(addr).await?;
+
+    Ok(TlsConnector::from(config).connect(name, tcp).await?)
+}
@@ -1,3 +1,6 @@
+pub mod http;
+pub mod traffic;
+
 use std::io::Result as ResultIo;
 
 use futures_util::SinkExt;
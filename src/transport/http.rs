@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use serde_json::{Value, json};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio::task::JoinHandle;
+
+use jsonrpsee::core::client::{ReceivedMessage, TransportReceiverT, TransportSenderT};
+
+use super::Error;
+
+/// Method opening subscription to incoming messages, emulated with server-sent events.
+const SUBSCRIBE: &str = "subscribeReceive";
+
+/// Method closing subscription to incoming messages.
+const UNSUBSCRIBE: &str = "unsubscribeReceive";
+
+/// Create transport to `signal-cli` daemon running in HTTP mode, at provided base URL.
+#[must_use]
+pub fn connect(base: &str) -> (Sender, Receiver) {
+    let (tx, rx) = unbounded_channel();
+
+    let sender = Sender {
+        client: reqwest::Client::new(),
+        base: base.trim_end_matches('/').to_string(),
+        tx,
+        events: HashMap::new(),
+        next: 0,
+    };
+
+    (sender, Receiver(rx))
+}
+
+/// Issue each request as a separate HTTP call, queueing responses for receiver.
+pub struct Sender {
+    client: reqwest::Client,
+    base: String,
+    tx: UnboundedSender<String>,
+
+    /// Streams of events of each emulated subscription, by its identifier.
+    events: HashMap<u64, JoinHandle<()>>,
+
+    /// Identifier of next subscription.
+    next: u64,
+}
+
+impl TransportSenderT for Sender {
+    type Error = Error;
+
+    async fn send(&mut self, body: String) -> Result<(), Self::Error> {
+        let request: Value = serde_json::from_str(&body).map_err(Error::from_error)?;
+
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+
+        match request.get("method").and_then(Value::as_str) {
+            Some(SUBSCRIBE) => {
+                // Subscriptions of each account run side by side, ones that ended are dropped
+                self.events.retain(|_, events| !events.is_finished());
+
+                let subscription = self.next;
+                self.next += 1;
+
+                let mut get = self.client.get(format!("{}/api/v1/events", self.base));
+
+                if let Some(account) = request["params"]["account"].as_str() {
+                    get = get.query(&[("account", account)]);
+                }
+
+                let events = listen(get, id, subscription, self.tx.clone());
+                self.events.insert(subscription, tokio::spawn(events));
+
+                Ok(())
+            }
+            Some(UNSUBSCRIBE) => {
+                let subscription = request["params"][0].as_u64();
+
+                if let Some(events) = subscription.and_then(|s| self.events.remove(&s)) {
+                    events.abort();
+                }
+
+                self.respond(&json!({"jsonrpc": "2.0", "id": id, "result": true}))
+            }
+            _ => {
+                // Do not hold up other requests while waiting for response
+                let post = self.client.post(format!("{}/api/v1/rpc", self.base));
+                let tx = self.tx.clone();
+
+                tokio::spawn(async move {
+                    let content_type = "application/json";
+                    let req = post
+                        .header(reqwest::header::CONTENT_TYPE, content_type)
+                        .body(body);
+
+                    let resp = match req
+                        .send()
+                        .await
+                        .and_then(reqwest::Response::error_for_status)
+                    {
+                        Ok(resp) => resp.text().await,
+                        Err(error) => Err(error),
+                    };
+
+                    // Surface transport failures as errors of the request itself
+                    let text = resp.unwrap_or_else(|error| {
+                        let error = json!({"code": -32603, "message": error.to_string()});
+                        json!({"jsonrpc": "2.0", "id": id, "error": error}).to_string()
+                    });
+
+                    // Notifications get an empty response
+                    if !text.trim().is_empty() {
+                        let _ = tx.send(text);
+                    }
+                });
+
+                Ok(())
+            }
+        }
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        for (_, events) in self.events.drain() {
+            events.abort();
+        }
+
+        Ok(())
+    }
+}
+
+impl Sender {
+    fn respond(&self, msg: &Value) -> Result<(), Error> {
+        self.tx.send(msg.to_string()).map_err(Error::from_error)
+    }
+}
+
+/// Yield responses and notifications in the order they were produced.
+pub struct Receiver(UnboundedReceiver<String>);
+
+impl TransportReceiverT for Receiver {
+    type Error = Error;
+
+    async fn receive(&mut self) -> Result<ReceivedMessage, Self::Error> {
+        let Some(msg) = self.0.recv().await else {
+            return Err(Error(String::from("Closed")));
+        };
+
+        Ok(ReceivedMessage::Text(msg))
+    }
+}
+
+/// Answer subscription request `id` once stream of events opens, or with error if it cannot,
+/// then convert events into notifications of `subscription`, closing it when stream ends for
+/// subscriber to subscribe again.
+async fn listen(
+    get: reqwest::RequestBuilder,
+    id: Value,
+    subscription: u64,
+    tx: UnboundedSender<String>,
+) {
+    let resp = match get
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+    {
+        Ok(resp) => resp,
+        Err(error) => {
+            let error = json!({"code": -32603, "message": error.to_string()});
+            let _ = tx.send(json!({"jsonrpc": "2.0", "id": id, "error": error}).to_string());
+            return;
+        }
+    };
+
+    let result = json!({"jsonrpc": "2.0", "id": id, "result": subscription});
+
+    if tx.send(result.to_string()).is_err() {
+        return;
+    }
+
+    let error = match stream_events(resp, subscription, &tx).await {
+        Ok(()) => String::from("Stream of events ended"),
+        Err(error) => error.to_string(),
+    };
+
+    let params = json!({"subscription": subscription, "error": error});
+    let _ = tx.send(json!({"jsonrpc": "2.0", "method": "receive", "params": params}).to_string());
+}
+
+async fn stream_events(
+    mut resp: reqwest::Response,
+    subscription: u64,
+    tx: &UnboundedSender<String>,
+) -> Result<(), reqwest::Error> {
+    let mut pending = Vec::new();
+    let mut data = String::new();
+
+    while let Some(chunk) = resp.chunk().await? {
+        pending.extend_from_slice(&chunk);
+
+        // Process complete lines only, events may be split across chunks
+        while let Some(i) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<_> = pending.drain(..=i).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+
+            if let Some(value) = line.strip_prefix("data:") {
+                // Multi-line payloads are split across several fields
+                if !data.is_empty() {
+                    data.push('\n');
+                }
+
+                data.push_str(value.strip_prefix(' ').unwrap_or(value));
+            } else if line.is_empty() && !data.is_empty() {
+                // Blank line terminates event
+                let Ok(result) = serde_json::from_str::<Value>(&data) else {
+                    tracing::warn!("Discarding malformed event of {} bytes", data.len());
+                    data.clear();
+                    continue;
+                };
+
+                data.clear();
+
+                let params = json!({"subscription": subscription, "result": result});
+                let notification = json!({"jsonrpc": "2.0", "method": "receive", "params": params});
+
+                if tx.send(notification.to_string()).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use jsonrpsee::core::client::{ReceivedMessage, TransportReceiverT, TransportSenderT};
+use serde_json::Value;
+
+/// Keys holding message bodies or attachment payloads, hidden when redacting.
+const BODIES: [&str; 4] = ["message", "attachments", "data", "text"];
+
+/// Placeholder standing in for redacted values.
+const REDACTED: &str = "[redacted]";
+
+/// Name of file traffic is recorded to, within recording directory.
+pub const RECORDING: &str = "traffic.jsonl";
+
+/// How to log JSON-RPC traffic with daemon, for troubleshooting interop issues.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Traffic {
+    /// Message bodies and phone numbers are replaced with a placeholder.
+    Redacted,
+
+    /// Messages are logged as exchanged, personal data included.
+    Verbatim,
+}
+
+/// Messages exchanged with daemon, appended to a file as they go, personal data included.
+///
+/// Each line holds a message under `sent` or `received`, with milliseconds since Unix epoch
+/// under `at`, for the mock daemon to replay later.
+pub struct Recorder(Mutex<File>);
+
+impl Recorder {
+    /// Start recording to directory, after messages recorded there before.
+    ///
+    /// # Errors
+    ///
+    /// Fails if recording file cannot be opened.
+    pub fn create(dir: &Path) -> std::io::Result<Arc<Self>> {
+        std::fs::create_dir_all(dir)?;
+
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(dir.join(RECORDING))?;
+
+        Ok(Arc::new(Self(Mutex::new(file))))
+    }
+
+    fn record(&self, direction: &str, msg: &str) {
+        use std::io::Write;
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        // Keep messages that are not JSON as strings, rather than losing them
+        let msg = serde_json::from_str(msg).unwrap_or_else(|_| Value::from(msg));
+
+        let line = format!("{}\n", serde_json::json!({ "at": at, direction: msg }));
+
+        let written = self
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write_all(line.as_bytes());
+
+        if let Err(error) = written {
+            tracing::warn!("Failed to record daemon traffic: {error}");
+        }
+    }
+}
+
+/// Log messages going through transport halves at debug level, and record them, if requested.
+pub fn wrap<S, R>(
+    sender: S,
+    receiver: R,
+    traffic: Option<Traffic>,
+    recorder: Option<&Arc<Recorder>>,
+) -> (Sender<S>, Receiver<R>) {
+    (
+        Sender {
+            inner: sender,
+            traffic,
+            recorder: recorder.cloned(),
+        },
+        Receiver {
+            inner: receiver,
+            traffic,
+            recorder: recorder.cloned(),
+        },
+    )
+}
+
+pub struct Sender<T> {
+    inner: T,
+    traffic: Option<Traffic>,
+    recorder: Option<Arc<Recorder>>,
+}
+
+impl<T: TransportSenderT + Send> TransportSenderT for Sender<T> {
+    type Error = T::Error;
+
+    async fn send(&mut self, body: String) -> Result<(), Self::Error> {
+        log(self.traffic, "-->", &body);
+
+        if let Some(recorder) = &self.recorder {
+            recorder.record("sent", &body);
+        }
+
+        self.inner.send(body).await
+    }
+
+    async fn send_ping(&mut self) -> Result<(), Self::Error> {
+        self.inner.send_ping().await
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        self.inner.close().await
+    }
+}
+
+pub struct Receiver<T> {
+    inner: T,
+    traffic: Option<Traffic>,
+    recorder: Option<Arc<Recorder>>,
+}
+
+impl<T: TransportReceiverT + Send> TransportReceiverT for Receiver<T> {
+    type Error = T::Error;
+
+    async fn receive(&mut self) -> Result<ReceivedMessage, Self::Error> {
+        let msg = self.inner.receive().await?;
+
+        if let ReceivedMessage::Text(text) = &msg {
+            log(self.traffic, "<--", text);
+
+            if let Some(recorder) = &self.recorder {
+                recorder.record("received", text);
+            }
+        }
+
+        Ok(msg)
+    }
+}
+
+fn log(traffic: Option<Traffic>, direction: &str, msg: &str) {
+    match traffic {
+        None => {}
+        Some(Traffic::Verbatim) => tracing::debug!("{direction} {msg}"),
+        Some(Traffic::Redacted) => {
+            let Ok(mut value) = serde_json::from_str::<Value>(msg) else {
+                return tracing::debug!("{direction} {REDACTED}");
+            };
+
+            redact(&mut value, true);
+            tracing::debug!("{direction} {value}");
+        }
+    }
+}
+
+/// Render logged value, redacted as requested.
+#[must_use]
+pub fn show(traffic: Traffic, mut value: Value) -> Value {
+    if matches!(traffic, Traffic::Redacted) {
+        redact(&mut value, true);
+    }
+
+    value
+}
+
+/// Replace phone numbers, and message bodies too if `bodies` is set.
+fn redact(value: &mut Value, bodies: bool) {
+    match value {
+        Value::String(s) if is_phone_number(s) => *s = String::from(REDACTED),
+        Value::Array(values) => values.iter_mut().for_each(|v| redact(v, bodies)),
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if bodies && BODIES.contains(&key.as_str()) && !value.is_null() {
+                    *value = Value::from(REDACTED);
+                } else {
+                    // Keep error descriptions readable, they are what troubleshooting is about
+                    redact(value, bodies && key != "error");
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_phone_number(s: &str) -> bool {
+    s.strip_prefix('+')
+        .is_some_and(|digits| digits.len() >= 6 && digits.bytes().all(|b| b.is_ascii_digit()))
+}
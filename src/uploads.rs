@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use super::outbox::now;
+
+/// Attachments uploaded once, for later sends to refer to by token until they expire.
+pub struct Uploads {
+    /// Time uploads are kept for, from when they are stored.
+    ttl: Duration,
+
+    /// Attachments as sends take them, with milliseconds since Unix epoch they expire at, by token.
+    stored: Mutex<HashMap<String, (String, u64)>>,
+}
+
+impl Uploads {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            stored: Mutex::default(),
+        }
+    }
+
+    /// Keep attachment, returning token standing for it and when it expires, dropping expired ones.
+    pub fn store(&self, attachment: String) -> (String, u64) {
+        let now = now();
+
+        let expires = now.saturating_add(u64::try_from(self.ttl.as_millis()).unwrap_or(u64::MAX));
+
+        let token = super::verify::token();
+
+        let mut stored = self.stored.lock().unwrap_or_else(PoisonError::into_inner);
+
+        stored.retain(|_, (_, expires)| *expires > now);
+        stored.insert(token.clone(), (attachment, expires));
+
+        drop(stored);
+
+        (token, expires)
+    }
+
+    /// Attachment token stands for, unless it is unknown or expired.
+    pub fn get(&self, token: &str) -> Option<String> {
+        let now = now();
+
+        let stored = self.stored.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let attachment = stored
+            .get(token)
+            .filter(|(_, expires)| *expires > now)
+            .cloned();
+
+        drop(stored);
+
+        attachment.map(|(attachment, _)| attachment)
+    }
+}
@@ -0,0 +1,103 @@
+use std::io;
+
+#[cfg(feature = "encryption")]
+use aes_gcm::Aes256Gcm;
+
+/// Length in bytes of nonce prefixing each sealed payload.
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 12;
+
+/// Protection of payloads written to disk, encrypting them with AES-256-GCM when given a key.
+///
+/// Queued messages, spilled events and conversation state go through it, written as they are
+/// when no key is set.
+#[derive(Default)]
+pub struct Vault {
+    #[cfg(feature = "encryption")]
+    cipher: Option<Aes256Gcm>,
+}
+
+impl Vault {
+    /// Vault encrypting payloads with key given as base64 of 32 bytes.
+    ///
+    /// # Errors
+    ///
+    /// Fails if key is not valid base64, or not 32 bytes long.
+    #[cfg(feature = "encryption")]
+    pub fn new(secret: &str) -> color_eyre::eyre::Result<Self> {
+        use aes_gcm::KeyInit;
+        use base64::Engine;
+        use base64::engine::general_purpose::STANDARD;
+        use color_eyre::eyre::bail;
+
+        let Ok(key) = STANDARD.decode(secret.trim()) else {
+            bail!("Storage secret is not valid base64");
+        };
+
+        let Ok(cipher) = Aes256Gcm::new_from_slice(&key) else {
+            bail!("Storage secret is {} bytes long instead of 32", key.len());
+        };
+
+        Ok(Self {
+            cipher: Some(cipher),
+        })
+    }
+
+    /// Payload to write to disk, prefixed with its random nonce when encrypted.
+    #[cfg_attr(
+        not(feature = "encryption"),
+        expect(
+            clippy::missing_const_for_fn,
+            clippy::unnecessary_wraps,
+            clippy::unused_self
+        )
+    )]
+    pub fn seal(&self, payload: Vec<u8>) -> io::Result<Vec<u8>> {
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = &self.cipher {
+            use aes_gcm::aead::{Aead, AeadCore, OsRng};
+
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+            let sealed = cipher
+                .encrypt(&nonce, payload.as_slice())
+                .map_err(|_| io::Error::other("Failed to encrypt payload"))?;
+
+            return Ok([nonce.as_slice(), &sealed].concat());
+        }
+
+        Ok(payload)
+    }
+
+    /// Payload read back from disk, failing on those tampered with or sealed with another key.
+    #[cfg_attr(
+        not(feature = "encryption"),
+        expect(
+            clippy::missing_const_for_fn,
+            clippy::unnecessary_wraps,
+            clippy::unused_self
+        )
+    )]
+    pub fn open(&self, sealed: Vec<u8>) -> io::Result<Vec<u8>> {
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = &self.cipher {
+            use aes_gcm::Nonce;
+            use aes_gcm::aead::Aead;
+
+            let invalid =
+                || io::Error::new(io::ErrorKind::InvalidData, "Failed to decrypt payload");
+
+            if sealed.len() < NONCE_LEN {
+                return Err(invalid());
+            }
+
+            let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+            return cipher
+                .decrypt(Nonce::from_slice(nonce), ciphertext)
+                .map_err(|_| invalid());
+        }
+
+        Ok(sealed)
+    }
+}
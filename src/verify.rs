@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use color_eyre::eyre::{Result, bail};
+use serde_json::{Value, json};
+
+/// Upper bound on time for webhooks to answer challenge.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Post `{"challenge": token}` to webhook, requiring it to echo token back, as plain text or
+/// in a `challenge` field of JSON, to prove it is meant to receive messages.
+pub async fn verify(client: &reqwest::Client, url: &str) -> Result<()> {
+    let token = token();
+
+    let resp = client
+        .post(url)
+        .timeout(TIMEOUT)
+        .json(&json!({ "challenge": token }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let body = resp.text().await?;
+
+    let echoed = match serde_json::from_str::<Value>(&body) {
+        Ok(Value::Object(object)) => {
+            object.get("challenge").and_then(Value::as_str) == Some(&token)
+        }
+        _ => body.trim() == token,
+    };
+
+    if !echoed {
+        bail!("{url} did not echo challenge");
+    }
+
+    Ok(())
+}
+
+/// Verify each of `(id, url)` webhooks, failing on first one that does not echo challenge.
+pub async fn verify_all(client: &reqwest::Client, webhooks: &[(String, String)]) -> Result<()> {
+    use color_eyre::eyre::WrapErr;
+
+    for (id, url) in webhooks {
+        verify(client, url)
+            .await
+            .wrap_err_with(|| format!("Webhook `{id}` failed verification"))?;
+    }
+
+    Ok(())
+}
+
+/// Unpredictable token, from randomly keyed hashers of standard library.
+pub fn token() -> String {
+    use std::hash::{BuildHasher, RandomState};
+
+    let state = RandomState::new();
+
+    format!("{:016x}{:016x}", state.hash_one(0), state.hash_one(1))
+}
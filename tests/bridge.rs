@@ -0,0 +1,889 @@
+//! End-to-end tests of HTTP endpoints and forwarding, against a scripted daemon.
+
+#![cfg(feature = "native")]
+
+mod support;
+
+use serde_json::json;
+
+use self::support::{FakeDaemon, Webhook, bridge};
+
+/// Identifier of a group, 32 bytes encoded in base64.
+const GROUP: &str = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+
+#[tokio::test]
+async fn send_reaches_daemon() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &[]).await;
+
+    let resp = client
+        .post("/send")
+        .body_json(&json!({
+            "recipient": { "kind": "person", "value": "+15550001" },
+            "message": "hello",
+        }))
+        .send()
+        .await;
+
+    resp.assert_status_is_ok();
+
+    let request = daemon.request("send").await;
+
+    assert_eq!(request["params"]["recipient"], "+15550001");
+    assert_eq!(request["params"]["message"], "hello");
+}
+
+#[tokio::test]
+async fn form_and_plain_text_sends_reach_daemon() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &[]).await;
+
+    // Unencoded `+` decodes to a space, as sent by `curl -d`
+    client
+        .post("/send")
+        .content_type("application/x-www-form-urlencoded")
+        .body("recipient=+15550001&message=from+form")
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    let request = daemon.request("send").await;
+
+    assert_eq!(request["params"]["recipient"], "+15550001");
+    assert_eq!(request["params"]["message"], "from form");
+
+    client
+        .post("/send")
+        .query("recipient", &"+15550002")
+        .content_type("text/plain")
+        .body("from text")
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    let request = daemon.request("send").await;
+
+    assert_eq!(request["params"]["recipient"], "+15550002");
+    assert_eq!(request["params"]["message"], "from text");
+}
+
+#[tokio::test]
+async fn group_sends_are_addressed_by_identifier() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &[]).await;
+
+    let resp = client
+        .post("/send")
+        .body_json(&json!({
+            "recipient": { "kind": "group", "value": GROUP },
+            "message": "hello",
+        }))
+        .send()
+        .await;
+
+    resp.assert_status_is_ok();
+
+    let request = daemon.request("send").await;
+
+    assert_eq!(request["params"]["groupId"], GROUP);
+    assert!(request["params"]["recipient"].is_null());
+}
+
+#[tokio::test]
+async fn prefixed_group_identifiers_are_decoded_and_checked() {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    let mut daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &[]).await;
+
+    for id in ["Z3JvdXA=", &STANDARD.encode("Z3JvdXA=")] {
+        client
+            .post("/send")
+            .body_json(&json!({ "recipient": format!("group.{id}"), "message": "hello" }))
+            .send()
+            .await
+            .assert_status(poem::http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // Identifiers are taken as signal-cli gives them, or encoded once more as upstream API does
+    for id in [GROUP.to_owned(), STANDARD.encode(GROUP)] {
+        client
+            .post("/send")
+            .body_json(&json!({ "recipient": format!("group.{id}"), "message": "hello" }))
+            .send()
+            .await
+            .assert_status_is_ok();
+
+        assert_eq!(daemon.request("send").await["params"]["groupId"], GROUP);
+    }
+}
+
+#[cfg(feature = "compat")]
+#[tokio::test]
+async fn compat_group_sends_are_addressed_by_identifier() {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    let mut daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &[]).await;
+
+    // Upstream API encodes identifiers of groups once more
+    let recipient = format!("group.{}", STANDARD.encode(GROUP));
+
+    client
+        .post("/v2/send")
+        .body_json(&json!({
+            "number": "+15550000",
+            "recipients": [recipient],
+            "message": "hello",
+        }))
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    let request = daemon.request("send").await;
+
+    assert_eq!(request["params"]["groupId"], GROUP);
+    assert!(request["params"]["recipient"].is_null());
+}
+
+#[cfg(feature = "compat")]
+#[tokio::test]
+async fn compat_attachments_listed_are_downloaded_from_directory() {
+    use poem::http::StatusCode;
+
+    let daemon = FakeDaemon::start().await;
+
+    let dir = std::env::temp_dir().join(format!("signal-http-attachments-{}", std::process::id()));
+
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.jpg"), b"image").unwrap();
+
+    let args = ["--attachments", dir.to_str().unwrap()];
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &args).await;
+
+    let resp = client.get("/v1/attachments").send().await;
+
+    resp.assert_status_is_ok();
+    resp.assert_json(json!(["a.jpg"])).await;
+
+    let resp = client.get("/v1/attachments/a.jpg").send().await;
+
+    resp.assert_status_is_ok();
+    resp.assert_bytes(b"image".to_vec()).await;
+
+    let missing = client.get("/v1/attachments/b.jpg").send().await;
+    let hidden = client.get("/v1/attachments/.hidden").send().await;
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    missing.assert_status(StatusCode::NOT_FOUND);
+    hidden.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[cfg(feature = "compat")]
+#[tokio::test]
+async fn compat_registration_takes_bodies_left_out() {
+    use poem::http::StatusCode;
+
+    let mut daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &[]).await;
+
+    client
+        .post("/v1/register/+15550000")
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    assert_eq!(daemon.request("register").await["params"]["voice"], false);
+
+    client
+        .post("/v1/register/+15550000")
+        .body_json(&json!({ "use_voice": true }))
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    assert_eq!(daemon.request("register").await["params"]["voice"], true);
+
+    client
+        .post("/v1/register/+15550000/verify/123456")
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let request = daemon.request("verify").await;
+
+    assert_eq!(request["params"]["verificationCode"], "123456");
+}
+
+#[cfg(feature = "compat")]
+#[tokio::test]
+async fn compat_link_codes_of_unknown_versions_are_refused() {
+    use poem::http::StatusCode;
+
+    let daemon = FakeDaemon::start().await;
+
+    let link = json!({ "deviceLinkUri": "sgnl://linkdevice?uuid=fake" });
+    daemon.script("startLink", json!({ "result": link }));
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &[]).await;
+
+    for version in [0, 41] {
+        let resp = client
+            .get("/v1/qrcodelink")
+            .query("device_name", &"laptop")
+            .query("qrcode_version", &version)
+            .send()
+            .await;
+
+        resp.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        resp.assert_text("QR code version must be between 1 and 40")
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn rate_limited_sends_reply_too_many_requests() {
+    let daemon = FakeDaemon::start().await;
+
+    daemon.script(
+        "send",
+        json!({ "error": { "code": -5, "message": "Rate limit", "data": { "retryAfterSeconds": 7 } } }),
+    );
+
+    let client = bridge(
+        &daemon,
+        "http://127.0.0.1:9/",
+        &["--rate-limit-budget", "0"],
+    )
+    .await;
+
+    let resp = client
+        .post("/send")
+        .body_json(&json!({
+            "recipient": { "kind": "person", "value": "+15550001" },
+            "message": "hello",
+        }))
+        .send()
+        .await;
+
+    resp.assert_status(poem::http::StatusCode::TOO_MANY_REQUESTS);
+    resp.assert_header("retry-after", "7");
+}
+
+#[tokio::test]
+async fn typing_indicators_are_not_paced_as_messages() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &["--send-rate", "1"]).await;
+
+    let recipient = json!({ "kind": "person", "value": "+15550001" });
+
+    client
+        .post("/send")
+        .body_json(&json!({ "recipient": recipient, "message": "hello" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    // Ceiling is reached, a second message would wait a minute
+    let typing = client
+        .post("/typing")
+        .body_json(&json!({ "recipient": recipient, "stop": false }))
+        .send();
+
+    tokio::time::timeout(std::time::Duration::from_secs(5), typing)
+        .await
+        .expect("typing indicator waited for ceiling of messages")
+        .assert_status_is_ok();
+
+    let request = daemon.request("sendTyping").await;
+
+    assert_eq!(request["params"]["recipient"], "+15550001");
+}
+
+#[tokio::test]
+async fn split_sends_tell_parts_delivered_before_failure() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let delivered = json!({ "result": { "timestamp": 1, "results": [] } });
+    let failed = json!({ "error": { "code": -1, "message": "Network failure" } });
+
+    daemon.script("send", json!([delivered, failed]));
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &["--split-messages", "20"]).await;
+
+    let resp = client
+        .post("/send")
+        .body_json(&json!({
+            "recipient": { "kind": "person", "value": "+15550001" },
+            "message": "first words of a message, then some more",
+        }))
+        .send()
+        .await;
+
+    resp.assert_status(poem::http::StatusCode::INTERNAL_SERVER_ERROR);
+    resp.assert_text("Delivered 1 of 3 parts of message, then failed: Network failure")
+        .await;
+
+    // Third part is never sent
+    for part in ["(1/3)", "(2/3)"] {
+        let request = daemon.request("send").await;
+
+        assert!(
+            request["params"]["message"]
+                .as_str()
+                .unwrap()
+                .starts_with(part)
+        );
+    }
+}
+
+#[tokio::test]
+async fn invalid_group_identifiers_are_rejected_before_daemon() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &[]).await;
+
+    let resp = client
+        .post("/send")
+        .body_json(&json!({
+            "recipient": { "kind": "group", "value": "Z3JvdXA=" },
+            "message": "hello",
+        }))
+        .send()
+        .await;
+
+    resp.assert_status(poem::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // First send reaching daemon is the valid one that follows
+    client
+        .post("/send")
+        .body_json(&json!({
+            "recipient": { "kind": "person", "value": "+15550001" },
+            "message": "valid",
+        }))
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    assert_eq!(daemon.request("send").await["params"]["message"], "valid");
+}
+
+#[tokio::test]
+async fn private_errors_hash_numbers_in_any_form() {
+    let daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &["--privacy"]).await;
+
+    for number in ["030 1234567", "0049 1234567 1234567 89"] {
+        let resp = client
+            .post("/send")
+            .body_json(&json!({
+                "recipient": { "kind": "person", "value": number },
+                "message": "hello",
+            }))
+            .send()
+            .await;
+
+        resp.assert_status(poem::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body = resp.0.into_body().into_string().await.unwrap();
+
+        assert!(body.contains("number#"), "{body}");
+        assert!(!body.contains("1234567"), "{body}");
+    }
+}
+
+#[tokio::test]
+async fn incoming_messages_reach_webhook() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let mut webhook = Webhook::start().await;
+
+    let _client = bridge(&daemon, &webhook.url, &[]).await;
+
+    let event = json!({
+        "account": "+15550000",
+        "envelope": {
+            "sourceNumber": "+15550001",
+            "timestamp": 1,
+            "dataMessage": { "message": "hi", "timestamp": 1 },
+        },
+    });
+
+    daemon.notify(1, event).await;
+
+    let payload = webhook.receive().await;
+
+    assert_eq!(payload["envelope"]["sourceNumber"], "+15550001");
+    assert_eq!(payload["envelope"]["dataMessage"]["message"], "hi");
+}
+
+#[tokio::test]
+async fn http_daemon_streams_events_of_each_account() {
+    let mut daemon = FakeDaemon::start_http().await;
+
+    let mut webhook = Webhook::start().await;
+
+    let accounts = ["--account", "+15550000", "--account", "+15559999"];
+
+    let _client = bridge(&daemon, &webhook.url, &accounts).await;
+
+    let mut opened = Vec::new();
+
+    for _ in 0..2 {
+        opened.push(daemon.request("events").await["params"]["account"].clone());
+    }
+
+    opened.sort_by_key(ToString::to_string);
+
+    assert_eq!(opened, ["+15550000", "+15559999"]);
+
+    let event = |account, message| {
+        json!({
+            "account": account,
+            "envelope": {
+                "sourceNumber": "+15550001",
+                "timestamp": 1,
+                "dataMessage": { "message": message, "timestamp": 1 },
+            },
+        })
+    };
+
+    daemon.notify(2, event("+15550000", "first")).await;
+
+    assert_eq!(webhook.receive().await["account"], "+15550000");
+
+    // Stream of first account ended, and is opened again
+    daemon.notify(3, event("+15559999", "second")).await;
+
+    assert_eq!(webhook.receive().await["account"], "+15559999");
+
+    daemon.notify(4, event("+15550000", "third")).await;
+
+    let payload = webhook.receive().await;
+
+    assert_eq!(payload["envelope"]["dataMessage"]["message"], "third");
+}
+
+#[tokio::test]
+async fn account_webhooks_suffice_without_default_one() {
+    use clap::Parser;
+    use signal_http::Args;
+
+    let mut daemon = FakeDaemon::start().await;
+
+    let mut webhook = Webhook::start().await;
+
+    let routed = format!("+15550000={}", webhook.url);
+
+    let args = |extra: &[&str]| {
+        let required = [
+            "signal-http",
+            "--daemon",
+            &daemon.addr,
+            "--account",
+            "+15550000",
+            "--account-webhook",
+            &routed,
+        ];
+
+        Args::try_parse_from(required.iter().chain(extra)).unwrap()
+    };
+
+    // Messages of accounts without webhook would have nowhere to go
+    let unrouted = args(&["--account", "+15559999"]);
+
+    assert!(signal_http::app(unrouted, Vec::new()).await.is_err());
+
+    let _app = signal_http::app(args(&[]), Vec::new()).await.unwrap();
+
+    let event = json!({
+        "account": "+15550000",
+        "envelope": {
+            "sourceNumber": "+15550001",
+            "timestamp": 1,
+            "dataMessage": { "message": "hi", "timestamp": 1 },
+        },
+    });
+
+    daemon.notify(1, event).await;
+
+    assert_eq!(webhook.receive().await["account"], "+15550000");
+}
+
+#[tokio::test]
+async fn unmodeled_fields_and_events_reach_webhook() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let mut webhook = Webhook::start().await;
+
+    let _client = bridge(&daemon, &webhook.url, &[]).await;
+
+    let attachment = json!({ "id": "a.jpg", "size": 1, "uploadTimestamp": 7 });
+
+    let event = json!({
+        "account": "+15550000",
+        "envelope": {
+            "sourceNumber": "+15550001",
+            "timestamp": 1,
+            "dataMessage": { "message": "hi", "attachments": [attachment] },
+        },
+    });
+
+    daemon.notify(1, event).await;
+
+    let payload = webhook.receive().await;
+
+    assert_eq!(
+        payload["envelope"]["dataMessage"]["attachments"][0],
+        attachment
+    );
+
+    // Timestamp of another type than modeled one
+    let event = json!({
+        "account": "+15550000",
+        "envelope": { "sourceNumber": "+15550001", "timestamp": "soon" },
+    });
+
+    daemon.notify(1, event.clone()).await;
+
+    assert_eq!(webhook.receive().await, event);
+}
+
+#[tokio::test]
+async fn calls_with_negative_identifiers_reach_webhook() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let mut webhook = Webhook::start().await;
+
+    let _client = bridge(&daemon, &webhook.url, &[]).await;
+
+    let event = json!({
+        "account": "+15550000",
+        "envelope": {
+            "sourceNumber": "+15550001",
+            "timestamp": 1,
+            "callMessage": {
+                "offerMessage": { "id": -4_611_686_018_427_387_904_i64, "type": "AUDIO_CALL" },
+            },
+        },
+    });
+
+    daemon.notify(1, event).await;
+
+    let payload = webhook.receive().await;
+
+    assert_eq!(payload["type"], "call");
+    assert_eq!(payload["call"]["id"], -4_611_686_018_427_387_904_i64);
+    assert_eq!(payload["call"]["action"], "offer");
+}
+
+#[tokio::test]
+async fn readiness_follows_daemon_connection() {
+    let daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &[]).await;
+
+    client
+        .get("/ready")
+        .send()
+        .await
+        .assert_status(poem::http::StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn routes_are_served_under_base_path() {
+    use poem::http::StatusCode;
+
+    let daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &["--base-path", "/signal/"]).await;
+
+    client
+        .get("/signal/ready")
+        .send()
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    client
+        .get("/ready")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[cfg(feature = "compat")]
+#[tokio::test]
+async fn untrusted_identities_link_to_trust_endpoint_exposed() {
+    let daemon = FakeDaemon::start().await;
+
+    daemon.script(
+        "send",
+        json!({ "result": {
+            "timestamp": 1,
+            "results": [{ "recipientAddress": { "number": "+15550001" }, "type": "IDENTITY_FAILURE" }],
+        } }),
+    );
+
+    daemon.script(
+        "listIdentities",
+        json!({ "result": [{ "safetyNumber": "12345" }] }),
+    );
+
+    let body = json!({
+        "account": "+15550000",
+        "recipient": { "kind": "person", "value": "+15550001" },
+        "message": "hello",
+    });
+
+    let exposed = bridge(&daemon, "http://127.0.0.1:9/", &["--base-path", "/signal/"]).await;
+
+    let resp = exposed.post("/signal/send").body_json(&body).send().await;
+
+    resp.assert_status(poem::http::StatusCode::CONFLICT);
+
+    let identity: serde_json::Value = resp.json().await.value().deserialize();
+
+    assert_eq!(
+        identity["trust"],
+        "/signal/v1/identities/+15550000/trust/+15550001"
+    );
+
+    let hidden = bridge(&daemon, "http://127.0.0.1:9/", &["--compat", "false"]).await;
+
+    let resp = hidden.post("/send").body_json(&body).send().await;
+
+    resp.assert_status(poem::http::StatusCode::CONFLICT);
+
+    let identity: serde_json::Value = resp.json().await.value().deserialize();
+
+    assert!(identity["trust"].is_null());
+}
+
+#[tokio::test]
+async fn administration_needs_unrestricted_token() {
+    use poem::http::StatusCode;
+
+    let daemon = FakeDaemon::start().await;
+
+    let tokens = [
+        "--api-token",
+        "ops=full",
+        "--api-token",
+        "shop=limited",
+        "--api-recipient",
+        "shop=+15550001",
+    ];
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &tokens).await;
+
+    let config = |token| {
+        client
+            .get("/admin/config")
+            .header("authorization", format!("Bearer {token}"))
+            .send()
+    };
+
+    config("limited").await.assert_status(StatusCode::FORBIDDEN);
+    config("full").await.assert_status_is_ok();
+
+    client
+        .post("/admin/webhooks")
+        .header("authorization", "Bearer limited")
+        .body_json(&json!({ "url": "http://127.0.0.1:9/stolen" }))
+        .send()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn administration_is_closed_without_tokens() {
+    let daemon = FakeDaemon::start().await;
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &[]).await;
+
+    client
+        .get("/admin/config")
+        .send()
+        .await
+        .assert_status(poem::http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn administration_is_reserved_to_admin_token_when_set() {
+    use poem::http::StatusCode;
+
+    let daemon = FakeDaemon::start().await;
+
+    let tokens = ["--api-token", "ops=full", "--admin-token", "root"];
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &tokens).await;
+
+    let config = |token| {
+        client
+            .get("/admin/config")
+            .header("authorization", format!("Bearer {token}"))
+            .send()
+    };
+
+    config("full").await.assert_status(StatusCode::FORBIDDEN);
+    config("root").await.assert_status_is_ok();
+}
+
+#[tokio::test]
+async fn reload_reads_configuration_file_given() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let (started, mut reloaded) = (Webhook::start().await, Webhook::start().await);
+
+    let path = std::env::temp_dir().join(format!("signal-http-{}.toml", std::process::id()));
+
+    std::fs::write(&path, format!("webhook = \"{}\"\n", reloaded.url)).unwrap();
+
+    let config = path.to_str().unwrap();
+
+    let args = ["--config", config, "--admin-token", "root"];
+
+    let client = bridge(&daemon, &started.url, &args).await;
+
+    client
+        .post("/admin/reload")
+        .header("authorization", "Bearer root")
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    std::fs::remove_file(&path).unwrap();
+
+    let event = json!({
+        "account": "+15550000",
+        "envelope": { "sourceNumber": "+15550001", "timestamp": 1 },
+    });
+
+    daemon.notify(1, event).await;
+
+    assert_eq!(reloaded.receive().await["account"], "+15550000");
+}
+
+#[tokio::test]
+async fn queued_sends_record_delivery_to_audit_log() {
+    let mut daemon = FakeDaemon::start().await;
+
+    let dir = std::env::temp_dir().join(format!("signal-http-outbox-{}", std::process::id()));
+    let log = dir.with_extension("log");
+
+    let args = [
+        "--outbox",
+        dir.to_str().unwrap(),
+        "--audit-log",
+        log.to_str().unwrap(),
+        "--api-token",
+        "app=secret",
+        "--admin-token",
+        "root",
+    ];
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &args).await;
+
+    client
+        .post("/send")
+        .query("queued", &true)
+        .header("authorization", "Bearer secret")
+        .body_json(&json!({
+            "recipient": { "kind": "person", "value": "+15550001" },
+            "message": "hello",
+        }))
+        .send()
+        .await
+        .assert_status(poem::http::StatusCode::ACCEPTED);
+
+    daemon.request("send").await;
+
+    // Outcome is recorded once daemon answered
+    let mut records = Vec::new();
+
+    for _ in 0..50 {
+        let resp = client
+            .get("/admin/audit")
+            .header("authorization", "Bearer root")
+            .send()
+            .await;
+
+        records = resp
+            .json()
+            .await
+            .value()
+            .deserialize::<Vec<serde_json::Value>>();
+
+        if records.len() == 2 {
+            break;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_file(&log);
+
+    let statuses: Vec<_> = records.iter().map(|r| r["status"].clone()).collect();
+
+    assert_eq!(statuses, [202, 200]);
+    assert_eq!(records[1]["caller"], "app");
+    assert_eq!(records[1]["recipient"], "+15550001");
+}
+
+#[tokio::test]
+async fn restricted_tokens_only_reach_endpoints_checking_recipients() {
+    use poem::http::StatusCode;
+
+    let daemon = FakeDaemon::start().await;
+
+    let tokens = [
+        "--api-token",
+        "shop=limited",
+        "--api-recipient",
+        "shop=+15550001",
+    ];
+
+    let client = bridge(&daemon, "http://127.0.0.1:9/", &tokens).await;
+
+    client
+        .put("/configuration")
+        .header("authorization", "Bearer limited")
+        .body_json(&json!({ "read_receipts": false }))
+        .send()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    client
+        .get("/v1/groups/+15550000")
+        .header("authorization", "Bearer limited")
+        .send()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    let send = |number: &str| {
+        client
+            .post("/send")
+            .header("authorization", "Bearer limited")
+            .body_json(&json!({
+                "recipient": { "kind": "person", "value": number },
+                "message": "hello",
+            }))
+            .send()
+    };
+
+    send("+15550002").await.assert_status(StatusCode::FORBIDDEN);
+    send("+15550001").await.assert_status_is_ok();
+}
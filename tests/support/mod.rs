@@ -0,0 +1,365 @@
+//! Scripted stand-ins for `signal-cli` daemon and webhooks, for tests to run bridge against.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use poem::endpoint::BoxEndpoint;
+use poem::test::TestClient;
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc, watch};
+
+/// Upper bound on time for expected requests and deliveries to happen.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `signal-cli` daemon speaking JSON-RPC over TCP, with canned replies and injectable events.
+pub struct FakeDaemon {
+    /// Address bridge connects to, as given to `--daemon`.
+    pub addr: String,
+
+    /// Scripted replies to methods, by name, as `{"result": ...}` or `{"error": ...}`.
+    replies: Arc<Mutex<HashMap<String, Value>>>,
+
+    /// Requests received so far, other than subscriptions, with streams of events opened in HTTP
+    /// mode as `events` ones.
+    requests: mpsc::UnboundedReceiver<Value>,
+
+    /// Incoming events, forwarded to every subscription.
+    events: broadcast::Sender<Value>,
+
+    /// Number of subscriptions opened so far.
+    subscriptions: watch::Receiver<u64>,
+}
+
+impl FakeDaemon {
+    /// Listen on a free local port, serving connections in the background.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+        let (daemon, server) = Self::new(listener.local_addr().unwrap().to_string());
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(server.clone().serve(stream));
+            }
+        });
+
+        daemon
+    }
+
+    /// Listen in HTTP mode on a free local port, streaming a single event on each stream of
+    /// events opened, for bridge to open another one after it.
+    pub async fn start_http() -> Self {
+        use poem::listener::{Acceptor, Listener};
+        use poem::{EndpointExt, Route, get, post};
+
+        let acceptor = poem::listener::TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+
+        let addr = acceptor.local_addr()[0].as_socket_addr().copied().unwrap();
+
+        let (daemon, server) = Self::new(format!("http://{addr}"));
+
+        let app = Route::new()
+            .at("/api/v1/rpc", post(answer_rpc))
+            .at("/api/v1/events", get(stream_events))
+            .data(server);
+
+        tokio::spawn(poem::Server::new_with_acceptor(acceptor).run(app));
+
+        daemon
+    }
+
+    fn new(addr: String) -> (Self, Server) {
+        let replies = Arc::new(Mutex::new(HashMap::new()));
+
+        let (requests, received) = mpsc::unbounded_channel();
+
+        let (events, _) = broadcast::channel(16);
+
+        let (opened, subscriptions) = watch::channel(0);
+
+        let server = Server {
+            replies: Arc::clone(&replies),
+            requests,
+            events: events.clone(),
+            opened: Arc::new(opened),
+        };
+
+        let daemon = Self {
+            addr,
+            replies,
+            requests: received,
+            events,
+            subscriptions,
+        };
+
+        (daemon, server)
+    }
+
+    /// Reply to every later call of `method` with `reply`, `{"result": ...}` or `{"error": ...}`,
+    /// or with each of an array of them in turn, last one repeating.
+    pub fn script(&self, method: &str, reply: Value) {
+        let mut replies = self.replies.lock().unwrap_or_else(PoisonError::into_inner);
+
+        replies.insert(method.to_owned(), reply);
+    }
+
+    /// Deliver incoming event to subscriptions, once bridge has opened `count` of them.
+    pub async fn notify(&mut self, count: u64, event: Value) {
+        let opened = self.subscriptions.wait_for(|n| *n >= count);
+
+        tokio::time::timeout(TIMEOUT, opened)
+            .await
+            .unwrap()
+            .unwrap();
+
+        self.events.send(event).unwrap();
+    }
+
+    /// Next request for `method`, skipping requests for other ones.
+    pub async fn request(&mut self, method: &str) -> Value {
+        loop {
+            let request = tokio::time::timeout(TIMEOUT, self.requests.recv())
+                .await
+                .unwrap()
+                .unwrap();
+
+            if request["method"] == method {
+                return request;
+            }
+        }
+    }
+}
+
+/// State shared by connections to fake daemon.
+#[derive(Clone)]
+struct Server {
+    replies: Arc<Mutex<HashMap<String, Value>>>,
+    requests: mpsc::UnboundedSender<Value>,
+    events: broadcast::Sender<Value>,
+    opened: Arc<watch::Sender<u64>>,
+}
+
+impl Server {
+    /// Answer newline-delimited requests, and notify subscriptions opened on connection.
+    async fn serve(self, stream: tokio::net::TcpStream) {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (reader, mut writer) = stream.into_split();
+
+        let mut lines = BufReader::new(reader).lines();
+
+        let mut events = self.events.subscribe();
+
+        let mut subscriptions = Vec::new();
+
+        loop {
+            let msg = tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) => self.answer(&line, &mut subscriptions),
+                    Ok(None) | Err(_) => return,
+                },
+                event = events.recv() => match event {
+                    Ok(event) => notifications(&subscriptions, &event),
+                    Err(_) => return,
+                },
+            };
+
+            if writer.write_all(msg.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn answer(&self, line: &str, subscriptions: &mut Vec<u64>) -> String {
+        let request: Value = serde_json::from_str(line).unwrap();
+
+        let method = request["method"].as_str().unwrap_or_default();
+
+        let reply = if method == "subscribeReceive" {
+            let mut id = 0;
+
+            self.opened.send_modify(|n| {
+                *n += 1;
+                id = *n;
+            });
+
+            subscriptions.push(id);
+
+            json!({ "result": id })
+        } else {
+            let scripted = match self
+                .replies
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get_mut(method)
+            {
+                Some(Value::Array(replies)) if replies.len() > 1 => Some(replies.remove(0)),
+                Some(Value::Array(replies)) => replies.first().cloned(),
+                reply => reply.cloned(),
+            };
+
+            let _ = self.requests.send(request.clone());
+
+            scripted.unwrap_or_else(|| json!({ "result": default(method, &request["params"]) }))
+        };
+
+        let mut resp = json!({ "jsonrpc": "2.0", "id": request["id"] });
+
+        resp.as_object_mut()
+            .unwrap()
+            .extend(reply.as_object().unwrap().clone());
+
+        format!("{resp}\n")
+    }
+}
+
+/// Answer request posted to daemon in HTTP mode.
+#[expect(clippy::needless_pass_by_value)]
+#[poem::handler]
+fn answer_rpc(body: String, poem::web::Data(server): poem::web::Data<&Server>) -> String {
+    server.answer(&body, &mut Vec::new())
+}
+
+/// Stream next incoming event of account, if any, as server-sent event, then end stream.
+#[poem::handler]
+fn stream_events(
+    poem::web::Query(query): poem::web::Query<HashMap<String, String>>,
+    poem::web::Data(server): poem::web::Data<&Server>,
+) -> poem::Body {
+    let account = query.get("account").cloned();
+
+    let _ = server
+        .requests
+        .send(json!({ "method": "events", "params": query }));
+
+    let events = server.events.subscribe();
+
+    server.opened.send_modify(|n| *n += 1);
+
+    let stream = futures_util::stream::unfold(Some(events), move |events| {
+        let account = account.clone();
+
+        async move {
+            let mut events = events?;
+
+            loop {
+                let event = events.recv().await.ok()?;
+
+                if account.is_none() || event["account"].as_str() == account.as_deref() {
+                    return Some((Ok::<_, std::io::Error>(format!("data: {event}\n\n")), None));
+                }
+            }
+        }
+    });
+
+    poem::Body::from_bytes_stream(stream)
+}
+
+/// Notify each subscription of incoming event, as newline-terminated JSON.
+fn notifications(subscriptions: &[u64], event: &Value) -> String {
+    use core::fmt::Write;
+
+    subscriptions.iter().fold(String::new(), |mut msg, s| {
+        let params = json!({ "subscription": s, "result": event });
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "receive", "params": params });
+
+        let _ = writeln!(msg, "{notification}");
+
+        msg
+    })
+}
+
+/// Result of unscripted method, successful for every recipient.
+fn default(method: &str, params: &Value) -> Value {
+    match method {
+        "version" => json!({ "version": "fake" }),
+        "send" => {
+            let recipients = match &params["recipient"] {
+                Value::Array(recipients) => recipients.clone(),
+                Value::Null => Vec::new(),
+                recipient => vec![recipient.clone()],
+            };
+
+            let results: Vec<_> = recipients
+                .iter()
+                .map(|r| json!({ "recipientAddress": { "number": r }, "type": "SUCCESS" }))
+                .collect();
+
+            json!({ "timestamp": 1, "results": results })
+        }
+        _ => json!({}),
+    }
+}
+
+/// Endpoint collecting payloads posted to it.
+pub struct Webhook {
+    pub url: String,
+    received: mpsc::UnboundedReceiver<Value>,
+}
+
+impl Webhook {
+    pub async fn start() -> Self {
+        use poem::listener::{Acceptor, Listener};
+        use poem::web::{Data, Json};
+        use poem::{EndpointExt, Route, Server, handler, post};
+
+        #[handler]
+        fn collect(Json(payload): Json<Value>, Data(sender): Data<&mpsc::UnboundedSender<Value>>) {
+            let _ = sender.send(payload);
+        }
+
+        let acceptor = poem::listener::TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+
+        let addr = acceptor.local_addr()[0].as_socket_addr().copied().unwrap();
+
+        let (sender, received) = mpsc::unbounded_channel();
+
+        let app = Route::new().at("/", post(collect)).data(sender);
+
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(app));
+
+        Self {
+            url: format!("http://{addr}/"),
+            received,
+        }
+    }
+
+    /// Next payload posted to webhook.
+    pub async fn receive(&mut self) -> Value {
+        tokio::time::timeout(TIMEOUT, self.received.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+}
+
+/// Start bridge connected to daemon and forwarding to webhook, with extra options.
+pub async fn bridge(
+    daemon: &FakeDaemon,
+    webhook: &str,
+    extra: &[&str],
+) -> TestClient<BoxEndpoint<'static>> {
+    use clap::Parser;
+    use signal_http::Args;
+
+    let required = [
+        "signal-http",
+        "--daemon",
+        &daemon.addr,
+        "--webhook",
+        webhook,
+    ];
+
+    let args = Args::try_parse_from(required.iter().chain(extra)).unwrap();
+
+    TestClient::new(signal_http::app(args, Vec::new()).await.unwrap())
+}